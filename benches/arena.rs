@@ -0,0 +1,41 @@
+use bumpalo::Bump;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use fallible_iterator::FallibleIterator;
+use sqlite3_parser::lexer::sql::{parse_arena_expr, Parser};
+
+/// `(a0 + 0) * 2 = b0 OR c0 LIKE 'x%' OR f(d0, 'y') IS NULL OR ...` with `n` terms
+fn expression(n: usize) -> String {
+    let mut sql = String::new();
+    for i in 0..n {
+        if i > 0 {
+            sql.push_str(" OR ");
+        }
+        sql.push_str(&format!(
+            "(a{i} + {i}) * 2 = b{i} OR c{i} LIKE 'x%' OR f(d{i}, 'y') IS NULL"
+        ));
+    }
+    sql
+}
+
+fn allocation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("allocation");
+    for n in [10, 1_000] {
+        let expr = expression(n);
+        let select = format!("SELECT {expr}");
+        group.throughput(Throughput::Bytes(expr.len() as u64));
+        group.bench_function(format!("box {n} terms"), |b| {
+            b.iter(|| black_box(Parser::new(select.as_bytes()).next().unwrap().unwrap()))
+        });
+        group.bench_function(format!("arena {n} terms"), |b| {
+            let mut bump = Bump::new();
+            b.iter(|| {
+                black_box(parse_arena_expr(&bump, &expr).unwrap());
+                bump.reset();
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, allocation);
+criterion_main!(benches);
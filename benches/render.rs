@@ -0,0 +1,39 @@
+use std::io::{self, Write};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use fallible_iterator::FallibleIterator;
+use sqlite3_parser::lexer::sql::Parser;
+
+/// `SELECT * FROM t WHERE id IN (0, 1, ...)` with `n` elements
+fn in_list(n: usize) -> String {
+    let mut sql = String::from("SELECT * FROM t WHERE id IN (");
+    for i in 0..n {
+        if i > 0 {
+            sql.push_str(", ");
+        }
+        sql.push_str(&i.to_string());
+    }
+    sql.push_str(");");
+    sql
+}
+
+fn render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+    let sql = in_list(100_000);
+    let cmd = Parser::new(sql.as_bytes()).next().unwrap().unwrap();
+    group.throughput(Throughput::Bytes(sql.len() as u64));
+    group.bench_function("in list 100000 to writer", |b| {
+        let mut out = io::BufWriter::new(Vec::new());
+        b.iter(|| {
+            write!(out, "{}", black_box(&cmd)).unwrap();
+            out.get_mut().clear();
+        })
+    });
+    group.bench_function("in list 100000 to string", |b| {
+        b.iter(|| black_box(&cmd).to_string())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, render);
+criterion_main!(benches);
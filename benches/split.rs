@@ -0,0 +1,54 @@
+use std::io::{self, Read};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sqlite3_parser::lexer::sql::Tokenizer;
+use sqlite3_parser::lexer::{InputStream, Scanner};
+
+/// Reader returning at most 4 KiB per `read` (like a pipe)
+struct Chunked<'a>(&'a [u8]);
+
+impl Read for Chunked<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.0.len()).min(4096);
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+/// `SELECT '...'` with a string literal of `n` bytes
+fn string_literal(n: usize) -> String {
+    let mut sql = String::with_capacity(n + 10);
+    sql.push_str("SELECT '");
+    sql.extend(std::iter::repeat('x').take(n));
+    sql.push_str("';");
+    sql
+}
+
+fn split(c: &mut Criterion) {
+    let mut group = c.benchmark_group("split");
+    group.sample_size(10);
+    for mb in [1, 2, 4] {
+        let sql = string_literal(mb << 20);
+        group.throughput(Throughput::Bytes(sql.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("string literal", format!("{mb} MB")),
+            &sql,
+            |b, sql| {
+                b.iter(|| {
+                    let input = InputStream::new(Chunked(sql.as_bytes()));
+                    let mut s = Scanner::new(input, Tokenizer::new());
+                    let mut n = 0;
+                    while s.scan().unwrap().is_some() {
+                        n += 1;
+                    }
+                    assert_eq!(n, 3);
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, split);
+criterion_main!(benches);
@@ -0,0 +1,35 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use fallible_iterator::FallibleIterator;
+use sqlite3_parser::lexer::sql::Parser;
+
+/// `INSERT INTO t VALUES (...), (...), ...` with `rows` rows
+fn multi_row_insert(rows: usize) -> String {
+    let mut sql = String::from("INSERT INTO t (id, name, score, data, flag) VALUES ");
+    for i in 0..rows {
+        if i > 0 {
+            sql.push_str(", ");
+        }
+        sql.push_str(&format!("({}, 'name {}', {}.5, X'0102', NULL)", i, i, i));
+    }
+    sql
+}
+
+fn values(c: &mut Criterion) {
+    let mut group = c.benchmark_group("values");
+    group.sample_size(20);
+    for rows in [1_000, 50_000] {
+        let sql = multi_row_insert(rows);
+        group.throughput(Throughput::Bytes(sql.len() as u64));
+        group.bench_function(format!("insert {} rows", rows), |b| {
+            b.iter_batched(
+                || Parser::new(sql.as_bytes()),
+                |mut parser| black_box(parser.next().unwrap().unwrap()),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, values);
+criterion_main!(benches);
@@ -1,6 +1,9 @@
 //! SQLite dialect
 
-use std::str;
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use core::fmt;
+use core::str;
 use uncased::UncasedStr;
 
 mod token;
@@ -14,21 +17,42 @@ impl TokenType {
     // => Syntax error on keyword will be better
     // => `from_token` will become unnecessary
     pub fn to_token(self, value: &[u8]) -> Token {
-        match self {
-            TokenType::TK_CTIME_KW => Some(from_bytes(value)),
-            TokenType::TK_JOIN_KW => Some(from_bytes(value)),
-            TokenType::TK_LIKE_KW => Some(from_bytes(value)),
-            TokenType::TK_PTR => Some(from_bytes(value)),
-            // Identifiers
-            TokenType::TK_STRING => Some(from_bytes(value)),
-            TokenType::TK_ID => Some(from_bytes(value)),
-            TokenType::TK_VARIABLE => Some(from_bytes(value)),
-            // Values
-            TokenType::TK_ANY => Some(from_bytes(value)),
-            TokenType::TK_BLOB => Some(from_bytes(value)),
-            TokenType::TK_INTEGER => Some(from_bytes(value)),
-            TokenType::TK_FLOAT => Some(from_bytes(value)),
-            _ => None,
+        if self.has_value() {
+            Some(from_bytes(value))
+        } else {
+            None
+        }
+    }
+
+    /// Whether the parser is given the text of the token (see `to_token`)
+    pub(crate) fn has_value(self) -> bool {
+        matches!(
+            self,
+            TokenType::TK_CTIME_KW
+                | TokenType::TK_JOIN_KW
+                | TokenType::TK_LIKE_KW
+                | TokenType::TK_PTR
+                // Identifiers
+                | TokenType::TK_STRING
+                | TokenType::TK_ID
+                | TokenType::TK_VARIABLE
+                // Values
+                | TokenType::TK_ANY
+                | TokenType::TK_BLOB
+                | TokenType::TK_INTEGER
+                | TokenType::TK_FLOAT
+        )
+    }
+}
+
+impl TokenType {
+    /// Token type from its code as used by the parser (`YYCODETYPE`)
+    pub(crate) fn from_code(code: u16) -> Option<TokenType> {
+        if code <= TokenType::TK_FILTER as u16 {
+            // TokenType discriminants are contiguous (see token.rs)
+            Some(unsafe { core::mem::transmute::<u16, TokenType>(code) })
+        } else {
+            None
         }
     }
 }
@@ -46,6 +70,16 @@ pub fn keyword_token(word: &[u8]) -> Option<TokenType> {
         .cloned()
 }
 
+/// All keywords recognized by the lexer (sorted, uppercase)
+pub fn keywords() -> &'static [&'static str] {
+    &KEYWORD_NAMES
+}
+
+/// Check if `word` is a keyword (case-insensitive)
+pub fn is_keyword(word: &str) -> bool {
+    KEYWORDS.contains_key(UncasedStr::new(word))
+}
+
 pub fn is_identifier(name: &str) -> bool {
     if name.is_empty() {
         return false;
@@ -377,4 +411,56 @@ impl TokenType {
             _ => None,
         }
     }
+
+    /// Canonical spelling for keywords / operators / punctuations,
+    /// short description for other tokens (identifiers, literals, ...)
+    pub const fn name(&self) -> &'static str {
+        use TokenType::*;
+        if let Some(str) = self.as_str() {
+            return str;
+        }
+        match self {
+            TK_EOF => "end of input",
+            TK_ID => "identifier",
+            TK_STRING => "string",
+            TK_INTEGER => "integer",
+            TK_FLOAT => "float",
+            TK_BLOB => "blob",
+            TK_VARIABLE => "variable",
+            TK_CTIME_KW => "CURRENT_TIMESTAMP",
+            TK_JOIN_KW => "JOIN",
+            TK_LIKE_KW => "LIKE",
+            TK_PTR => "->",
+            _ => "?",
+        }
+    }
+
+    /// Representative source text of a token of this type, usable by fuzzers
+    /// to generate token sequences: canonical spelling for keywords / operators /
+    /// punctuations, one spelling for token classes (like `LEFT` for `JOIN_KW`).
+    /// `None` for identifiers, literals and variables (their content must be
+    /// generated) and for tokens not produced by the lexer.
+    pub const fn sample_lexeme(&self) -> Option<&'static str> {
+        use TokenType::*;
+        match self {
+            TK_ANY => None,
+            TK_CTIME_KW => Some("CURRENT_TIMESTAMP"),
+            TK_JOIN_KW => Some("LEFT"),
+            TK_LIKE_KW => Some("GLOB"),
+            TK_PTR => Some("->"),
+            _ => self.as_str(),
+        }
+    }
+
+    /// Source text of a token of this type:
+    /// its `value` (see [`TokenType::to_token`]) if any, else its canonical spelling.
+    pub fn text<'a>(&self, value: Option<&'a str>) -> &'a str {
+        value.unwrap_or(self.name())
+    }
+}
+
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
 }
@@ -5,4 +5,6 @@ pub mod sql;
 
 #[cfg(feature = "buf_redux")]
 pub use scan::InputStream;
-pub use scan::{Input, ScanError, Scanner, Splitter};
+#[cfg(feature = "std")]
+pub use scan::{Input, PushInput, Scanned, Scanner, Span};
+pub use scan::{ScanError, Splitter};
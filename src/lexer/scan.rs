@@ -1,9 +1,14 @@
 //! Adaptation/port of [Go scanner](http://tip.golang.org/pkg/bufio/#Scanner).
 
+#[cfg(feature = "std")]
 use log::debug;
 
+use core::fmt;
+#[cfg(feature = "std")]
+use core::ops::Range;
+#[cfg(feature = "std")]
 use std::error::Error;
-use std::fmt;
+#[cfg(feature = "std")]
 use std::io;
 
 #[cfg(feature = "buf_redux")]
@@ -11,6 +16,7 @@ use buf_redux::Buffer;
 #[cfg(feature = "buf_redux")]
 const MAX_CAPACITY: usize = 1024 * 1024 * 1024;
 
+#[cfg(feature = "std")]
 pub trait Input: fmt::Debug {
     fn fill_buf(&mut self) -> io::Result<()>; // -> io::Result<&[u8]>;
     fn eof(&self) -> bool; //&mut self -> io::Result<bool>
@@ -21,6 +27,7 @@ pub trait Input: fmt::Debug {
 }
 
 /// Memory input
+#[cfg(feature = "std")]
 impl Input for &[u8] {
     #[inline]
     fn fill_buf(&mut self) -> io::Result<()> {
@@ -53,6 +60,78 @@ impl Input for &[u8] {
     }
 }
 
+/// Input fed incrementally by the caller (like a REPL reading SQL line by line).
+///
+/// While [`PushInput::finish`] has not been called, scanning a token which may
+/// continue in the next chunk fails with an [`io::ErrorKind::WouldBlock`] error:
+/// the partial token is kept, and scanning can be resumed after pushing more input.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct PushInput {
+    buf: Vec<u8>,
+    /// Number of bytes of `buf` already consumed
+    pos: usize,
+    eof: bool,
+}
+
+#[cfg(feature = "std")]
+impl PushInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a chunk of input
+    pub fn push_str(&mut self, chunk: &str) {
+        debug_assert!(!self.eof, "push after finish");
+        if self.pos > 0 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+        self.buf.extend_from_slice(chunk.as_bytes());
+    }
+
+    /// Mark the end of input
+    pub fn finish(&mut self) {
+        self.eof = true;
+    }
+}
+
+#[cfg(feature = "std")]
+impl Input for PushInput {
+    fn fill_buf(&mut self) -> io::Result<()> {
+        if self.eof {
+            Ok(())
+        } else {
+            Err(io::Error::from(io::ErrorKind::WouldBlock))
+        }
+    }
+
+    #[inline]
+    fn eof(&self) -> bool {
+        self.eof
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+
+    #[inline]
+    fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.pos == self.buf.len()
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+}
+
 /// Streaming input
 #[cfg(feature = "buf_redux")]
 pub struct InputStream<R> {
@@ -138,8 +217,13 @@ impl<R> fmt::Debug for InputStream<R> {
     }
 }
 
+#[cfg(feature = "std")]
 pub trait ScanError: Error + From<io::Error> + Sized {
-    fn position(&mut self, line: u64, column: usize);
+    fn position(&mut self, line: u64, column: usize, offset: usize);
+}
+#[cfg(not(feature = "std"))]
+pub trait ScanError: fmt::Debug + fmt::Display + Sized {
+    fn position(&mut self, line: u64, column: usize, offset: usize);
 }
 
 /// The `(&[u8], TokenType)` is the token.
@@ -163,11 +247,19 @@ pub trait Splitter: Sized {
     /// The function is never called with an empty data slice unless at EOF.
     /// If `eof` is true, however, data may be non-empty and,
     /// as always, holds unprocessed text.
+    ///
+    /// When more data is requested (`Ok((None, 0))`), the next call is given
+    /// the same data, possibly extended, so the function may resume its scan
+    /// where it stopped instead of examining the whole data again.
     fn split<'input>(
         &mut self,
         data: &'input [u8],
         eof: bool,
     ) -> SplitResult<'input, Self::TokenType, Self::Error>;
+
+    /// Forget the state kept from previous calls to `split`
+    /// (the input has been changed).
+    fn reset(&mut self) {}
 }
 
 /// Like a `BufReader` but with a growable buffer.
@@ -177,6 +269,7 @@ pub trait Splitter: Sized {
 /// Scanning stops unrecoverably at EOF, the first I/O error, or a token too
 /// large to fit in the buffer. When a scan stops, the reader may have
 /// advanced arbitrarily far past the last token.
+#[cfg(feature = "std")]
 pub struct Scanner<I: Input, S: Splitter> {
     /// The reader provided by the client.
     input: I,
@@ -186,8 +279,17 @@ pub struct Scanner<I: Input, S: Splitter> {
     line: u64,
     /// current column number (byte offset, not char offset)
     column: usize,
+    /// current byte offset from the start of the input
+    offset: usize,
+    /// `\r\n` is a single line terminator
+    crlf: bool,
+    /// `\r` not yet counted as a column (`crlf` mode)
+    cr: bool,
+    /// byte offset of the whitespace / comments preceding the next token
+    trivia: usize,
 }
 
+#[cfg(feature = "std")]
 impl<I: Input, S: Splitter> Scanner<I, S> {
     pub fn new(input: I, splitter: S) -> Scanner<I, S> {
         Scanner {
@@ -195,6 +297,10 @@ impl<I: Input, S: Splitter> Scanner<I, S> {
             splitter,
             line: 1,
             column: 1,
+            offset: 0,
+            crlf: false,
+            cr: false,
+            trivia: 0,
         }
     }
 
@@ -208,26 +314,108 @@ impl<I: Input, S: Splitter> Scanner<I, S> {
         self.column
     }
 
+    /// Current byte offset from the start of the input
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Byte offset just after the last token
+    pub(crate) fn token_end(&self) -> usize {
+        self.trivia
+    }
+
+    /// Treat `\r\n` as a single line terminator: the `\r` is not counted as
+    /// a column. By default, only `\n` is a line terminator.
+    pub fn crlf(&mut self, crlf: bool) {
+        self.crlf = crlf;
+    }
+
     pub fn splitter(&self) -> &S {
         &self.splitter
     }
 
+    pub fn splitter_mut(&mut self) -> &mut S {
+        &mut self.splitter
+    }
+
+    pub fn input_mut(&mut self) -> &mut I {
+        &mut self.input
+    }
+
     /// Reset the scanner such that it behaves as if it had never been used.
     pub fn reset(&mut self, input: I) {
         self.input = input;
+        self.splitter.reset();
         self.line = 1;
         self.column = 1;
+        self.offset = 0;
+        self.cr = false;
+        self.trivia = 0;
     }
 }
 
+#[cfg(feature = "std")]
 type ScanResult<'input, TokenType, Error> = Result<Option<(&'input [u8], TokenType)>, Error>;
+#[cfg(feature = "std")]
+type SpannedScanResult<'input, TokenType, Error> =
+    Result<Option<(&'input [u8], TokenType, Span)>, Error>;
+
+/// Byte ranges of a token and of the whitespace / comments preceding it
+/// (see [`Scanner::scan_spanned`])
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    /// Whitespace and comments skipped since the previous token
+    pub trivia: Range<usize>,
+    /// Token, including its delimiters (like `X'...'` for a blob)
+    pub token: Range<usize>,
+}
 
+/// Token or lexical error (see [`Scanner::scan_recovering`])
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum Scanned<'input, TokenType, Error> {
+    Token(&'input [u8], TokenType),
+    /// Bytes skipped because of a lexical error
+    Error(&'input [u8], Error),
+}
+
+#[cfg(feature = "std")]
+type RecoveringScanResult<'input, TokenType, Error> =
+    Result<Option<(Scanned<'input, TokenType, Error>, Span)>, Error>;
+
+#[cfg(feature = "std")]
 impl<I: Input, S: Splitter> Scanner<I, S> {
     /// Advance the Scanner to next token.
     /// Return the token as a byte slice.
     /// Return `None` when the end of the input is reached.
     /// Return any error that occurs while reading the input.
     pub fn scan(&mut self) -> ScanResult<'_, S::TokenType, S::Error> {
+        let tok = self.scan_spanned()?;
+        Ok(tok.map(|(token, token_type, _)| (token, token_type)))
+    }
+
+    /// Like [`Scanner::scan`] but also return the byte ranges of the token
+    /// and of the whitespace / comments preceding it.
+    /// Trailing whitespace and comments (not followed by any token) are not reported.
+    pub fn scan_spanned(&mut self) -> SpannedScanResult<'_, S::TokenType, S::Error> {
+        match self.next(false)? {
+            Some((Scanned::Token(token, token_type), span)) => Ok(Some((token, token_type, span))),
+            Some((Scanned::Error(_, e), _)) => Err(e),
+            None => Ok(None),
+        }
+    }
+
+    /// Like [`Scanner::scan_spanned`] but a lexical error (like an unterminated
+    /// literal) does not stop the scan: the error is returned with the first
+    /// character where it occurred, and scanning resumes at the next character.
+    /// Useful to keep highlighting the rest of a script.
+    /// Only I/O errors are returned as `Err`.
+    pub fn scan_recovering(&mut self) -> RecoveringScanResult<'_, S::TokenType, S::Error> {
+        self.next(true)
+    }
+
+    fn next(&mut self, recover: bool) -> RecoveringScanResult<'_, S::TokenType, S::Error> {
         use std::mem;
         debug!(target: "scanner", "scan(line: {}, column: {})", self.line, self.column);
         // Loop until we have a token.
@@ -236,25 +424,42 @@ impl<I: Input, S: Splitter> Scanner<I, S> {
             // See if we can get a token with what we already have.
             if !self.input.is_empty() || eof {
                 // TODO: I don't know how to make the borrow checker happy!
-                let data = unsafe { mem::transmute(self.input.buffer()) };
-                match self.splitter.split(data, eof) {
+                let data: &[u8] = unsafe { mem::transmute(self.input.buffer()) };
+                let (scanned, amt) = match self.splitter.split(data, eof) {
                     Err(mut e) => {
-                        e.position(self.line, self.column);
-                        return Err(e);
+                        e.position(self.line, self.column, self.offset);
+                        if !recover || data.is_empty() {
+                            return Err(e);
+                        }
+                        let amt = utf8_len(data[0]).min(data.len());
+                        (Scanned::Error(&data[..amt], e), amt)
                     }
                     Ok((None, 0)) => {
                         // Request more data
+                        if eof {
+                            // Shut it down.
+                            return Ok(None);
+                        }
+                        self.input.fill_buf()?;
+                        continue;
                     }
                     Ok((None, amt)) => {
                         // Ignore/skip this data
                         self.consume(amt);
                         continue;
                     }
-                    Ok((tok, amt)) => {
-                        self.consume(amt);
-                        return Ok(tok);
+                    Ok((Some((token, token_type)), amt)) => {
+                        (Scanned::Token(token, token_type), amt)
                     }
-                }
+                };
+                let start = self.offset;
+                self.consume(amt);
+                let span = Span {
+                    trivia: self.trivia..start,
+                    token: start..self.offset,
+                };
+                self.trivia = self.offset;
+                return Ok(Some((scanned, span)));
             }
             // We cannot generate a token with what we are holding.
             // If we've already hit EOF, we are done.
@@ -272,23 +477,47 @@ impl<I: Input, S: Splitter> Scanner<I, S> {
         debug!(target: "scanner", "consume({})", amt);
         debug_assert!(amt <= self.input.len());
         for byte in &self.input.buffer()[..amt] {
+            if self.cr {
+                self.cr = false;
+                if *byte != b'\n' {
+                    self.column += 1;
+                }
+            }
             if *byte == b'\n' {
                 self.line += 1;
                 self.column = 1;
+            } else if *byte == b'\r' && self.crlf {
+                // counted only if not followed by `\n`
+                self.cr = true;
             } else {
                 self.column += 1;
             }
         }
+        self.offset += amt;
         self.input.consume(amt);
     }
 }
 
+// Length of the UTF-8 character starting with `b`
+#[cfg(feature = "std")]
+fn utf8_len(b: u8) -> usize {
+    match b {
+        0xF0.. => 4,
+        0xE0.. => 3,
+        0xC0.. => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(feature = "std")]
 impl<I: Input, S: Splitter> fmt::Debug for Scanner<I, S> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Scanner")
             .field("input", &self.input)
             .field("line", &self.line)
             .field("column", &self.column)
+            .field("offset", &self.offset)
+            .field("crlf", &self.crlf)
             .finish()
     }
 }
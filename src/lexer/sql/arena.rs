@@ -0,0 +1,465 @@
+//! Expression parser allocating nodes in a caller-owned arena
+//! (see [`ast::arena`](crate::ast::arena))
+use alloc::format;
+use bumpalo::collections::Vec;
+use bumpalo::Bump;
+use core::str;
+use memchr::memchr_iter;
+
+use super::{Error, Tokenizer};
+use crate::dialect::from_token;
+use crate::dialect::TokenType::{self, *};
+use crate::lexer::scan::{ScanError, Splitter};
+use crate::parser::ast::arena::{Expr, Literal};
+use crate::parser::ast::{Distinctness, LikeOperator, Operator, UnaryOperator};
+use crate::parser::parse::{yyParser, YYCODETYPE};
+
+/// Maximum nesting of expressions (like the parser stack depth)
+const MAX_DEPTH: usize = 128;
+
+/// Parse a single expression, allocating its nodes in `bump`.
+///
+/// Identifiers and literals are borrowed from `sql`.
+/// Subqueries, `CAST`, `RAISE` and window functions are not supported.
+///
+/// ```rust
+/// use bumpalo::Bump;
+/// use sqlite3_parser::ast::arena::Expr;
+/// use sqlite3_parser::lexer::sql::parse_arena_expr;
+///
+/// let bump = Bump::new();
+/// let expr = parse_arena_expr(&bump, "a + 1").unwrap();
+/// assert!(matches!(expr, Expr::Binary(Expr::Id("a"), _, _)));
+/// ```
+pub fn parse_arena_expr<'a>(bump: &'a Bump, sql: &'a str) -> Result<&'a Expr<'a>, Error> {
+    let mut parser = ArenaParser {
+        bump,
+        sql,
+        tokenizer: Tokenizer::new(),
+        pos: 0,
+        peeked: None,
+        depth: 0,
+    };
+    let expr = parser.expr(0)?;
+    match parser.next()? {
+        None => Ok(bump.alloc(expr)),
+        token => Err(parser.syntax_error(token)),
+    }
+}
+
+/// Recursive descent (precedence climbing) parser for `expr` of parse.y
+struct ArenaParser<'a> {
+    bump: &'a Bump,
+    sql: &'a str,
+    tokenizer: Tokenizer,
+    /// Byte offset just after the last scanned token
+    pos: usize,
+    /// Scanned but not yet consumed token
+    peeked: Option<(TokenType, &'a str)>,
+    depth: usize,
+}
+
+impl<'a> ArenaParser<'a> {
+    fn scan(&mut self) -> Result<Option<(TokenType, &'a str)>, Error> {
+        let data = self.sql.as_bytes();
+        loop {
+            match self.tokenizer.split(&data[self.pos..], true) {
+                Err(err) => return Err(self.position(err)),
+                Ok((None, 0)) => return Ok(None),
+                Ok((None, amt)) => self.pos += amt,
+                Ok((Some((token, token_type)), amt)) => {
+                    self.pos += amt;
+                    // `token` is a slice of `sql`
+                    return Ok(Some((token_type, unsafe {
+                        str::from_utf8_unchecked(token)
+                    })));
+                }
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<TokenType>, Error> {
+        if self.peeked.is_none() {
+            self.peeked = self.scan()?;
+        }
+        Ok(self.peeked.map(|(token_type, _)| token_type))
+    }
+
+    fn next(&mut self) -> Result<Option<(TokenType, &'a str)>, Error> {
+        match self.peeked.take() {
+            Some(token) => Ok(Some(token)),
+            None => self.scan(),
+        }
+    }
+
+    // Consume the next token if it is a `token_type`
+    fn eat(&mut self, token_type: TokenType) -> Result<bool, Error> {
+        if self.peek()? == Some(token_type) {
+            self.peeked = None;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn expect(&mut self, token_type: TokenType) -> Result<&'a str, Error> {
+        match self.next()? {
+            Some((ty, text)) if ty == token_type => Ok(text),
+            token => Err(self.syntax_error(token)),
+        }
+    }
+
+    fn position(&self, mut err: Error) -> Error {
+        let consumed = &self.sql.as_bytes()[..self.pos];
+        let line = memchr_iter(b'\n', consumed).count() as u64 + 1;
+        let column = self.pos
+            - consumed
+                .iter()
+                .rposition(|b| *b == b'\n')
+                .map_or(0, |i| i + 1);
+        err.position(line, column + 1, self.pos);
+        err
+    }
+
+    fn syntax_error(&self, token: Option<(TokenType, &str)>) -> Error {
+        let msg = match token {
+            Some((token_type, text)) => format!(
+                "near \"{}\": syntax error",
+                token_type.text(token_type.has_value().then_some(text))
+            ),
+            None => "incomplete input".into(),
+        };
+        self.position(Error::SyntaxError(msg, None))
+    }
+
+    fn unsupported(&self, what: &str) -> Error {
+        self.position(Error::ParserError(
+            format!("{} not supported in arena expressions", what),
+            None,
+        ))
+    }
+
+    fn node(&mut self, min: u8) -> Result<&'a Expr<'a>, Error> {
+        let expr = self.expr(min)?;
+        Ok(self.bump.alloc(expr))
+    }
+
+    // Expression whose operators bind at least as tightly as `min`
+    fn expr(&mut self, min: u8) -> Result<Expr<'a>, Error> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.position(Error::SyntaxError("parser stack overflow".into(), None)));
+        }
+        let mut lhs = self.primary()?;
+        while let Some(token_type) = self.peek()? {
+            match precedence(token_type) {
+                Some(precedence) if precedence >= min => {}
+                _ => break,
+            }
+            let (_, text) = self.next()?.unwrap();
+            lhs = self.infix(lhs, token_type, text)?;
+        }
+        self.depth -= 1;
+        Ok(lhs)
+    }
+
+    fn primary(&mut self) -> Result<Expr<'a>, Error> {
+        let Some((token_type, text)) = self.next()? else {
+            return Err(self.syntax_error(None));
+        };
+        Ok(match token_type {
+            TK_NULL => Expr::Literal(Literal::Null),
+            TK_BLOB => Expr::Literal(Literal::Blob(text)),
+            TK_STRING if self.peek()? == Some(TK_DOT) => self.qualified(text)?,
+            TK_STRING => Expr::Literal(Literal::String(text)),
+            TK_FLOAT | TK_INTEGER => Expr::Literal(Literal::Numeric(text)),
+            TK_VARIABLE => Expr::Variable(text),
+            TK_CTIME_KW => Expr::Literal(if "CURRENT_DATE".eq_ignore_ascii_case(text) {
+                Literal::CurrentDate
+            } else if "CURRENT_TIME".eq_ignore_ascii_case(text) {
+                Literal::CurrentTime
+            } else {
+                Literal::CurrentTimestamp
+            }),
+            TK_LP => {
+                if matches!(self.peek()?, Some(TK_SELECT | TK_WITH | TK_VALUES)) {
+                    return Err(self.unsupported("subquery"));
+                }
+                let exprs = self.exprlist()?;
+                self.expect(TK_RP)?;
+                Expr::Parenthesized(exprs)
+            }
+            TK_CASE => self.case()?,
+            TK_NOT => Expr::Unary(UnaryOperator::Not, self.node(3)?),
+            TK_BITNOT | TK_PLUS | TK_MINUS => Expr::Unary(
+                UnaryOperator::from(token_type as YYCODETYPE),
+                self.node(12)?,
+            ),
+            TK_EXISTS => return Err(self.unsupported("subquery")),
+            TK_CAST | TK_RAISE if self.peek()? == Some(TK_LP) => {
+                return Err(self.unsupported(token_type.name()))
+            }
+            _ if is_id(token_type) => {
+                let name = self.id(token_type, text);
+                if self.peek()? == Some(TK_DOT) {
+                    self.qualified(name)?
+                } else if token_type != TK_JOIN_KW && self.eat(TK_LP)? {
+                    self.function_call(name)?
+                } else {
+                    Expr::Id(name)
+                }
+            }
+            _ => return Err(self.syntax_error(Some((token_type, text)))),
+        })
+    }
+
+    // Text of an identifier: keywords used as identifiers are not borrowed
+    fn id(&self, token_type: TokenType, text: &'a str) -> &'a str {
+        if token_type.has_value() || matches!(token_type, TK_WINDOW | TK_OVER | TK_FILTER) {
+            text
+        } else {
+            self.bump
+                .alloc_str(&from_token(token_type as YYCODETYPE, None))
+        }
+    }
+
+    // `nm` of parse.y
+    fn name(&mut self) -> Result<&'a str, Error> {
+        match self.next()? {
+            Some((token_type, text)) if token_type == TK_STRING || is_id(token_type) => {
+                Ok(self.id(token_type, text))
+            }
+            token => Err(self.syntax_error(token)),
+        }
+    }
+
+    // `nm DOT nm` or `nm DOT nm DOT nm`
+    fn qualified(&mut self, first: &'a str) -> Result<Expr<'a>, Error> {
+        self.expect(TK_DOT)?;
+        let second = self.name()?;
+        if self.eat(TK_DOT)? {
+            Ok(Expr::DoublyQualified(first, second, self.name()?))
+        } else {
+            Ok(Expr::Qualified(first, second))
+        }
+    }
+
+    // After `id LP`
+    fn function_call(&mut self, name: &'a str) -> Result<Expr<'a>, Error> {
+        let expr = if self.eat(TK_STAR)? {
+            self.expect(TK_RP)?;
+            Expr::FunctionCallStar { name }
+        } else {
+            let distinctness = if self.eat(TK_DISTINCT)? {
+                Some(Distinctness::Distinct)
+            } else if self.eat(TK_ALL)? {
+                Some(Distinctness::All)
+            } else {
+                None
+            };
+            let args = if self.eat(TK_RP)? {
+                None
+            } else {
+                let args = self.exprlist()?;
+                self.expect(TK_RP)?;
+                Some(args)
+            };
+            Expr::FunctionCall {
+                name,
+                distinctness,
+                args,
+            }
+        };
+        if matches!(self.peek()?, Some(TK_FILTER | TK_OVER)) {
+            return Err(self.unsupported("window function"));
+        }
+        Ok(expr)
+    }
+
+    // After `CASE`
+    fn case(&mut self) -> Result<Expr<'a>, Error> {
+        let base = if self.peek()? == Some(TK_WHEN) {
+            None
+        } else {
+            Some(self.node(0)?)
+        };
+        let mut when_then_pairs = Vec::new_in(self.bump);
+        while self.eat(TK_WHEN)? {
+            let when = self.expr(0)?;
+            self.expect(TK_THEN)?;
+            when_then_pairs.push((when, self.expr(0)?));
+        }
+        if when_then_pairs.is_empty() {
+            let token = self.next()?;
+            return Err(self.syntax_error(token));
+        }
+        let else_expr = if self.eat(TK_ELSE)? {
+            Some(self.node(0)?)
+        } else {
+            None
+        };
+        self.expect(TK_END)?;
+        Ok(Expr::Case {
+            base,
+            when_then_pairs: when_then_pairs.into_bump_slice(),
+            else_expr,
+        })
+    }
+
+    // `nexprlist` of parse.y
+    fn exprlist(&mut self) -> Result<&'a [Expr<'a>], Error> {
+        let mut exprs = Vec::new_in(self.bump);
+        loop {
+            exprs.push(self.expr(0)?);
+            if !self.eat(TK_COMMA)? {
+                return Ok(exprs.into_bump_slice());
+            }
+        }
+    }
+
+    // After the operator token of a binary or postfix expression
+    fn infix(
+        &mut self,
+        lhs: Expr<'a>,
+        token_type: TokenType,
+        text: &'a str,
+    ) -> Result<Expr<'a>, Error> {
+        let lhs = self.bump.alloc(lhs);
+        Ok(match token_type {
+            TK_ISNULL => Expr::IsNull(lhs),
+            TK_NOTNULL => Expr::NotNull(lhs),
+            TK_NOT => match self.next()? {
+                Some((TK_NULL, _)) => Expr::NotNull(lhs),
+                Some((TK_BETWEEN, _)) => self.between(lhs, true)?,
+                Some((TK_IN, _)) => self.in_list(lhs, true)?,
+                Some((token_type @ (TK_LIKE_KW | TK_MATCH), text)) => {
+                    self.like(lhs, true, token_type, text)?
+                }
+                token => return Err(self.syntax_error(token)),
+            },
+            TK_BETWEEN => self.between(lhs, false)?,
+            TK_IN => self.in_list(lhs, false)?,
+            TK_LIKE_KW | TK_MATCH => self.like(lhs, false, token_type, text)?,
+            TK_COLLATE => match self.next()? {
+                Some((token_type, text)) if token_type == TK_STRING || is_id(token_type) => {
+                    Expr::Collate(lhs, self.id(token_type, text))
+                }
+                token => return Err(self.syntax_error(token)),
+            },
+            TK_IS => {
+                let op = if self.eat(TK_NOT)? {
+                    if self.eat(TK_DISTINCT)? {
+                        self.expect(TK_FROM)?;
+                        Operator::IsNotDistinctFrom
+                    } else {
+                        Operator::IsNot
+                    }
+                } else if self.eat(TK_DISTINCT)? {
+                    self.expect(TK_FROM)?;
+                    Operator::IsDistinctFrom
+                } else {
+                    Operator::Is
+                };
+                Expr::Binary(lhs, op, self.node(5)?)
+            }
+            TK_PTR => {
+                let op = if text == "->>" {
+                    Operator::ArrowRightShift
+                } else {
+                    Operator::ArrowRight
+                };
+                Expr::Binary(lhs, op, self.node(11)?)
+            }
+            _ => {
+                let rhs = self.node(precedence(token_type).unwrap() + 1)?;
+                Expr::Binary(lhs, Operator::from(token_type as YYCODETYPE), rhs)
+            }
+        })
+    }
+
+    // After `BETWEEN`: `a BETWEEN b AND c OR d` is `(a BETWEEN b AND c) OR d`
+    fn between(&mut self, lhs: &'a Expr<'a>, not: bool) -> Result<Expr<'a>, Error> {
+        let start = self.node(3)?;
+        self.expect(TK_AND)?;
+        let end = self.node(5)?;
+        Ok(Expr::Between {
+            lhs,
+            not,
+            start,
+            end,
+        })
+    }
+
+    // After `IN`
+    fn in_list(&mut self, lhs: &'a Expr<'a>, not: bool) -> Result<Expr<'a>, Error> {
+        if !self.eat(TK_LP)? {
+            return Err(self.unsupported("IN table"));
+        }
+        let rhs = match self.peek()? {
+            Some(TK_SELECT | TK_WITH | TK_VALUES) => return Err(self.unsupported("subquery")),
+            Some(TK_RP) => None,
+            _ => Some(self.exprlist()?),
+        };
+        self.expect(TK_RP)?;
+        Ok(Expr::InList { lhs, not, rhs })
+    }
+
+    // After `LIKE`, `GLOB`, `REGEXP` or `MATCH`
+    fn like(
+        &mut self,
+        lhs: &'a Expr<'a>,
+        not: bool,
+        token_type: TokenType,
+        text: &str,
+    ) -> Result<Expr<'a>, Error> {
+        let op = if token_type == TK_MATCH {
+            LikeOperator::Match
+        } else if "GLOB".eq_ignore_ascii_case(text) {
+            LikeOperator::Glob
+        } else if "REGEXP".eq_ignore_ascii_case(text) {
+            LikeOperator::Regexp
+        } else {
+            LikeOperator::Like
+        };
+        let rhs = self.node(5)?;
+        let escape = if self.eat(TK_ESCAPE)? {
+            Some(self.node(5)?)
+        } else {
+            None
+        };
+        Ok(Expr::Like {
+            lhs,
+            not,
+            op,
+            rhs,
+            escape,
+        })
+    }
+}
+
+// Whether `token_type` can be used as an identifier (`id` or `JOIN_KW`)
+fn is_id(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TK_ID | TK_INDEXED | TK_JOIN_KW | TK_WINDOW | TK_OVER | TK_FILTER
+    ) || yyParser::parse_fallback(token_type as YYCODETYPE) == TK_ID as YYCODETYPE
+}
+
+// Binding power of a binary or postfix operator (see `%left` declarations of
+// the grammar): `NOT` of `NOT NULL`, `NOT LIKE`, ... binds like prefix `NOT`
+fn precedence(token_type: TokenType) -> Option<u8> {
+    match token_type {
+        TK_OR => Some(1),
+        TK_AND => Some(2),
+        TK_NOT => Some(3),
+        TK_IS | TK_MATCH | TK_LIKE_KW | TK_BETWEEN | TK_IN | TK_ISNULL | TK_NOTNULL | TK_NE
+        | TK_EQ => Some(4),
+        TK_GT | TK_LE | TK_LT | TK_GE => Some(5),
+        TK_BITAND | TK_BITOR | TK_LSHIFT | TK_RSHIFT => Some(7),
+        TK_PLUS | TK_MINUS => Some(8),
+        TK_STAR | TK_SLASH | TK_REM => Some(9),
+        TK_CONCAT | TK_PTR => Some(10),
+        TK_COLLATE => Some(11),
+        _ => None,
+    }
+}
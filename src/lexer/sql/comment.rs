@@ -0,0 +1,88 @@
+//! Comments of a script
+use core::fmt;
+
+use super::Tokenizer;
+use crate::lexer::scan::Splitter;
+
+/// Delimiters of a [`Comment`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommentKind {
+    /// `-- ...` up to the end of the line
+    Line,
+    /// `/* ... */`
+    Block,
+}
+
+/// Comment with its delimiters stripped
+///
+/// Its `Display` implementation reconstructs the exact source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Comment<'input> {
+    pub kind: CommentKind,
+    /// Text between the delimiters (the line break ending a `--` comment excluded)
+    pub text: &'input str,
+}
+
+impl fmt::Display for Comment<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            CommentKind::Line => write!(f, "--{}", self.text),
+            CommentKind::Block => write!(f, "/*{}*/", self.text),
+        }
+    }
+}
+
+/// Iterator over the comments of a script.
+///
+/// See [`comments`].
+pub struct Comments<'input> {
+    sql: &'input str,
+    tokenizer: Tokenizer,
+}
+
+/// Iterate over the comments of `sql` script, in order
+/// (including comments inside `CREATE TRIGGER` bodies, excluding
+/// comment-like text inside literals).
+/// Iteration stops if the lexer fails (unterminated literal, ...).
+pub fn comments(sql: &str) -> Comments<'_> {
+    Comments {
+        sql,
+        tokenizer: Tokenizer::new(),
+    }
+}
+
+impl<'input> Iterator for Comments<'input> {
+    type Item = Comment<'input>;
+
+    fn next(&mut self) -> Option<Comment<'input>> {
+        let sql = self.sql;
+        let mut pos = 0;
+        while pos < sql.len() {
+            match self.tokenizer.split(&sql.as_bytes()[pos..], true) {
+                Err(_) | Ok((None, 0)) => break,
+                Ok((None, amt)) => {
+                    let skipped = &sql[pos..pos + amt];
+                    pos += amt;
+                    let comment = if let Some(text) = skipped.strip_prefix("--") {
+                        Comment {
+                            kind: CommentKind::Line,
+                            text: text.strip_suffix('\n').unwrap_or(text),
+                        }
+                    } else if let Some(text) = skipped.strip_prefix("/*") {
+                        Comment {
+                            kind: CommentKind::Block,
+                            text: &text[..text.len() - 2],
+                        }
+                    } else {
+                        continue; // whitespaces
+                    };
+                    self.sql = &sql[pos..];
+                    return Some(comment);
+                }
+                Ok((Some(_), amt)) => pos += amt,
+            }
+        }
+        self.sql = "";
+        None
+    }
+}
@@ -0,0 +1,170 @@
+//! Lossless concrete syntax tree
+//!
+//! Unlike the AST, the CST keeps every byte of the input (whitespace,
+//! comments, keyword casing, literal spelling): rendering a tree gives back
+//! exactly the parsed text. Its structure is shallow (statements and
+//! parenthesized groups of tokens); the AST of a statement is obtained with
+//! [`Node::to_ast`].
+use core::fmt;
+
+use super::split::{token_class, START, TRANS};
+use super::{Error, Parser, TokenType, TokenType::*, Tokenizer};
+use crate::lexer::scan::Splitter;
+use crate::lexer::Scanner;
+use crate::parser::ast::Cmd;
+use fallible_iterator::FallibleIterator;
+
+/// Kind of a [`Node`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    /// Whole input
+    Script,
+    /// Statement, from its first token to its terminating `;` (if any)
+    Statement,
+    /// From a `(` to the matching `)` (if any)
+    Parenthesized,
+}
+
+/// Kind of a [`Element::Trivia`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    /// `-- ...` (with its line break) or `/* ... */`
+    Comment,
+}
+
+/// Child of a [`Node`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Element {
+    Node(Node),
+    /// Token type and source text
+    Token(TokenType, String),
+    Trivia(TriviaKind, String),
+}
+
+/// Inner node of a CST
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub children: Vec<Element>,
+}
+
+impl Node {
+    fn new(kind: NodeKind) -> Node {
+        Node {
+            kind,
+            children: Vec::new(),
+        }
+    }
+
+    /// Statement nodes of a script
+    pub fn statements(&self) -> impl Iterator<Item = &Node> {
+        self.children.iter().filter_map(|child| match child {
+            Element::Node(node) if node.kind == NodeKind::Statement => Some(node),
+            _ => None,
+        })
+    }
+
+    /// Tokens of this node and of its descendants (trivia excluded), in order
+    pub fn tokens(&self) -> Vec<(TokenType, &str)> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<(TokenType, &'a str)>) {
+        for child in self.children.iter() {
+            match child {
+                Element::Node(node) => node.collect_tokens(tokens),
+                Element::Token(token_type, text) => tokens.push((*token_type, text)),
+                Element::Trivia(..) => {}
+            }
+        }
+    }
+
+    /// Project the text of this node onto the AST
+    /// (`None` if it contains only empty statements).
+    /// For a script, only its first statement is parsed.
+    pub fn to_ast(&self) -> Result<Option<Cmd>, Error> {
+        let sql = self.to_string();
+        Parser::new(sql.as_bytes()).next()
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.children.iter().try_for_each(|child| child.fmt(f))
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Element::Node(node) => node.fmt(f),
+            Element::Token(_, text) | Element::Trivia(_, text) => f.write_str(text),
+        }
+    }
+}
+
+/// Build the CST of `sql` script.
+/// Statements are split like [`split_statements`](super::split_statements)
+/// does. Only lexical errors are reported: syntax errors are detected when
+/// statements are projected onto the AST.
+pub fn parse(sql: &str) -> Result<Node, Error> {
+    let mut scanner = Scanner::new(sql.as_bytes(), Tokenizer::new());
+    // open nodes, the script first
+    let mut stack = vec![Node::new(NodeKind::Script)];
+    let mut state = 0;
+    while let Some((_, token_type, span)) = scanner.scan_spanned()? {
+        push_trivia(stack.last_mut().unwrap(), &sql[span.trivia]);
+        if stack.len() == 1 {
+            stack.push(Node::new(NodeKind::Statement));
+        }
+        if token_type == TK_LP {
+            stack.push(Node::new(NodeKind::Parenthesized));
+        }
+        let token = Element::Token(token_type, sql[span.token].to_owned());
+        stack.last_mut().unwrap().children.push(token);
+        if token_type == TK_RP && stack.last().unwrap().kind == NodeKind::Parenthesized {
+            close(&mut stack);
+        }
+        state = TRANS[state as usize][token_class(token_type)];
+        if token_type == TK_SEMI && state == START {
+            while stack.len() > 1 {
+                close(&mut stack);
+            }
+        }
+    }
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+    let mut script = stack.pop().unwrap();
+    push_trivia(&mut script, &sql[scanner.token_end()..]);
+    Ok(script)
+}
+
+// Add the innermost open node to its parent
+fn close(stack: &mut Vec<Node>) {
+    let node = stack.pop().unwrap();
+    stack.last_mut().unwrap().children.push(Element::Node(node));
+}
+
+// Split whitespace and comments
+fn push_trivia(node: &mut Node, trivia: &str) {
+    let mut tokenizer = Tokenizer::new();
+    let mut pos = 0;
+    while pos < trivia.len() {
+        let amt = match tokenizer.split(&trivia.as_bytes()[pos..], true) {
+            Ok((None, amt)) if amt > 0 => amt,
+            _ => trivia.len() - pos,
+        };
+        let text = &trivia[pos..pos + amt];
+        let kind = if text.starts_with("--") || text.starts_with("/*") {
+            TriviaKind::Comment
+        } else {
+            TriviaKind::Whitespace
+        };
+        node.children.push(Element::Trivia(kind, text.to_owned()));
+        pos += amt;
+    }
+}
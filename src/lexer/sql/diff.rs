@@ -0,0 +1,76 @@
+//! Token-level diff of two SQL texts
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::Tokenizer;
+use crate::lexer::scan::Splitter;
+
+/// Edit operation of a [`token_diff`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffOp<'input> {
+    /// Token of both texts
+    Equal(&'input str),
+    /// Token only in the first text
+    Delete(&'input str),
+    /// Token only in the second text
+    Insert(&'input str),
+}
+
+/// Diff the tokens of `a` and `b` (whitespace and comments ignored), using
+/// the longest common subsequence of their token texts.
+/// Deletions come before insertions at the same position.
+/// If the lexer fails (unterminated literal, ...), the rest of the text is
+/// a single token.
+pub fn token_diff<'input>(a: &'input str, b: &'input str) -> Vec<DiffOp<'input>> {
+    let (a, b) = (tokens(a), tokens(b));
+    let (n, m) = (a.len(), b.len());
+    // lcs[i][j]: length of the longest common subsequence of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut ops = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && a[i] == b[j] {
+            ops.push(DiffOp::Equal(a[i]));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(DiffOp::Delete(a[i]));
+            i += 1;
+        } else {
+            ops.push(DiffOp::Insert(b[j]));
+            j += 1;
+        }
+    }
+    ops
+}
+
+fn tokens(sql: &str) -> Vec<&str> {
+    let data = sql.as_bytes();
+    let mut tokenizer = Tokenizer::new();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        match tokenizer.split(&data[pos..], true) {
+            Err(_) => {
+                tokens.push(sql[pos..].trim_end());
+                break;
+            }
+            Ok((None, 0)) => break,
+            Ok((None, amt)) => pos += amt,
+            Ok((Some(_), amt)) => {
+                tokens.push(&sql[pos..pos + amt]);
+                pos += amt;
+            }
+        }
+    }
+    tokens
+}
@@ -1,5 +1,8 @@
+use alloc::string::{String, ToString};
+use core::fmt::{self, Write};
+#[cfg(feature = "std")]
 use std::error;
-use std::fmt;
+#[cfg(feature = "std")]
 use std::io;
 
 use crate::lexer::scan::ScanError;
@@ -9,51 +12,150 @@ use crate::parser::ParserError;
 #[derive(Debug)]
 pub enum Error {
     /// I/O Error
+    #[cfg(feature = "std")]
     Io(io::Error),
-    UnrecognizedToken(Option<(u64, usize)>),
-    UnterminatedLiteral(Option<(u64, usize)>),
-    UnterminatedBracket(Option<(u64, usize)>),
-    UnterminatedBlockComment(Option<(u64, usize)>),
-    BadVariableName(Option<(u64, usize)>),
-    BadNumber(Option<(u64, usize)>),
-    ExpectedEqualsSign(Option<(u64, usize)>),
-    MalformedBlobLiteral(Option<(u64, usize)>),
-    MalformedHexInteger(Option<(u64, usize)>),
-    SyntaxError(String, Option<(u64, usize)>),
-    ParserError(String, Option<(u64, usize)>),
+    UnrecognizedToken(Option<(u64, usize, usize)>),
+    UnterminatedLiteral(Option<(u64, usize, usize)>),
+    UnterminatedBracket(Option<(u64, usize, usize)>),
+    UnterminatedBlockComment(Option<(u64, usize, usize)>),
+    BadVariableName(Option<(u64, usize, usize)>),
+    BadNumber(Option<(u64, usize, usize)>),
+    ExpectedEqualsSign(Option<(u64, usize, usize)>),
+    MalformedBlobLiteral(Option<(u64, usize, usize)>),
+    MalformedHexInteger(Option<(u64, usize, usize)>),
+    SyntaxError(String, Option<(u64, usize, usize)>),
+    ParserError(String, Option<(u64, usize, usize)>),
+    /// Identifier longer than `ParserOptions::max_identifier_length`: identifier, limit
+    IdentifierTooLong(String, usize, Option<(u64, usize, usize)>),
+    /// More tokens than `ParserOptions::max_tokens`: limit
+    BudgetExceeded(usize, Option<(u64, usize, usize)>),
+    /// More statements than `ParserOptions::max_statements`: limit
+    TooManyStatements(usize, Option<(u64, usize, usize)>),
+    /// Parsing aborted by `Parser::cancel_flag`
+    Cancelled(Option<(u64, usize, usize)>),
+}
+
+impl Error {
+    /// Line and column (byte offset, not char offset) where the error occurred
+    pub fn line_column(&self) -> Option<(u64, usize)> {
+        self.pos().map(|(line, column, _)| (line, column))
+    }
+
+    /// Byte offset from the start of the input where the error occurred
+    pub fn offset(&self) -> Option<usize> {
+        self.pos().map(|(_, _, offset)| offset)
+    }
+
+    fn pos(&self) -> Option<(u64, usize, usize)> {
+        match *self {
+            #[cfg(feature = "std")]
+            Error::Io(_) => None,
+            Error::UnrecognizedToken(pos)
+            | Error::UnterminatedLiteral(pos)
+            | Error::UnterminatedBracket(pos)
+            | Error::UnterminatedBlockComment(pos)
+            | Error::BadVariableName(pos)
+            | Error::BadNumber(pos)
+            | Error::ExpectedEqualsSign(pos)
+            | Error::MalformedBlobLiteral(pos)
+            | Error::MalformedHexInteger(pos)
+            | Error::SyntaxError(_, pos)
+            | Error::ParserError(_, pos)
+            | Error::IdentifierTooLong(_, _, pos)
+            | Error::BudgetExceeded(_, pos)
+            | Error::TooManyStatements(_, pos)
+            | Error::Cancelled(pos) => pos,
+        }
+    }
+
+    /// Render the error followed by the offending line of `source` (the input
+    /// which was parsed) and a caret under the error position:
+    /// ```text
+    /// near "x": syntax error at (1, 23)
+    /// 1 | SELECT 1 FROM t WHER x
+    ///   |                       ^
+    /// ```
+    /// For syntax errors, the position is just after the unexpected token.
+    pub fn snippet(&self, source: &str) -> String {
+        let mut snippet = self.to_string();
+        let (line, offset) = match self.pos() {
+            Some((line, _, offset)) if source.is_char_boundary(offset) => (line, offset),
+            _ => return snippet,
+        };
+        let start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |i| offset + i);
+        let text = source[start..end].trim_end_matches('\r');
+        let width = source[start..offset].chars().count();
+        let gutter = line.to_string();
+        let _ = write!(
+            snippet,
+            "\n{} | {}\n{:w$} | {:>c$}",
+            gutter,
+            text,
+            "",
+            "^",
+            w = gutter.len(),
+            c = width + 1
+        );
+        snippet
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
+            #[cfg(feature = "std")]
             Error::Io(ref err) => err.fmt(f),
-            Error::UnrecognizedToken(pos) => write!(f, "unrecognized token at {:?}", pos.unwrap()),
+            Error::UnrecognizedToken(pos) => write!(f, "unrecognized token at {:?}", at(pos)),
             Error::UnterminatedLiteral(pos) => {
-                write!(f, "non-terminated literal at {:?}", pos.unwrap())
+                write!(f, "non-terminated literal at {:?}", at(pos))
             }
             Error::UnterminatedBracket(pos) => {
-                write!(f, "non-terminated bracket at {:?}", pos.unwrap())
+                write!(f, "non-terminated bracket at {:?}", at(pos))
             }
             Error::UnterminatedBlockComment(pos) => {
-                write!(f, "non-terminated block comment at {:?}", pos.unwrap())
+                write!(f, "non-terminated block comment at {:?}", at(pos))
             }
-            Error::BadVariableName(pos) => write!(f, "bad variable name at {:?}", pos.unwrap()),
-            Error::BadNumber(pos) => write!(f, "bad number at {:?}", pos.unwrap()),
-            Error::ExpectedEqualsSign(pos) => write!(f, "expected = sign at {:?}", pos.unwrap()),
+            Error::BadVariableName(pos) => write!(f, "bad variable name at {:?}", at(pos)),
+            Error::BadNumber(pos) => write!(f, "bad number at {:?}", at(pos)),
+            Error::ExpectedEqualsSign(pos) => write!(f, "expected = sign at {:?}", at(pos)),
             Error::MalformedBlobLiteral(pos) => {
-                write!(f, "malformed blob literal at {:?}", pos.unwrap())
+                write!(f, "malformed blob literal at {:?}", at(pos))
             }
             Error::MalformedHexInteger(pos) => {
-                write!(f, "malformed hex integer at {:?}", pos.unwrap())
+                write!(f, "malformed hex integer at {:?}", at(pos))
+            }
+            Error::SyntaxError(ref msg, pos) => write!(f, "{} at {:?}", msg, at(pos)),
+            Error::ParserError(ref msg, pos) => write!(f, "{} at {:?}", msg, at(pos)),
+            Error::IdentifierTooLong(ref id, max, pos) => write!(
+                f,
+                "identifier too long (max {}): {} at {:?}",
+                max,
+                id,
+                at(pos)
+            ),
+            Error::BudgetExceeded(max, pos) => {
+                write!(f, "too many tokens (max {}) at {:?}", max, at(pos))
             }
-            Error::SyntaxError(ref msg, pos) => write!(f, "{} at {:?}", msg, pos.unwrap()),
-            Error::ParserError(ref msg, pos) => write!(f, "{} at {:?}", msg, pos.unwrap()),
+            Error::TooManyStatements(max, pos) => {
+                write!(f, "too many statements (max {}) at {:?}", max, at(pos))
+            }
+            Error::Cancelled(pos) => write!(f, "parsing cancelled at {:?}", at(pos)),
         }
     }
 }
 
+fn at(pos: Option<(u64, usize, usize)>) -> (u64, usize) {
+    let (line, column, _) = pos.unwrap();
+    (line, column)
+}
+
+#[cfg(feature = "std")]
 impl error::Error for Error {}
 
+#[cfg(feature = "std")]
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Error {
         Error::Io(err)
@@ -67,20 +169,25 @@ impl From<ParserError> for Error {
 }
 
 impl ScanError for Error {
-    fn position(&mut self, line: u64, column: usize) {
+    fn position(&mut self, line: u64, column: usize, offset: usize) {
         match *self {
+            #[cfg(feature = "std")]
             Error::Io(_) => {}
-            Error::UnrecognizedToken(ref mut pos) => *pos = Some((line, column)),
-            Error::UnterminatedLiteral(ref mut pos) => *pos = Some((line, column)),
-            Error::UnterminatedBracket(ref mut pos) => *pos = Some((line, column)),
-            Error::UnterminatedBlockComment(ref mut pos) => *pos = Some((line, column)),
-            Error::BadVariableName(ref mut pos) => *pos = Some((line, column)),
-            Error::BadNumber(ref mut pos) => *pos = Some((line, column)),
-            Error::ExpectedEqualsSign(ref mut pos) => *pos = Some((line, column)),
-            Error::MalformedBlobLiteral(ref mut pos) => *pos = Some((line, column)),
-            Error::MalformedHexInteger(ref mut pos) => *pos = Some((line, column)),
-            Error::SyntaxError(_, ref mut pos) => *pos = Some((line, column)),
-            Error::ParserError(_, ref mut pos) => *pos = Some((line, column)),
+            Error::UnrecognizedToken(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::UnterminatedLiteral(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::UnterminatedBracket(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::UnterminatedBlockComment(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::BadVariableName(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::BadNumber(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::ExpectedEqualsSign(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::MalformedBlobLiteral(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::MalformedHexInteger(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::SyntaxError(_, ref mut pos) => *pos = Some((line, column, offset)),
+            Error::ParserError(_, ref mut pos) => *pos = Some((line, column, offset)),
+            Error::IdentifierTooLong(_, _, ref mut pos) => *pos = Some((line, column, offset)),
+            Error::BudgetExceeded(_, ref mut pos) => *pos = Some((line, column, offset)),
+            Error::TooManyStatements(_, ref mut pos) => *pos = Some((line, column, offset)),
+            Error::Cancelled(ref mut pos) => *pos = Some((line, column, offset)),
         }
     }
 }
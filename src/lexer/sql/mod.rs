@@ -1,41 +1,138 @@
 //! Adaptation/port of [`SQLite` tokenizer](http://www.sqlite.org/src/artifact?ci=trunk&filename=src/tokenize.c)
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::ops::Range;
+#[cfg(feature = "std")]
 use fallible_iterator::FallibleIterator;
-use memchr::memchr;
+use memchr::{memchr, memchr_iter};
+#[cfg(feature = "std")]
 use std::collections::VecDeque;
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "std")]
+use std::sync::Arc;
 
+#[cfg(feature = "std")]
+use crate::dialect::from_bytes;
 pub use crate::dialect::TokenType;
 pub use crate::dialect::TokenType::*;
-use crate::dialect::{
-    from_bytes, is_identifier_continue, is_identifier_start, keyword_token, MAX_KEYWORD_LEN,
-};
-use crate::parser::ast::Cmd;
+use crate::dialect::{is_identifier_continue, is_identifier_start, keyword_token, MAX_KEYWORD_LEN};
+#[cfg(feature = "std")]
+use crate::parser::ast::{Cmd, Diagnostic, Expr, Name, PragmaBody, Stmt};
 use crate::parser::parse::{yyParser, YYCODETYPE};
+#[cfg(feature = "std")]
 use crate::parser::Context;
 
+#[cfg(feature = "arena")]
+mod arena;
+mod comment;
+#[cfg(feature = "std")]
+pub mod cst;
+mod diff;
 mod error;
-#[cfg(test)]
+mod split;
+#[cfg(all(test, feature = "std"))]
 mod test;
 
+#[cfg(feature = "std")]
 use crate::lexer::scan::ScanError;
 use crate::lexer::scan::Splitter;
+#[cfg(feature = "std")]
 use crate::lexer::{Input, Scanner};
+#[cfg(feature = "arena")]
+pub use arena::parse_arena_expr;
+pub use comment::{comments, Comment, CommentKind, Comments};
+pub use diff::{token_diff, DiffOp};
 pub use error::Error;
+pub use split::{split_statements, SplitStatements};
 
 // TODO Extract scanning stuff and move this into the parser crate
 // to make possible to use the tokenizer without depending on the parser...
 
+/// Parser options
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// Maximum length (in characters, delimiters excluded) of identifiers
+    /// (no limit by default, like SQLite)
+    pub max_identifier_length: Option<usize>,
+    /// Whether `"..."` may be used where only a string literal is expected
+    /// (like `DEFAULT "abc"`), as SQLite does by default.
+    /// When `false`, `"..."` is always an identifier and such usages are rejected.
+    pub double_quote_is_string: bool,
+    /// Allow `ORDER BY` and `LIMIT` on `DELETE` and `UPDATE`
+    /// (only supported by SQLite when compiled with `SQLITE_ENABLE_UPDATE_DELETE_LIMIT`)
+    pub update_delete_limit: bool,
+    /// Capture the argument of a `PRAGMA` which does not fit the value grammar
+    /// (like `PRAGMA foo(a b)`) as raw text in a `PragmaBody::RawCall`
+    /// or `PragmaBody::RawEquals`
+    /// (tokens separated by a space where there was whitespace in the input)
+    /// instead of failing.
+    pub lenient_pragma: bool,
+    /// Maximum number of tokens parsed over the lifetime of the parser
+    /// (all statements included, no limit by default):
+    /// bound the work spent on adversarial input.
+    pub max_tokens: Option<usize>,
+    /// Maximum number of statements parsed over the lifetime of the parser
+    /// (no limit by default): the next statement is rejected with
+    /// [`Error::TooManyStatements`] as soon as it starts. Empty statements
+    /// (`;`) are not counted.
+    pub max_statements: Option<usize>,
+    /// Options of the underlying tokenizer
+    pub lexer: LexerOptions,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            max_identifier_length: None,
+            double_quote_is_string: true,
+            update_delete_limit: false,
+            lenient_pragma: false,
+            max_tokens: None,
+            max_statements: None,
+            lexer: LexerOptions::default(),
+        }
+    }
+}
+
+/// Number of tokens parsed between two polls of the cancel flag
+#[cfg(feature = "std")]
+const CANCEL_POLL_INTERVAL: u8 = 64;
+
+#[cfg(feature = "std")]
 pub struct Parser<I: Input> {
     scanner: Scanner<I, Tokenizer>,
     parser: yyParser,
     buffer: Vec<u8>,
     lookahead: VecDeque<(TokenType, String)>,
+    options: ParserOptions,
+    warnings: Vec<Diagnostic>,
+    expected: Vec<TokenType>,
+    /// Number of tokens parsed (see `ParserOptions::max_tokens`)
+    tokens: usize,
+    /// Number of statements parsed (see `ParserOptions::max_statements`)
+    statements: usize,
+    cancel: Option<Arc<AtomicBool>>,
+    /// Tokens parsed until the cancel flag is polled again
+    cancel_countdown: u8,
+    /// Source range and first line of the last statement
+    range: (Range<usize>, u64),
 }
 
+#[cfg(feature = "std")]
 impl<I: Input> Parser<I> {
     pub fn new(input: I) -> Parser<I> {
-        let lexer = Tokenizer::new();
+        Parser::with_options(input, ParserOptions::default())
+    }
+
+    pub fn with_options(input: I, options: ParserOptions) -> Parser<I> {
+        let lexer = Tokenizer::with_options(options.lexer);
         let scanner = Scanner::new(input, lexer);
-        let ctx = Context::new();
+        let mut ctx = Context::new();
+        ctx.double_quote_is_string = options.double_quote_is_string;
+        ctx.update_delete_limit = options.update_delete_limit;
         let parser = yyParser::new(ctx);
         let buffer = Vec::new();
         let lookahead = VecDeque::new();
@@ -44,19 +141,95 @@ impl<I: Input> Parser<I> {
             parser,
             buffer,
             lookahead,
+            options,
+            warnings: Vec::new(),
+            expected: Vec::new(),
+            tokens: 0,
+            statements: 0,
+            cancel: None,
+            cancel_countdown: CANCEL_POLL_INTERVAL,
+            range: (0..0, 1),
         }
     }
 
+    pub fn options(&self) -> &ParserOptions {
+        &self.options
+    }
+
+    /// Abort parsing with [`Error::Cancelled`] once `flag` is set
+    /// (checked every few tokens, so possibly from another thread)
+    pub fn cancel_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.cancel = Some(flag);
+    }
+
     pub fn reset(&mut self, input: I) {
         self.scanner.reset(input);
     }
 
+    /// Consume the warnings reported since the last call:
+    /// constructs accepted by the parser (possibly thanks to a lenient option)
+    /// but which would be rejected by SQLite.
+    pub fn take_warnings(&mut self) -> Vec<Diagnostic> {
+        core::mem::take(&mut self.warnings)
+    }
+
+    /// Tokens which would have been accepted where the last syntax error
+    /// occurred (empty if the last statement has been parsed successfully).
+    /// At the end of input, a `;` is implicitly added so the tokens expected
+    /// after a valid but incomplete prefix are reported.
+    pub fn expected_at_error(&self) -> Vec<TokenType> {
+        self.expected.clone()
+    }
+
+    fn collect_warnings(&mut self) {
+        let position = Some((self.scanner.line(), self.scanner.column()));
+        let splitter = self.scanner.splitter_mut();
+        for _ in 0..core::mem::take(&mut splitter.nested_comments) {
+            self.warnings.push(Diagnostic {
+                position,
+                ..Diagnostic::new("nested block comment")
+            });
+        }
+        for diagnostic in self.parser.ctx.take_warnings() {
+            self.warnings.push(Diagnostic {
+                position,
+                ..diagnostic
+            });
+        }
+    }
+
+    /// Parse the next statement with the contiguous block of `--` comments
+    /// directly preceding it (without blank line in between).
+    /// Comment markers and surrounding whitespaces are stripped.
+    pub fn next_with_comments(&mut self) -> Result<Option<(Cmd, Vec<String>)>, Error> {
+        self.scanner.splitter_mut().collect_comments();
+        let cmd = self.next()?;
+        Ok(cmd.map(|cmd| (cmd, self.scanner.splitter_mut().take_leading_comments())))
+    }
+
+    /// Parse the next statement with its byte range in the input, from its
+    /// first token to its terminating `;` (included, if any), and the line
+    /// where it starts.
+    pub fn next_with_range(&mut self) -> Result<Option<(Cmd, Range<usize>, u64)>, Error> {
+        let cmd = self.next()?;
+        Ok(cmd.map(|cmd| {
+            let (range, line) = self.range.clone();
+            (cmd, range, line)
+        }))
+    }
+
     pub fn line(&self) -> u64 {
         self.scanner.line()
     }
     pub fn column(&self) -> usize {
         self.scanner.column()
     }
+    /// Byte offset where parsing stopped: just past the terminating `;` of the
+    /// last statement returned by [`next`](FallibleIterator::next).
+    /// The unconsumed input starts there.
+    pub fn offset(&self) -> usize {
+        self.scanner.offset()
+    }
 
     /*
      ** Return the id of the next token in input.
@@ -142,21 +315,137 @@ impl<I: Input> Parser<I> {
         }
         Ok(TK_ID)
     }
+
+    /// Replace the argument of a `PRAGMA` (the tokens following `=` or `(`)
+    /// by a single string token with its raw text when it does not fit the grammar.
+    /// Return whether the argument has been replaced.
+    fn raw_pragma_argument(&mut self, call: bool) -> Result<bool, Error> {
+        let mut types = Vec::new();
+        // (start, end) offsets of the tokens scanned here
+        let mut spans = Vec::new();
+        let mut depth = 0;
+        loop {
+            let n = types.len();
+            let scanned = n >= self.lookahead.len();
+            let t = self.get_token(n)?;
+            match t {
+                TK_EOF | TK_SEMI if call => return Ok(false), // unbalanced
+                TK_EOF | TK_SEMI => break,
+                TK_RP if call && depth == 0 => break,
+                TK_LP => depth += 1,
+                TK_RP => depth -= 1,
+                _ => {}
+            }
+            spans.push(if scanned {
+                let end = self.scanner.offset();
+                let len = match self.lookahead[n] {
+                    (TK_BLOB, ref blob) => blob.len() + 3, // X'...'
+                    (_, ref value) => value.len(),
+                };
+                Some((end - len, end))
+            } else {
+                None
+            });
+            types.push(t);
+        }
+        let fits = matches!(
+            types[..],
+            [] | [TK_ID | TK_INTEGER | TK_FLOAT | TK_ON | TK_DELETE | TK_DEFAULT]
+                | [TK_PLUS | TK_MINUS, TK_INTEGER | TK_FLOAT]
+        );
+        if fits {
+            return Ok(false);
+        }
+        let mut raw = String::new();
+        for (i, (token_type, value)) in self.lookahead.drain(..types.len()).enumerate() {
+            let adjacent = i == 0
+                || matches!((spans[i - 1], spans[i]), (Some((_, end)), Some((start, _))) if end == start);
+            if !adjacent {
+                raw.push(' ');
+            }
+            if token_type == TK_BLOB {
+                raw.push_str("X'");
+                raw.push_str(&value);
+                raw.push('\'');
+            } else {
+                raw.push_str(&value);
+            }
+        }
+        self.lookahead.push_front((TK_STRING, raw));
+        self.warnings.push(Diagnostic {
+            position: Some((self.scanner.line(), self.scanner.column())),
+            ..Diagnostic::new("PRAGMA argument captured as raw text")
+        });
+        Ok(true)
+    }
+
+    // First-pass estimate of the number of rows of a `VALUES` clause:
+    // opening parentheses until the next `;` in the buffered input
+    fn estimate_rows(&mut self) -> usize {
+        let rest = self.scanner.input_mut().buffer();
+        let rest = &rest[..memchr(b';', rest).unwrap_or(rest.len())];
+        memchr_iter(b'(', rest).count()
+    }
+
+    // Count a parsed token against `max_tokens` and poll the cancel flag
+    fn check_budget(&mut self) -> Result<(), Error> {
+        self.tokens += 1;
+        if let Some(max) = self.options.max_tokens {
+            if self.tokens > max {
+                return Err(Error::BudgetExceeded(max, None));
+            }
+        }
+        if let Some(ref flag) = self.cancel {
+            self.cancel_countdown -= 1;
+            if self.cancel_countdown == 0 {
+                self.cancel_countdown = CANCEL_POLL_INTERVAL;
+                if flag.load(Ordering::Relaxed) {
+                    return Err(Error::Cancelled(None));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Count a statement against `max_statements`
+    fn count_statement(&mut self) -> Result<(), Error> {
+        self.statements += 1;
+        match self.options.max_statements {
+            Some(max) if self.statements > max => Err(Error::TooManyStatements(max, None)),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_identifier(options: &ParserOptions, value: &[u8]) -> Result<(), Error> {
+        if let Some(max) = options.max_identifier_length {
+            let id = match value {
+                [b'"', id @ .., b'"'] | [b'`', id @ .., b'`'] | [b'[', id @ .., b']'] => id,
+                id => id,
+            };
+            let id = from_bytes(id);
+            if id.chars().count() > max {
+                return Err(Error::IdentifierTooLong(id, max, None));
+            }
+        }
+        Ok(())
+    }
 }
 
+#[cfg(feature = "std")]
 macro_rules! try_with_position {
     ($scanner:expr, $expr:expr) => {
         match $expr {
             Ok(val) => val,
             Err(err) => {
                 let mut err = Error::from(err);
-                err.position($scanner.line(), $scanner.column());
+                err.position($scanner.line(), $scanner.column(), $scanner.offset());
                 return Err(err);
             }
         }
     };
 }
 
+#[cfg(feature = "std")]
 impl<I: Input> FallibleIterator for Parser<I> {
     type Item = Cmd;
     type Error = Error;
@@ -164,21 +453,38 @@ impl<I: Input> FallibleIterator for Parser<I> {
     fn next(&mut self) -> Result<Option<Cmd>, Error> {
         //print!("line: {}, column: {}: ", self.scanner.line(), self.scanner.column());
         self.parser.ctx.reset();
+        self.expected.clear();
         let mut last_token_parsed = TK_EOF;
+        let mut pragma = false;
+        let mut raw_pragma = false;
         let mut eof = false;
+        // whether the first token of the statement has been scanned
+        let mut started = false;
+        // offset of the first token, lines spanned by the first token
+        let mut start: Option<(usize, u64)> = None;
         loop {
+            try_with_position!(self.scanner, self.check_budget());
             let lookahead = self.lookahead.pop_front();
             let (value, mut token_type) = if let Some((token_type, ref value)) = lookahead {
                 (value.as_bytes(), token_type)
             } else {
-                match self.scanner.scan()? {
+                match self.scanner.scan_spanned()? {
                     None => {
                         eof = true;
                         break;
                     }
-                    Some(tuple) => tuple,
+                    Some((value, token_type, span)) => {
+                        if !started {
+                            let newlines = value.iter().filter(|b| **b == b'\n').count();
+                            start = Some((span.token.start, newlines as u64));
+                        }
+                        (value, token_type)
+                    }
                 }
             };
+            if token_type == TK_ID {
+                try_with_position!(self.scanner, Self::check_identifier(&self.options, value));
+            }
             let token = if token_type >= TK_WINDOW {
                 debug_assert!(
                     token_type == TK_OVER || token_type == TK_FILTER || token_type == TK_WINDOW
@@ -198,8 +504,30 @@ impl<I: Input> FallibleIterator for Parser<I> {
             } else {
                 token_type.to_token(value)
             };
+            if token_type == TK_VALUES {
+                self.parser.ctx.values_capacity = self.estimate_rows();
+            }
             //print!("({:?}, {:?})", token_type, token);
             try_with_position!(self.scanner, self.parser.sqlite3Parser(token_type, token));
+            self.collect_warnings();
+            if let Some((offset, newlines)) = start.take() {
+                self.range = (offset..offset, self.scanner.line() - newlines);
+                started = true;
+                if token_type != TK_SEMI {
+                    try_with_position!(self.scanner, self.count_statement());
+                }
+            }
+            if token_type == TK_SEMI && !self.parser.ctx.done() {
+                started = false; // empty statement
+            }
+            if token_type == TK_PRAGMA {
+                pragma = matches!(last_token_parsed, TK_EOF | TK_SEMI | TK_EXPLAIN | TK_PLAN);
+            } else if pragma && (token_type == TK_EQ || token_type == TK_LP) {
+                pragma = false;
+                if self.options.lenient_pragma {
+                    raw_pragma = self.raw_pragma_argument(token_type == TK_LP)?;
+                }
+            }
             last_token_parsed = token_type;
             if self.parser.ctx.done() {
                 //println!();
@@ -207,6 +535,7 @@ impl<I: Input> FallibleIterator for Parser<I> {
             }
         }
         self.lookahead.clear();
+        self.range.0.end = self.scanner.token_end();
         if last_token_parsed == TK_EOF {
             return Ok(None); // empty input
         }
@@ -217,27 +546,140 @@ impl<I: Input> FallibleIterator for Parser<I> {
                 try_with_position!(self.scanner, self.parser.sqlite3Parser(TK_SEMI, None));
             }
             try_with_position!(self.scanner, self.parser.sqlite3Parser(TK_EOF, None));
+            self.collect_warnings();
         }
         self.parser.sqlite3ParserFinalize();
         if let Some(msg) = self.parser.ctx.error() {
+            self.expected = self
+                .parser
+                .ctx
+                .take_expected()
+                .into_iter()
+                .filter_map(TokenType::from_code)
+                .collect();
             let mut err = Error::SyntaxError(msg, None);
-            err.position(self.scanner.line(), self.scanner.column());
+            err.position(
+                self.scanner.line(),
+                self.scanner.column(),
+                self.scanner.offset(),
+            );
             return Err(err);
         }
-        let cmd = self.parser.ctx.cmd();
-        assert_ne!(cmd, None);
+        let mut cmd = self.parser.ctx.cmd();
+        if raw_pragma {
+            if let Some(
+                Cmd::Stmt(Stmt::Pragma(_, Some(body)))
+                | Cmd::Explain(Stmt::Pragma(_, Some(body)))
+                | Cmd::ExplainQueryPlan(Stmt::Pragma(_, Some(body))),
+            ) = &mut cmd
+            {
+                // the raw text has been parsed as a name
+                match body {
+                    PragmaBody::Equals(Expr::Name(Name(raw))) => {
+                        *body = PragmaBody::RawEquals(core::mem::take(raw));
+                    }
+                    PragmaBody::Call(Expr::Name(Name(raw))) => {
+                        *body = PragmaBody::RawCall(core::mem::take(raw));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        // only empty statements (`;`) until the end of input
+        assert!(cmd.is_some() || eof);
         Ok(cmd)
     }
 }
 
+/// Token text (as returned by the [`Tokenizer`]) and type.
+///
+/// Synthetic tokens can be built directly: `(b"LIMIT", TK_LIMIT)`.
 pub type Token<'input> = (&'input [u8], TokenType);
 
+/// SQL text of `tokens` separated by a single space, which can be parsed
+/// back: for example, tokens of a statement with tokens spliced in.
+/// Like the tokenizer returns them, the text of a `TK_BLOB` token is the
+/// hexadecimal digits only and the text of a `?NNN` `TK_VARIABLE` is `NNN`.
+pub fn tokens_to_sql<'input, T: IntoIterator<Item = Token<'input>>>(tokens: T) -> String {
+    let mut sql = String::new();
+    for (text, token_type) in tokens {
+        if !sql.is_empty() {
+            sql.push(' ');
+        }
+        let text = String::from_utf8_lossy(text);
+        match token_type {
+            TK_BLOB => {
+                sql.push_str("X'");
+                sql.push_str(&text);
+                sql.push('\'');
+            }
+            TK_VARIABLE if !text.starts_with(['$', '@', '#', ':']) => {
+                sql.push('?');
+                sql.push_str(&text);
+            }
+            _ => sql.push_str(&text),
+        }
+    }
+    sql
+}
+
+/// Lexer options
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LexerOptions {
+    /// Allow nested block comments like `/* /* */ */`
+    /// (not supported by SQLite: the first `*/` terminates the comment)
+    pub nested_block_comments: bool,
+    /// Reject with [`Error::UnrecognizedToken`] a `%` which does not follow an
+    /// operand (like the `%s` / `%(name)s` placeholders of templating tools
+    /// left in the SQL): `a % 2` is a modulo but `a = %s` is rejected.
+    /// By default, `%` is always the modulo operator.
+    pub reject_bare_percent: bool,
+}
+
 #[derive(Default)]
-pub struct Tokenizer {}
+pub struct Tokenizer {
+    options: LexerOptions,
+    /// Type of the last token (see `LexerOptions::reject_bare_percent`)
+    last: Option<TokenType>,
+    comments: Option<LeadingComments>,
+    /// Number of nested block comments skipped (and not yet reported)
+    nested_comments: usize,
+    /// Number of bytes of the current (incomplete) token already examined
+    /// when more data has been requested
+    resume: usize,
+}
 
 impl Tokenizer {
     pub fn new() -> Tokenizer {
-        Tokenizer {}
+        Tokenizer::default()
+    }
+
+    pub fn with_options(options: LexerOptions) -> Tokenizer {
+        Tokenizer {
+            options,
+            last: None,
+            comments: None,
+            nested_comments: 0,
+            resume: 0,
+        }
+    }
+
+    pub fn options(&self) -> &LexerOptions {
+        &self.options
+    }
+
+    /// Start collecting the `--` comments preceding each statement
+    fn collect_comments(&mut self) {
+        if self.comments.is_none() {
+            self.comments = Some(LeadingComments::new());
+        }
+    }
+
+    /// Consume the comments preceding the current statement
+    fn take_leading_comments(&mut self) -> Vec<String> {
+        self.comments
+            .as_mut()
+            .map_or_else(Vec::new, |c| core::mem::take(&mut c.leading))
     }
 }
 
@@ -261,6 +703,62 @@ impl Splitter for Tokenizer {
         data: &'input [u8],
         eof: bool,
     ) -> Result<(Option<Token<'input>>, usize), Error> {
+        let (token, amt) = self.next_token(data, eof)?;
+        if let Some((_, token_type)) = token {
+            if token_type == TK_REM
+                && self.options.reject_bare_percent
+                && !self.last.is_some_and(ends_operand)
+            {
+                return Err(Error::UnrecognizedToken(None));
+            }
+            self.last = Some(token_type);
+        }
+        if let Some(ref mut comments) = self.comments {
+            match token {
+                Some((_, token_type)) => comments.token(token_type),
+                None => comments.skipped(&data[..amt]),
+            }
+        }
+        Ok((token, amt))
+    }
+
+    fn reset(&mut self) {
+        self.resume = 0;
+        self.last = None;
+    }
+}
+
+// Whether a token of type `ty` may end an operand
+// (such that a following `%` is a modulo)
+fn ends_operand(ty: TokenType) -> bool {
+    matches!(
+        ty,
+        TK_ID
+            | TK_STRING
+            | TK_INTEGER
+            | TK_FLOAT
+            | TK_BLOB
+            | TK_VARIABLE
+            | TK_RP
+            | TK_NULL
+            | TK_CTIME_KW
+            | TK_END
+            | TK_JOIN_KW
+            | TK_WINDOW
+            | TK_OVER
+            | TK_FILTER
+    ) || yyParser::parse_fallback(ty as YYCODETYPE) == TK_ID as YYCODETYPE
+}
+
+impl Tokenizer {
+    fn next_token<'input>(
+        &mut self,
+        data: &'input [u8],
+        eof: bool,
+    ) -> Result<(Option<Token<'input>>, usize), Error> {
+        // `data` starts with the same bytes as in the previous call
+        // when it has requested more data
+        let resume = core::mem::take(&mut self.resume);
         if eof && data.is_empty() {
             return Ok((None, 0));
         }
@@ -279,11 +777,13 @@ impl Splitter for Tokenizer {
                 if let Some(b) = data.get(1) {
                     if *b == b'-' {
                         // eat comment
-                        if let Some(i) = memchr(b'\n', data) {
-                            return Ok((None, i + 1));
+                        let from = resume.max(2);
+                        if let Some(i) = memchr(b'\n', &data[from..]) {
+                            return Ok((None, from + i + 1));
                         } else if eof {
                             return Ok((None, data.len()));
                         } // else ask more data until '\n'
+                        self.resume = data.len();
                     } else if *b == b'>' {
                         if let Some(b) = data.get(2) {
                             if *b == b'>' {
@@ -307,20 +807,21 @@ impl Splitter for Tokenizer {
                 if let Some(b) = data.get(1) {
                     if *b == b'*' {
                         // eat comment
-                        let mut pb = 0;
-                        let mut end = None;
-                        for (i, b) in data.iter().enumerate().skip(2) {
-                            if *b == b'/' && pb == b'*' {
-                                end = Some(i);
-                                break;
+                        let nested = self.options.nested_block_comments;
+                        if let Some(i) = block_comment_end(data, nested, resume) {
+                            if self.options.nested_block_comments
+                                && data[2..i - 1].windows(2).any(|w| w == b"/*")
+                            {
+                                self.nested_comments += 1;
                             }
-                            pb = *b;
-                        }
-                        if let Some(i) = end {
                             return Ok((None, i + 1));
                         } else if eof {
                             return Err(Error::UnterminatedBlockComment(None));
                         } // else ask more data until '*/'
+                        if !nested {
+                            // the last byte may be the '*' of '*/'
+                            self.resume = data.len() - 1;
+                        }
                     } else {
                         return Ok((Some((&data[..1], TK_SLASH)), 1));
                     }
@@ -388,7 +889,9 @@ impl Splitter for Tokenizer {
             b',' => return Ok((Some((&data[..1], TK_COMMA)), 1)),
             b'&' => return Ok((Some((&data[..1], TK_BITAND)), 1)),
             b'~' => return Ok((Some((&data[..1], TK_BITNOT)), 1)),
-            quote @ b'`' | quote @ b'\'' | quote @ b'"' => return literal(data, eof, quote),
+            quote @ b'`' | quote @ b'\'' | quote @ b'"' => {
+                return literal(data, eof, quote, resume, &mut self.resume)
+            }
             b'.' => {
                 if let Some(b) = data.get(1) {
                     if b.is_ascii_digit() {
@@ -402,12 +905,14 @@ impl Splitter for Tokenizer {
             }
             b'0'..=b'9' => return number(data, eof),
             b'[' => {
-                if let Some(i) = memchr(b']', data) {
+                let from = resume.max(1);
+                if let Some(i) = memchr(b']', &data[from..]).map(|i| from + i) {
                     // Keep original quotes / '[' ... ’]'
                     return Ok((Some((&data[0..i + 1], TK_ID)), i + 1));
                 } else if eof {
                     return Err(Error::UnterminatedBracket(None));
                 } // else ask more data until ']'
+                self.resume = data.len();
             }
             b'?' => {
                 match data.iter().skip(1).position(|&b| !b.is_ascii_digit()) {
@@ -448,7 +953,7 @@ impl Splitter for Tokenizer {
             b if is_identifier_start(b) => {
                 return if b == b'x' || b == b'X' {
                     if let Some(&b'\'') = data.get(1) {
-                        blob_literal(data, eof)
+                        blob_literal(data, eof, resume, &mut self.resume)
                     } else {
                         Ok(self.identifierish(data, eof))
                     }
@@ -463,13 +968,106 @@ impl Splitter for Tokenizer {
     }
 }
 
-fn literal(data: &[u8], eof: bool, quote: u8) -> Result<(Option<Token<'_>>, usize), Error> {
+/// Contiguous block of `--` comments directly preceding a statement
+#[derive(Default)]
+struct LeadingComments {
+    /// `true` when only whitespaces have been seen since the last line break
+    line_start: bool,
+    /// `true` when the next token starts a new statement
+    stmt_start: bool,
+    /// comments seen since the last token
+    pending: Vec<String>,
+    /// comments preceding the current statement
+    leading: Vec<String>,
+}
+
+impl LeadingComments {
+    fn new() -> LeadingComments {
+        LeadingComments {
+            line_start: true,
+            stmt_start: true,
+            ..LeadingComments::default()
+        }
+    }
+
+    fn token(&mut self, token_type: TokenType) {
+        if self.stmt_start {
+            self.leading = core::mem::take(&mut self.pending);
+        } else {
+            self.pending.clear();
+        }
+        self.line_start = false;
+        self.stmt_start = token_type == TK_SEMI;
+    }
+
+    /// `skipped` is either some spaces or a comment
+    fn skipped(&mut self, skipped: &[u8]) {
+        match skipped {
+            [b'-', b'-', comment @ ..] => {
+                if self.line_start {
+                    self.pending
+                        .push(String::from_utf8_lossy(comment).trim().to_owned());
+                } else {
+                    // comment at the end of a line with some tokens
+                    self.pending.clear();
+                }
+                self.line_start = true;
+            }
+            [b'/', b'*', ..] => {
+                self.pending.clear();
+                self.line_start = false;
+            }
+            _ => {
+                for _ in skipped.iter().filter(|&&b| b == b'\n') {
+                    if self.line_start {
+                        // blank line
+                        self.pending.clear();
+                    }
+                    self.line_start = true;
+                }
+            }
+        }
+    }
+}
+
+/// Index of the `/` closing the block comment starting at `data[0]`
+/// (`data[..from]` is known to not contain the end of a non-nested comment)
+fn block_comment_end(data: &[u8], nested: bool, from: usize) -> Option<usize> {
+    debug_assert!(data.starts_with(b"/*"));
+    let mut depth = 1;
+    let mut i = if nested { 2 } else { from.max(2) };
+    while i + 1 < data.len() {
+        if data[i] == b'*' && data[i + 1] == b'/' {
+            depth -= 1;
+            if !nested || depth == 0 {
+                return Some(i + 1);
+            }
+            i += 2;
+        } else if nested && data[i] == b'/' && data[i + 1] == b'*' {
+            depth += 1;
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// `data[..from]` has already been examined (without reaching the closing quote),
+/// `resume` is set when more data is requested.
+fn literal<'input>(
+    data: &'input [u8],
+    eof: bool,
+    quote: u8,
+    from: usize,
+    resume: &mut usize,
+) -> Result<(Option<Token<'input>>, usize), Error> {
     debug_assert_eq!(data[0], quote);
     let tt = if quote == b'\'' { TK_STRING } else { TK_ID };
     let mut pb = 0;
     let mut end = None;
     // data[0] == quote => skip(1)
-    for (i, b) in data.iter().enumerate().skip(1) {
+    for (i, b) in data.iter().enumerate().skip(from.max(1)) {
         if *b == quote {
             if pb == quote {
                 // escaped quote
@@ -493,16 +1091,27 @@ fn literal(data: &[u8], eof: bool, quote: u8) -> Result<(Option<Token<'_>>, usiz
         return Err(Error::UnterminatedLiteral(None));
     }
     // else ask more data until closing quote
+    // (a trailing quote may be the first half of an escaped quote)
+    *resume = if pb == quote {
+        data.len() - 1
+    } else {
+        data.len()
+    };
     Ok((None, 0))
 }
 
-fn blob_literal(data: &[u8], eof: bool) -> Result<(Option<Token<'_>>, usize), Error> {
+fn blob_literal<'input>(
+    data: &'input [u8],
+    eof: bool,
+    from: usize,
+    resume: &mut usize,
+) -> Result<(Option<Token<'input>>, usize), Error> {
     debug_assert!(data[0] == b'x' || data[0] == b'X');
     debug_assert_eq!(data[1], b'\'');
     if let Some((i, b)) = data
         .iter()
         .enumerate()
-        .skip(2)
+        .skip(from.max(2))
         .find(|&(_, &b)| !b.is_ascii_hexdigit())
     {
         if *b != b'\'' || i % 2 != 0 {
@@ -513,6 +1122,7 @@ fn blob_literal(data: &[u8], eof: bool) -> Result<(Option<Token<'_>>, usize), Er
         return Err(Error::MalformedBlobLiteral(None));
     }
     // else ask more data
+    *resume = data.len();
     Ok((None, 0))
 }
 
@@ -655,11 +1265,11 @@ impl Tokenizer {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
-    use super::Tokenizer;
+    use super::{Error, LexerOptions, Tokenizer};
     use crate::dialect::TokenType;
-    use crate::lexer::Scanner;
+    use crate::lexer::{PushInput, Scanned, Scanner};
 
     #[test]
     fn fallible_iterator() {
@@ -673,4 +1283,263 @@ mod tests {
         assert_eq!("parser_trace".as_bytes(), token2);
         assert_eq!(TokenType::TK_ID, token_type2);
     }
+
+    #[test]
+    fn push_input() {
+        fn would_block(s: &mut Scanner<PushInput, Tokenizer>) -> bool {
+            matches!(s.scan(), Err(Error::Io(ref e)) if e.kind() == std::io::ErrorKind::WouldBlock)
+        }
+        let mut s = Scanner::new(PushInput::new(), Tokenizer::new());
+        assert!(would_block(&mut s));
+        s.input_mut().push_str("SEL");
+        // partial keyword
+        assert!(would_block(&mut s));
+        s.input_mut().push_str("ECT 'a\nb");
+        assert_eq!(
+            s.scan().unwrap().unwrap(),
+            (&b"SELECT"[..], TokenType::TK_SELECT)
+        );
+        // partial string literal
+        assert!(would_block(&mut s));
+        s.input_mut().push_str("c' FROM t;");
+        assert_eq!(
+            s.scan().unwrap().unwrap(),
+            (&b"'a\nbc'"[..], TokenType::TK_STRING)
+        );
+        assert_eq!(
+            s.scan().unwrap().unwrap(),
+            (&b"FROM"[..], TokenType::TK_FROM)
+        );
+        assert_eq!(s.scan().unwrap().unwrap(), (&b"t"[..], TokenType::TK_ID));
+        assert_eq!(s.scan().unwrap().unwrap(), (&b";"[..], TokenType::TK_SEMI));
+        assert!(would_block(&mut s));
+        s.input_mut().finish();
+        assert_eq!(s.scan().unwrap(), None);
+        assert_eq!((s.line(), s.column()), (2, 12));
+    }
+
+    #[test]
+    fn crlf() {
+        fn positions(sql: &str, crlf: bool) -> Vec<(u64, usize)> {
+            let mut s = Scanner::new(sql.as_bytes(), Tokenizer::new());
+            s.crlf(crlf);
+            let mut positions = vec![];
+            while s.scan().unwrap().is_some() {
+                positions.push((s.line(), s.column()));
+            }
+            positions
+        }
+        let sql = "SELECT a,\r\n  b -- c\r\nFROM t\r";
+        assert_eq!(
+            positions(sql, true),
+            [(1, 7), (1, 9), (1, 10), (2, 4), (3, 5), (3, 7)]
+        );
+        assert_eq!(positions(sql, false), positions(sql, true));
+        // position just after a `\r`
+        let mut s = Scanner::new(sql.as_bytes(), Tokenizer::new());
+        s.crlf(true);
+        while s.scan().unwrap().is_some() {}
+        assert_eq!((s.line(), s.column()), (3, 7));
+        let mut s = Scanner::new(sql.as_bytes(), Tokenizer::new());
+        while s.scan().unwrap().is_some() {}
+        assert_eq!((s.line(), s.column()), (3, 8));
+        // lone `\r`
+        let sql = "SELECT\r1\r\n2";
+        assert_eq!(positions(sql, true), [(1, 7), (1, 9), (2, 2)]);
+    }
+
+    #[test]
+    fn scan_spanned() {
+        let sql = "SELECT  1 -- one\n,\n\n/* two */ x'02' ; ";
+        let mut s = Scanner::new(sql.as_bytes(), Tokenizer::new());
+        let mut spans = vec![];
+        while let Some((_, token_type, span)) = s.scan_spanned().unwrap() {
+            spans.push((token_type, &sql[span.trivia], &sql[span.token]));
+        }
+        assert_eq!(
+            spans,
+            [
+                (TokenType::TK_SELECT, "", "SELECT"),
+                (TokenType::TK_INTEGER, "  ", "1"),
+                (TokenType::TK_COMMA, " -- one\n", ","),
+                (TokenType::TK_BLOB, "\n\n/* two */ ", "x'02'"),
+                (TokenType::TK_SEMI, " ", ";"),
+            ]
+        );
+        // trivia split across reads
+        let mut s = Scanner::new(PushInput::new(), Tokenizer::new());
+        s.input_mut().push_str("SELECT  ");
+        assert!(s.scan_spanned().unwrap().is_some());
+        assert!(s.scan_spanned().is_err()); // would block
+        s.input_mut().push_str(" \n 1");
+        s.input_mut().finish();
+        let (_, _, span) = s.scan_spanned().unwrap().unwrap();
+        assert_eq!(span.trivia, 6..11);
+        assert_eq!(span.token, 11..12);
+    }
+
+    #[test]
+    fn resume_split() {
+        fn tokens<I: crate::lexer::Input>(
+            s: &mut Scanner<I, Tokenizer>,
+        ) -> Vec<(Vec<u8>, TokenType)> {
+            let mut tokens = vec![];
+            loop {
+                match s.scan() {
+                    Ok(Some((token, token_type))) => tokens.push((token.to_vec(), token_type)),
+                    Ok(None) => return tokens,
+                    Err(e) => panic!("unexpected error: {}", e),
+                }
+            }
+        }
+        for sql in [
+            "SELECT 'it''s', \"a\"\"b\", `c`, x'0A1b' -- comment\n/* a * / b **/ [d e];",
+            "SELECT '''', ''''''",
+            "SELECT 1 /***/",
+        ] {
+            let expected = tokens(&mut Scanner::new(sql.as_bytes(), Tokenizer::new()));
+            // feed one byte at a time
+            let mut s = Scanner::new(PushInput::new(), Tokenizer::new());
+            let mut actual = vec![];
+            for i in 0..sql.len() {
+                s.input_mut().push_str(&sql[i..=i]);
+                while let Ok(Some((token, token_type))) = s.scan() {
+                    actual.push((token.to_vec(), token_type));
+                }
+            }
+            s.input_mut().finish();
+            actual.extend(tokens(&mut s));
+            assert_eq!(actual, expected, "{}", sql);
+        }
+    }
+
+    #[test]
+    fn block_comments() {
+        let input = "/* a /* b */ SELECT */ 1".as_bytes();
+        let mut s = Scanner::new(input, Tokenizer::new());
+        let (_, token_type) = s.scan().unwrap().unwrap();
+        assert_eq!(TokenType::TK_SELECT, token_type);
+
+        let options = LexerOptions {
+            nested_block_comments: true,
+            ..LexerOptions::default()
+        };
+        let mut s = Scanner::new(input, Tokenizer::with_options(options));
+        let (token, token_type) = s.scan().unwrap().unwrap();
+        assert_eq!(TokenType::TK_INTEGER, token_type);
+        assert_eq!(b"1", token);
+    }
+
+    #[test]
+    fn unterminated_nested_block_comment() {
+        let input = "/* a /* b */ SELECT".as_bytes();
+        let mut s = Scanner::new(input, Tokenizer::new());
+        let (_, token_type) = s.scan().unwrap().unwrap();
+        assert_eq!(TokenType::TK_SELECT, token_type);
+
+        let options = LexerOptions {
+            nested_block_comments: true,
+            ..LexerOptions::default()
+        };
+        let mut s = Scanner::new(input, Tokenizer::with_options(options));
+        assert!(matches!(
+            s.scan(),
+            Err(Error::UnterminatedBlockComment(Some(_)))
+        ));
+    }
+
+    #[test]
+    fn scan_recovering() {
+        let sql = "SELECT 'abc FROM t; SELECT 1";
+        let mut s = Scanner::new(sql.as_bytes(), Tokenizer::new());
+        let mut tokens = vec![];
+        while let Some((scanned, span)) = s.scan_recovering().unwrap() {
+            tokens.push(match scanned {
+                Scanned::Token(_, token_type) => (Some(token_type), &sql[span.token]),
+                Scanned::Error(bytes, e) => {
+                    assert_eq!(bytes, sql[span.token.clone()].as_bytes());
+                    assert!(matches!(
+                        e,
+                        Error::UnterminatedLiteral(Some(_)) | Error::BadNumber(Some(_))
+                    ));
+                    (None, &sql[span.token])
+                }
+            });
+        }
+        assert_eq!(
+            tokens,
+            [
+                (Some(TokenType::TK_SELECT), "SELECT"),
+                (None, "'"), // unterminated string
+                (Some(TokenType::TK_ID), "abc"),
+                (Some(TokenType::TK_FROM), "FROM"),
+                (Some(TokenType::TK_ID), "t"),
+                (Some(TokenType::TK_SEMI), ";"),
+                (Some(TokenType::TK_SELECT), "SELECT"),
+                (Some(TokenType::TK_INTEGER), "1"),
+            ]
+        );
+
+        let sql = "SELECT 1x, 'a";
+        let mut s = Scanner::new(sql.as_bytes(), Tokenizer::new());
+        let mut tokens = vec![];
+        while let Some((scanned, span)) = s.scan_recovering().unwrap() {
+            tokens.push((matches!(scanned, Scanned::Error(..)), &sql[span.token]));
+        }
+        assert_eq!(
+            tokens,
+            [
+                (false, "SELECT"),
+                (true, "1"), // bad number
+                (false, "x"),
+                (false, ","),
+                (true, "'"),
+                (false, "a"),
+            ]
+        );
+        // the default scan stops
+        let mut s = Scanner::new(sql.as_bytes(), Tokenizer::new());
+        s.scan().unwrap();
+        assert!(matches!(s.scan(), Err(Error::BadNumber(Some(_)))));
+    }
+}
+
+/// Smoke tests which do not depend on `std`
+#[cfg(test)]
+mod no_std_tests {
+    use super::{split_statements, Splitter, TokenType, Tokenizer};
+    use crate::ast::{Expr, Id, Literal, Operator};
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn split() {
+        let mut tokenizer = Tokenizer::new();
+        let mut input = "SELECT 1 -- comment\n;".as_bytes();
+        let mut tokens = Vec::new();
+        while let (token, amt @ 1..) = tokenizer.split(input, true).unwrap() {
+            tokens.extend(token.map(|(_, token_type)| token_type));
+            input = &input[amt..];
+        }
+        assert_eq!(
+            tokens,
+            [
+                TokenType::TK_SELECT,
+                TokenType::TK_INTEGER,
+                TokenType::TK_SEMI
+            ]
+        );
+        assert_eq!(split_statements("SELECT 1; SELECT 2;").count(), 2);
+    }
+
+    #[test]
+    fn render() {
+        let expr = Expr::Binary(
+            Box::new(Expr::Id(Id("a".to_string()))),
+            Operator::Equals,
+            Box::new(Expr::Literal(Literal::Numeric("1".to_string()))),
+        );
+        assert_eq!(expr.to_string(), "a = 1");
+    }
 }
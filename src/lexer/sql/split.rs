@@ -0,0 +1,123 @@
+//! Split a script into statements
+//! (adaptation of [`sqlite3_complete`](http://www.sqlite.org/src/artifact?ci=trunk&filename=src/complete.c))
+use super::{TokenType, TokenType::*, Tokenizer};
+use crate::lexer::scan::Splitter;
+
+/*
+** Token types used by the state machine:
+**
+**   0 SEMI      A semicolon.
+**   1 OTHER     Any other SQL token.
+**   2 EXPLAIN   The "explain" keyword.
+**   3 CREATE    The "create" keyword.
+**   4 TEMP      The "temp" or "temporary" keyword.
+**   5 TRIGGER   The "trigger" keyword.
+**   6 END       The "end" keyword.
+**
+** Whitespace and comments are skipped by the tokenizer.
+*/
+pub(super) fn token_class(token_type: TokenType) -> usize {
+    match token_type {
+        TK_SEMI => 0,
+        TK_EXPLAIN => 2,
+        TK_CREATE => 3,
+        TK_TEMP => 4,
+        TK_TRIGGER => 5,
+        TK_END => 6,
+        _ => 1,
+    }
+}
+
+/*
+** States:
+**
+**   0 INVALID   We have not yet seen a non-whitespace character.
+**   1 START     At the beginning or end of an SQL statement.
+**   2 NORMAL    We are in the middle of statement which ends with a single
+**               semicolon.
+**   3 EXPLAIN   The keyword EXPLAIN has been seen at the beginning of
+**               a statement.
+**   4 CREATE    The keyword CREATE has been seen at the beginning of a
+**               statement, possibly preceded by EXPLAIN and/or followed by
+**               TEMP or TEMPORARY
+**   5 TRIGGER   We are in the middle of a trigger definition that must be
+**               ended by a semicolon, the keyword END, and another semicolon.
+**   6 SEMI      We've seen the first semicolon in the ";END;" that occurs at
+**               the end of a trigger definition.
+**   7 END       We've seen the ";END" of the ";END;" that occurs at the end
+**               of a trigger definition.
+*/
+pub(super) const START: u8 = 1;
+#[rustfmt::skip]
+pub(super) static TRANS: [[u8; 7]; 8] = [
+                    /* SEMI  OTHER  EXPLAIN  CREATE  TEMP  TRIGGER  END */
+    /* 0 INVALID: */ [    1,     2,       3,      4,    2,       2,   2, ],
+    /* 1   START: */ [    1,     2,       3,      4,    2,       2,   2, ],
+    /* 2  NORMAL: */ [    1,     2,       2,      2,    2,       2,   2, ],
+    /* 3 EXPLAIN: */ [    1,     3,       2,      4,    2,       2,   2, ],
+    /* 4  CREATE: */ [    1,     2,       2,      2,    4,       5,   2, ],
+    /* 5 TRIGGER: */ [    6,     5,       5,      5,    5,       5,   5, ],
+    /* 6    SEMI: */ [    6,     5,       5,      5,    5,       5,   7, ],
+    /* 7     END: */ [    1,     5,       5,      5,    5,       5,   5, ],
+];
+
+/// Iterator over the statements of a script.
+///
+/// See [`split_statements`].
+pub struct SplitStatements<'input> {
+    sql: &'input str,
+    tokenizer: Tokenizer,
+}
+
+/// Split `sql` script into individual statements (without their terminating
+/// semicolon, leading/trailing whitespace and comments).
+///
+/// Semicolons inside literals, comments and `CREATE TRIGGER ... BEGIN ... END`
+/// bodies do not split. Empty statements are skipped.
+/// If the lexer fails (unterminated literal, ...), the rest of the script is
+/// returned as the last statement.
+pub fn split_statements(sql: &str) -> SplitStatements<'_> {
+    SplitStatements {
+        sql,
+        tokenizer: Tokenizer::new(),
+    }
+}
+
+impl<'input> Iterator for SplitStatements<'input> {
+    type Item = &'input str;
+
+    fn next(&mut self) -> Option<&'input str> {
+        let sql = self.sql;
+        let data = sql.as_bytes();
+        let mut state = 0;
+        let mut pos = 0;
+        let mut start = None;
+        let mut end = 0;
+        while pos < data.len() {
+            match self.tokenizer.split(&data[pos..], true) {
+                Err(_) => {
+                    self.sql = "";
+                    let start = start.unwrap_or(pos);
+                    return Some(sql[start..].trim_end());
+                }
+                Ok((None, 0)) => break,
+                Ok((None, amt)) => pos += amt,
+                Ok((Some((_, token_type)), amt)) => {
+                    state = TRANS[state as usize][token_class(token_type)];
+                    if token_type == TK_SEMI && state == START {
+                        if let Some(start) = start {
+                            self.sql = &sql[pos + amt..];
+                            return Some(&sql[start..end]);
+                        }
+                    } else {
+                        start.get_or_insert(pos);
+                        end = pos + amt;
+                    }
+                    pos += amt;
+                }
+            }
+        }
+        self.sql = "";
+        start.map(|start| &sql[start..end])
+    }
+}
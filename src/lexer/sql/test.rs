@@ -1,7 +1,18 @@
 use fallible_iterator::FallibleIterator;
 
-use super::{Error, Parser};
-use crate::parser::ast::{ParameterInfo, ToTokens};
+use super::{
+    comments, split_statements, token_diff, tokens_to_sql, Comment, CommentKind, DiffOp, Error,
+    LexerOptions, Parser, ParserOptions, Token, TokenType, Tokenizer,
+};
+use crate::dialect::{is_keyword, keyword_token, keywords};
+use crate::lexer::Scanner;
+use crate::parser::ast::{
+    canonicalize, lint_cartesian, propagate_rename, qualify_tables, Affinity, AlterTableBody, As,
+    Cmd, ColumnConstraint, CompareOptions, CreateTableBody, Diagnostic, Expr, FormatOptions, Id,
+    InsertBody, LikeOperator, Literal, Name, OneSelect, Operator, ParameterInfo, ParameterStyle,
+    PragmaBody, QuoteStyle, ResultColumn, SelectTable, Stmt, StmtMetrics, TableSchema, ToTokens,
+    UnaryOperator,
+};
 
 #[test]
 fn count_placeholders() {
@@ -55,3 +66,3055 @@ fn duplicate_column() {
         assert!(msg.contains("duplicate column name"));
     }
 }
+
+#[test]
+fn split_script() {
+    let sql = "SELECT ';'; -- comment ;\n;; /* ; */ SELECT 2 ;SELECT 3";
+    let stmts: Vec<_> = split_statements(sql).collect();
+    assert_eq!(stmts, vec!["SELECT ';'", "SELECT 2", "SELECT 3"]);
+}
+
+#[test]
+fn split_trigger() {
+    let sql = "CREATE TABLE t(x);
+CREATE TEMP TRIGGER tr AFTER INSERT ON t BEGIN
+  INSERT INTO log VALUES (new.x);
+  UPDATE t SET x = x + 1;
+END;
+SELECT * FROM t;";
+    let stmts: Vec<_> = split_statements(sql).collect();
+    assert_eq!(stmts.len(), 3);
+    assert_eq!(stmts[0], "CREATE TABLE t(x)");
+    assert!(stmts[1].starts_with("CREATE TEMP TRIGGER"));
+    assert!(stmts[1].ends_with("END"));
+    assert_eq!(stmts[2], "SELECT * FROM t");
+    for stmt in stmts {
+        let mut parser = Parser::new(stmt.as_bytes());
+        assert!(parser.next().unwrap().is_some());
+    }
+}
+
+fn syntax_error(sql: &str) -> String {
+    let mut parser = Parser::new(sql.as_bytes());
+    match parser.next().unwrap_err() {
+        Error::SyntaxError(msg, _) => msg,
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn syntax_error_near_token() {
+    let msg = syntax_error("SELECT 1 FROM t WHERE x = 1 1");
+    assert!(msg.starts_with("near \"1\": syntax error"), "{}", msg);
+    let msg = syntax_error("SELECT * FROM t ORDER x");
+    assert!(msg.starts_with("near \"x\": syntax error"), "{}", msg);
+    assert!(msg.contains("expected one of: BY"), "{}", msg);
+    let msg = syntax_error("CREATE TABLE t (x) SELECT");
+    assert!(msg.starts_with("near \"SELECT\": syntax error"), "{}", msg);
+    let msg = syntax_error("INSERT INTO t VALUES (1,)");
+    assert!(msg.starts_with("near \")\": syntax error"), "{}", msg);
+    assert!(msg.contains("expected one of: "), "{}", msg);
+}
+
+fn round_trip(sql: &str) {
+    let mut parser = Parser::new(sql.as_bytes());
+    let ast = parser.next().unwrap().unwrap();
+    assert_eq!(ast.to_string(), sql);
+}
+
+#[test]
+fn index_nulls_order() {
+    round_trip("CREATE INDEX i ON t (a DESC NULLS LAST);");
+    round_trip("CREATE UNIQUE INDEX i ON t (a NULLS FIRST, b ASC NULLS LAST);");
+    round_trip("CREATE INDEX i ON t (a COLLATE nocase DESC NULLS FIRST);");
+    round_trip("CREATE TABLE t (a, PRIMARY KEY (a ASC NULLS FIRST));");
+    round_trip("CREATE TABLE t (a, UNIQUE (a COLLATE binary NULLS LAST));");
+}
+
+#[test]
+fn partial_index() {
+    round_trip("CREATE INDEX i ON t (a) WHERE a IS NOT NULL;");
+    round_trip(
+        "CREATE UNIQUE INDEX IF NOT EXISTS main.i ON t (a, b DESC) WHERE b > 0 AND a IS NOT NULL;",
+    );
+}
+
+#[test]
+fn expression_index() {
+    round_trip("CREATE INDEX i ON t (lower (name));");
+    round_trip("CREATE INDEX i ON t (a, lower (name) COLLATE nocase DESC NULLS LAST, b + c);");
+}
+
+// `EXISTS` subquery of the `WHERE` clause of a `SELECT`
+fn exists_subquery(sql: &str) -> OneSelect {
+    let mut parser = Parser::new(sql.as_bytes());
+    let ast = parser.next().unwrap().unwrap();
+    if let Cmd::Stmt(Stmt::Select(select)) = ast {
+        if let OneSelect::Select {
+            where_clause: Some(Expr::Exists(subquery)),
+            ..
+        } = select.body.select
+        {
+            return subquery.body.select;
+        }
+    }
+    panic!("no EXISTS subquery in {}", sql);
+}
+
+#[test]
+fn correlated_subquery() {
+    let outer = [Name("t1".to_owned())];
+    let sub = exists_subquery("SELECT * FROM t1 WHERE EXISTS (SELECT 1 FROM t2 WHERE t2.a = t1.a)");
+    assert!(sub.is_correlated(&outer));
+    let sub = exists_subquery("SELECT * FROM t1 WHERE EXISTS (SELECT 1 FROM t2 WHERE t2.a = 1)");
+    assert!(!sub.is_correlated(&outer));
+    // shadowing alias
+    let sub =
+        exists_subquery("SELECT * FROM t1 WHERE EXISTS (SELECT 1 FROM t2 AS t1 WHERE t1.a = 1)");
+    assert!(!sub.is_correlated(&outer));
+    // nested subquery
+    let sub = exists_subquery(
+        "SELECT * FROM t1 WHERE EXISTS (SELECT 1 FROM t2 WHERE t2.a IN (SELECT b FROM t3 WHERE t3.c = T1.c))",
+    );
+    assert!(sub.is_correlated(&outer));
+    let sub = exists_subquery(
+        "SELECT * FROM t1 WHERE EXISTS (SELECT 1 FROM t2 WHERE t2.a IN (SELECT b FROM t3 WHERE t3.c = t2.c))",
+    );
+    assert!(!sub.is_correlated(&outer));
+}
+
+#[test]
+fn raise() {
+    round_trip("CREATE TRIGGER tr BEFORE DELETE ON t BEGIN\nSELECT RAISE (IGNORE);\nEND;");
+    round_trip("CREATE TRIGGER tr BEFORE DELETE ON t BEGIN\nSELECT RAISE (ABORT, 'msg');\nEND;");
+    round_trip("CREATE TRIGGER tr BEFORE DELETE ON t BEGIN\nSELECT RAISE (ROLLBACK, 'x');\nEND;");
+    round_trip(
+        "CREATE TRIGGER tr BEFORE DELETE ON t BEGIN\nSELECT RAISE (FAIL, 'y' || old.x);\nEND;",
+    );
+    let msg = syntax_error("SELECT RAISE(REPLACE, 'msg')");
+    assert!(msg.starts_with("near \"REPLACE\": syntax error"), "{}", msg);
+}
+
+// `WHERE` clause of a `SELECT`
+fn where_clause(sql: &str) -> Expr {
+    let mut parser = Parser::new(sql.as_bytes());
+    let ast = parser.next().unwrap().unwrap();
+    if let Cmd::Stmt(Stmt::Select(select)) = ast {
+        if let OneSelect::Select {
+            where_clause: Some(expr),
+            ..
+        } = select.body.select
+        {
+            return expr;
+        }
+    }
+    panic!("no WHERE clause in {}", sql);
+}
+
+#[test]
+fn like_escape() {
+    round_trip(r"SELECT * FROM t WHERE x LIKE '%a\%%' ESCAPE '\';");
+    round_trip(r"SELECT * FROM t WHERE x NOT LIKE p ESCAPE e AND y = 1;");
+
+    let expr = where_clause(r"SELECT * FROM t WHERE x LIKE '%a\%%' ESCAPE '\' AND y = 1");
+    match expr {
+        Expr::Binary(lhs, Operator::And, _) => {
+            assert!(matches!(
+                *lhs,
+                Expr::Like {
+                    not: false,
+                    escape: Some(_),
+                    ..
+                }
+            ))
+        }
+        expr => panic!("unexpected expression: {:?}", expr),
+    }
+    let expr = where_clause("SELECT * FROM t WHERE x NOT LIKE p ESCAPE e");
+    assert!(matches!(
+        expr,
+        Expr::Like {
+            not: true,
+            escape: Some(_),
+            ..
+        }
+    ));
+    let expr = where_clause("SELECT * FROM t WHERE x LIKE p AND y = 1");
+    match expr {
+        Expr::Binary(lhs, Operator::And, _) => {
+            assert!(matches!(*lhs, Expr::Like { escape: None, .. }))
+        }
+        expr => panic!("unexpected expression: {:?}", expr),
+    }
+}
+
+#[test]
+fn schema_qualified_names() {
+    for sql in [
+        "CREATE TABLE main.t (x);",
+        "CREATE TABLE IF NOT EXISTS aux.t AS SELECT 1;",
+        "DROP TABLE main.t;",
+        "CREATE VIRTUAL TABLE main.vt USING fts5 ();",
+        "CREATE INDEX main.i ON t (x);",
+        "DROP INDEX IF EXISTS main.i;",
+        "CREATE VIEW main.v AS SELECT 1;",
+        "DROP VIEW main.v;",
+        "CREATE TRIGGER main.tr AFTER INSERT ON t BEGIN\nSELECT 1;\nEND;",
+        "DROP TRIGGER IF EXISTS main.tr;",
+        "ALTER TABLE main.t RENAME TO u;",
+        "ALTER TABLE main.t ADD COLUMN y;",
+        "ALTER TABLE main.t DROP COLUMN y;",
+        "REINDEX main.i;",
+        "ANALYZE main.t;",
+    ] {
+        round_trip(sql);
+    }
+}
+
+fn parser_error(sql: &str) -> String {
+    let mut parser = Parser::new(sql.as_bytes());
+    match parser.next().unwrap_err() {
+        Error::ParserError(msg, _) => msg,
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn check_constraints() {
+    round_trip("CREATE TABLE t (a INTEGER, b INTEGER, CHECK (a < b AND b <= a * 2));");
+    round_trip("CREATE TABLE t (a TEXT CHECK (length (a) > 0 AND a GLOB '[a-z]*'));");
+    round_trip("CREATE TABLE t (a, CONSTRAINT c CHECK (coalesce (a, 0) IN (0, 1)));");
+    let msg = parser_error("CREATE TABLE t (a, CHECK (a IN (SELECT x FROM u)))");
+    assert!(msg.contains("subqueries prohibited in CHECK constraints"));
+    let msg = parser_error("CREATE TABLE t (a CHECK (abs ((SELECT 1)) > 0))");
+    assert!(msg.contains("subqueries prohibited in CHECK constraints"));
+    let msg = parser_error("CREATE TABLE t (a CHECK (EXISTS (SELECT 1)))");
+    assert!(msg.contains("subqueries prohibited in CHECK constraints"));
+    // in a window frame bound
+    let msg = parser_error(
+        "CREATE TABLE t (a CHECK (sum(a) OVER (ROWS BETWEEN (SELECT 1) PRECEDING AND 1 FOLLOWING) > 0))",
+    );
+    assert!(msg.contains("subqueries prohibited in CHECK constraints"));
+}
+
+#[test]
+fn max_identifier_length() {
+    let options = ParserOptions {
+        max_identifier_length: Some(8),
+        ..ParserOptions::default()
+    };
+    for sql in [
+        "SELECT abcdefgh FROM t",
+        "SELECT \"abcdefgh\" FROM t",
+        "SELECT [abcdefgh] FROM t",
+        "SELECT x AS `abcdefgh`",
+    ] {
+        let mut parser = Parser::with_options(sql.as_bytes(), options);
+        assert!(parser.next().unwrap().is_some(), "{}", sql);
+    }
+    for sql in [
+        "SELECT abcdefghi FROM t",
+        "SELECT \"abcdefghi\" FROM t",
+        "SELECT x FROM [abcdefghi]",
+        "SELECT x AS `abcdefghi`",
+    ] {
+        let mut parser = Parser::with_options(sql.as_bytes(), options);
+        match parser.next().unwrap_err() {
+            Error::IdentifierTooLong(id, max, Some(_)) => {
+                assert_eq!(id, "abcdefghi");
+                assert_eq!(max, 8);
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+    let mut parser = Parser::new("SELECT abcdefghi FROM t".as_bytes());
+    assert!(parser.next().unwrap().is_some());
+}
+
+#[test]
+fn double_quote_is_string() {
+    let strict = ParserOptions {
+        double_quote_is_string: false,
+        ..ParserOptions::default()
+    };
+    // always accepted as identifiers
+    for sql in [
+        "SELECT \"a\" FROM \"t\" WHERE \"b\" = 'x';",
+        "CREATE TABLE \"t\" (\"a\" DEFAULT 'x', b DEFAULT x);",
+    ] {
+        for options in [ParserOptions::default(), strict] {
+            let mut parser = Parser::with_options(sql.as_bytes(), options);
+            let cmd = parser.next().unwrap().unwrap();
+            assert_eq!(cmd.to_string(), sql);
+        }
+    }
+    // string literal spelled with double quotes
+    let sql = "CREATE TABLE t (a DEFAULT \"x\");";
+    let mut parser = Parser::new(sql.as_bytes());
+    assert_eq!(parser.next().unwrap().unwrap().to_string(), sql);
+    let mut parser = Parser::with_options(sql.as_bytes(), strict);
+    match parser.next().unwrap_err() {
+        Error::ParserError(msg, Some(_)) => {
+            assert_eq!(msg, "double-quoted string literal: \"x\"")
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn temporary() {
+    let options = FormatOptions {
+        temporary: true,
+        ..FormatOptions::default()
+    };
+    for sql in [
+        "CREATE TEMP TABLE t (x);",
+        "CREATE TEMPORARY TABLE t (x);",
+        "create temporary view v AS SELECT 1;",
+    ] {
+        let mut parser = Parser::new(sql.as_bytes());
+        let ast = parser.next().unwrap().unwrap();
+        match ast {
+            Cmd::Stmt(Stmt::CreateTable { temporary, .. })
+            | Cmd::Stmt(Stmt::CreateView { temporary, .. }) => assert!(temporary),
+            _ => panic!("unexpected statement: {}", ast),
+        }
+        let rendered = ast.to_string();
+        assert!(rendered.starts_with("CREATE TEMP "), "{}", rendered);
+        let rendered = ast.display_with(options).to_string();
+        assert!(rendered.starts_with("CREATE TEMPORARY "), "{}", rendered);
+    }
+    round_trip("CREATE TEMP TRIGGER tr AFTER INSERT ON t BEGIN\nSELECT 1;\nEND;");
+}
+
+fn metrics(sql: &str) -> StmtMetrics {
+    let mut parser = Parser::new(sql.as_bytes());
+    parser.next().unwrap().unwrap().metrics()
+}
+
+#[test]
+fn stmt_metrics() {
+    assert_eq!(
+        metrics("SELECT 1"),
+        StmtMetrics {
+            max_expr_depth: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        metrics(
+            "SELECT count(*), max(a), max(a, b) FROM t JOIN u ON t.id = u.id, v \
+             WHERE a + b * 2 > 0 GROUP BY c HAVING sum(d) > 1"
+        ),
+        StmtMetrics {
+            joins: 2,
+            subqueries: 0,
+            compound_selects: 0,
+            aggregate_functions: 3,
+            max_expr_depth: 4,
+        }
+    );
+    assert_eq!(
+        metrics(
+            "SELECT a FROM (SELECT a FROM t UNION SELECT a FROM u) \
+             WHERE a IN (SELECT x FROM w) OR EXISTS (SELECT 1 UNION ALL SELECT 2 EXCEPT SELECT 3)"
+        ),
+        StmtMetrics {
+            joins: 0,
+            subqueries: 3,
+            compound_selects: 3,
+            aggregate_functions: 0,
+            max_expr_depth: 3,
+        }
+    );
+    assert_eq!(
+        metrics("EXPLAIN DELETE FROM t WHERE x = (SELECT avg(y) FROM u)"),
+        StmtMetrics {
+            subqueries: 1,
+            aggregate_functions: 1,
+            max_expr_depth: 2,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn offset_requires_limit() {
+    // `OFFSET` parsed as an alias
+    assert_eq!(
+        syntax_error("SELECT * FROM t OFFSET 5"),
+        "OFFSET requires LIMIT"
+    );
+    assert_eq!(syntax_error("SELECT 1 OFFSET 5;"), "OFFSET requires LIMIT");
+    assert_eq!(
+        parser_error("SELECT * FROM t ORDER BY x OFFSET 5"),
+        "OFFSET requires LIMIT"
+    );
+    // nested queries
+    assert_eq!(
+        syntax_error("SELECT (SELECT a FROM t LIMIT 1) FROM u OFFSET 5"),
+        "OFFSET requires LIMIT"
+    );
+    assert_eq!(
+        syntax_error("SELECT * FROM t WHERE a IN (SELECT a FROM u OFFSET 1) LIMIT 2"),
+        "OFFSET requires LIMIT"
+    );
+    assert_eq!(
+        parser_error("SELECT * FROM t WHERE a IN (SELECT a FROM u ORDER BY a OFFSET 1)"),
+        "OFFSET requires LIMIT"
+    );
+    assert_eq!(
+        parser_error("SELECT * FROM t LIMIT 1 OFFSET (SELECT a FROM u ORDER BY a OFFSET 1)"),
+        "OFFSET requires LIMIT"
+    );
+    // not where `LIMIT` is unexpected
+    let msg = syntax_error("SELECT offset 5");
+    assert!(msg.starts_with("near \"5\": syntax error"), "{}", msg);
+    round_trip("SELECT * FROM t LIMIT 5;");
+    round_trip("SELECT * FROM t LIMIT 5 OFFSET 10;");
+    round_trip("SELECT * FROM t LIMIT (SELECT a FROM u LIMIT 1 OFFSET 1) OFFSET 2;");
+    round_trip("SELECT (SELECT a FROM t LIMIT 1 OFFSET 2) FROM u LIMIT 3 OFFSET 4;");
+    // alias
+    round_trip("SELECT 1 OFFSET;");
+    round_trip("SELECT * FROM t OFFSET WHERE OFFSET.a = 1;");
+}
+
+#[test]
+fn column_default() {
+    round_trip("CREATE TABLE t (a INTEGER DEFAULT - 1, b REAL DEFAULT + 3.14, c DEFAULT 0x10);");
+    round_trip("CREATE TABLE t (a DEFAULT NULL, b DEFAULT 'x', c DEFAULT X'00', d DEFAULT TRUE);");
+    round_trip("CREATE TABLE t (a DEFAULT CURRENT_TIMESTAMP, b DEFAULT (1 + 2), c DEFAULT (- a));");
+    let mut parser = Parser::new("CREATE TABLE t (a DEFAULT -1)".as_bytes());
+    let ast = parser.next().unwrap().unwrap();
+    match ast {
+        Cmd::Stmt(Stmt::CreateTable {
+            body: CreateTableBody::ColumnsAndConstraints { columns, .. },
+            ..
+        }) => assert!(matches!(
+            columns[0].constraints[0].constraint,
+            ColumnConstraint::Default(Expr::Unary(UnaryOperator::Negative, ref x))
+                if matches!(**x, Expr::Literal(Literal::Numeric(ref n)) if n == "1")
+        )),
+        _ => panic!("unexpected statement: {}", ast),
+    }
+    let msg = syntax_error("CREATE TABLE t (a DEFAULT 1 + 2)");
+    assert!(msg.starts_with("near \"+\": syntax error"), "{}", msg);
+    let msg = syntax_error("CREATE TABLE t (a DEFAULT abs(-1))");
+    assert!(msg.starts_with("near \"(\": syntax error"), "{}", msg);
+}
+
+#[test]
+fn with_dml() {
+    round_trip("WITH c AS (SELECT 1) INSERT INTO t SELECT * FROM c;");
+    round_trip("WITH c (a) AS MATERIALIZED (SELECT 1) INSERT INTO t (a) VALUES (1);");
+    round_trip("WITH c AS (SELECT 1) DELETE FROM t WHERE a IN c;");
+    round_trip("WITH RECURSIVE c AS (SELECT 1) UPDATE t SET a = 1 WHERE a IN (SELECT * FROM c);");
+}
+
+#[test]
+fn numeric_literal_spelling() {
+    round_trip("SELECT 0x10, 0XfF, 1e3, 1.5E-3, 2e+10, 1.0, 1.50, .5, 5., 007;");
+    round_trip("CREATE TABLE t (a DEFAULT 1.0, b DEFAULT - 1e3, c CHECK (c > 0x10));");
+    let expr = where_clause("SELECT 1 WHERE x = 1.500");
+    assert!(matches!(
+        expr,
+        Expr::Binary(_, Operator::Equals, ref rhs)
+            if matches!(**rhs, Expr::Literal(Literal::Numeric(ref n)) if n == "1.500")
+    ));
+}
+
+#[test]
+fn is_distinct_from() {
+    round_trip("SELECT a IS DISTINCT FROM b FROM t;");
+    round_trip("SELECT * FROM t WHERE a IS NOT DISTINCT FROM NULL;");
+    round_trip("SELECT a IS b, a IS NOT b FROM t;");
+    assert!(matches!(
+        where_clause("SELECT 1 WHERE a IS NOT DISTINCT FROM b"),
+        Expr::Binary(_, Operator::IsNotDistinctFrom, _)
+    ));
+    assert!(matches!(
+        where_clause("SELECT 1 WHERE a IS DISTINCT FROM b"),
+        Expr::Binary(_, Operator::IsDistinctFrom, _)
+    ));
+}
+
+#[test]
+fn json_operators() {
+    round_trip("SELECT data -> '$.a' FROM t;");
+    round_trip("SELECT data ->> 'b' FROM t WHERE data ->> '$.c' = 1;");
+    // same precedence as `||`, left associative
+    match where_clause("SELECT 1 WHERE data -> 'a' ->> 'b' = 1") {
+        Expr::Binary(lhs, Operator::Equals, _) => match *lhs {
+            Expr::Binary(lhs, Operator::ArrowRightShift, _) => {
+                assert!(matches!(*lhs, Expr::Binary(_, Operator::ArrowRight, _)))
+            }
+            e => panic!("unexpected expression: {:?}", e),
+        },
+        e => panic!("unexpected expression: {:?}", e),
+    }
+}
+
+/// Render `expr` with explicit parentheses around operators
+fn parenthesize(expr: &Expr) -> String {
+    match expr {
+        Expr::Binary(lhs, op, rhs) => format!(
+            "({} {} {})",
+            parenthesize(lhs),
+            op.display_with(FormatOptions::default()),
+            parenthesize(rhs)
+        ),
+        Expr::Collate(x, collation) => format!("({} COLLATE {})", parenthesize(x), collation),
+        Expr::Unary(op, x) => format!(
+            "({} {})",
+            op.display_with(FormatOptions::default()),
+            parenthesize(x)
+        ),
+        expr => expr.to_string(),
+    }
+}
+
+#[test]
+fn operator_precedence() {
+    for (sql, expected) in [
+        ("a << b >> c", "((a << b) >> c)"),
+        ("a & b | c", "((a & b) | c)"),
+        ("a | b & c", "((a | b) & c)"),
+        ("a | b << c", "((a | b) << c)"),
+        ("a + b << c", "((a + b) << c)"),
+        ("a << b + c", "(a << (b + c))"),
+        ("a < b << c", "(a < (b << c))"),
+        ("a % b * c", "((a % b) * c)"),
+        ("a + b % c", "(a + (b % c))"),
+        ("a || b * c", "((a || b) * c)"),
+        ("a || b || c", "((a || b) || c)"),
+        ("~a", "(~ a)"),
+        ("~a + b", "((~ a) + b)"),
+        ("~a || b", "((~ a) || b)"),
+        ("~~a", "(~ (~ a))"),
+        ("-a || b", "((- a) || b)"),
+        ("~a << b", "((~ a) << b)"),
+        ("NOT a = b", "(NOT (a = b))"),
+        ("a = b AND c OR d", "(((a = b) AND c) OR d)"),
+    ] {
+        let expr = where_clause(&format!("SELECT 1 WHERE {}", sql));
+        assert_eq!(parenthesize(&expr), expected, "{}", sql);
+    }
+}
+
+#[test]
+fn single_line() {
+    let options = FormatOptions {
+        single_line: true,
+        ..FormatOptions::default()
+    };
+    let sql = "CREATE TRIGGER tr\n  AFTER INSERT ON t\nBEGIN\n  INSERT INTO log\n    VALUES ('line 1\nline 2');\n  DELETE FROM t;\nEND";
+    let mut parser = Parser::new(sql.as_bytes());
+    let ast = parser.next().unwrap().unwrap();
+    assert_eq!(
+        ast.display_with(options).to_string(),
+        "CREATE TRIGGER tr AFTER INSERT ON t BEGIN INSERT INTO log VALUES ('line 1\nline 2'); DELETE FROM t; END;"
+    );
+    // default rendering is unchanged
+    assert_eq!(
+        ast.to_string(),
+        "CREATE TRIGGER tr AFTER INSERT ON t BEGIN\nINSERT INTO log VALUES ('line 1\nline 2');\nDELETE FROM t;\nEND;"
+    );
+}
+
+#[test]
+fn alter_table_rename_to() {
+    round_trip("ALTER TABLE old RENAME TO new;");
+    round_trip("ALTER TABLE main.old RENAME TO new;");
+    let mut parser = Parser::new("ALTER TABLE main.old RENAME TO new".as_bytes());
+    match parser.next().unwrap().unwrap() {
+        Cmd::Stmt(Stmt::AlterTable(tbl_name, AlterTableBody::RenameTo(new))) => {
+            assert_eq!(tbl_name.db_name, Some(Name("main".to_owned())));
+            assert_eq!(new, Name("new".to_owned()));
+        }
+        ast => panic!("unexpected statement: {}", ast),
+    }
+    assert_eq!(
+        parser_error("ALTER TABLE main.old RENAME TO other.new"),
+        "new table name cannot be qualified: other.new"
+    );
+}
+
+#[test]
+fn leading_comments() {
+    let sql = "-- Users\n-- one row per user\nCREATE TABLE users (id);\n\
+               CREATE TABLE t (x); -- trailing\n\
+               CREATE TABLE u (x);\n\
+               -- detached\n\n\
+               CREATE TABLE v (x);\n\
+               /* block */\n-- documented\n  CREATE VIEW w AS SELECT 1;";
+    let mut parser = Parser::new(sql.as_bytes());
+    let mut comments = vec![];
+    while let Some((cmd, leading)) = parser.next_with_comments().unwrap() {
+        comments.push((cmd.to_string(), leading));
+    }
+    assert_eq!(
+        comments,
+        vec![
+            (
+                "CREATE TABLE users (id);".to_owned(),
+                vec!["Users".to_owned(), "one row per user".to_owned()]
+            ),
+            ("CREATE TABLE t (x);".to_owned(), vec![]),
+            ("CREATE TABLE u (x);".to_owned(), vec![]),
+            ("CREATE TABLE v (x);".to_owned(), vec![]),
+            (
+                "CREATE VIEW w AS SELECT 1;".to_owned(),
+                vec!["documented".to_owned()]
+            ),
+        ]
+    );
+}
+
+fn add_column_warnings(sql: &str) -> Vec<String> {
+    let mut parser = Parser::new(sql.as_bytes());
+    match parser.next().unwrap().unwrap() {
+        Cmd::Stmt(Stmt::AlterTable(_, AlterTableBody::AddColumn(cd))) => cd
+            .add_column_warnings()
+            .into_iter()
+            .map(|d| d.message)
+            .collect(),
+        ast => panic!("unexpected statement: {}", ast),
+    }
+}
+
+#[test]
+fn alter_table_add_column() {
+    round_trip("ALTER TABLE t ADD COLUMN c TEXT NOT NULL DEFAULT '';");
+    round_trip("ALTER TABLE t ADD COLUMN c INTEGER CONSTRAINT fk REFERENCES p (id) ON DELETE CASCADE CHECK (c > 0) COLLATE nocase;");
+    round_trip("ALTER TABLE t ADD COLUMN c AS (a + 1) VIRTUAL;");
+    assert!(add_column_warnings("ALTER TABLE t ADD c TEXT NOT NULL DEFAULT ''").is_empty());
+    assert!(add_column_warnings("ALTER TABLE t ADD c AS (a + 1)").is_empty());
+    // parsed but rejected by SQLite
+    assert_eq!(
+        add_column_warnings("ALTER TABLE t ADD c INTEGER PRIMARY KEY"),
+        vec!["Cannot add a PRIMARY KEY column"]
+    );
+    assert_eq!(
+        add_column_warnings("ALTER TABLE t ADD c UNIQUE NOT NULL DEFAULT NULL"),
+        vec![
+            "Cannot add a UNIQUE column",
+            "Cannot add a NOT NULL column with default value NULL"
+        ]
+    );
+    assert_eq!(
+        add_column_warnings("ALTER TABLE t ADD c AS (a + 1) STORED"),
+        vec!["cannot add a STORED column"]
+    );
+}
+
+#[test]
+fn table_constraints_order() {
+    round_trip(
+        "CREATE TABLE t (a INTEGER CHECK (a > 0) PRIMARY KEY, b UNIQUE, \
+         CONSTRAINT u UNIQUE (b, a), CHECK (a < b), FOREIGN KEY (b) REFERENCES p (id), \
+         PRIMARY KEY (a));",
+    );
+    // like SQLite, table constraints cannot be followed by a column definition
+    let msg = syntax_error("CREATE TABLE t (a, PRIMARY KEY (a), b)");
+    assert!(msg.starts_with("near \"b\": syntax error"), "{}", msg);
+}
+
+fn rename_table(sql: &str) -> String {
+    let mut parser = Parser::new(sql.as_bytes());
+    let mut cmd = parser.next().unwrap().unwrap();
+    cmd.rename_table(&Name("t".to_owned()), Name("tenant_t".to_owned()));
+    cmd.to_string()
+}
+
+#[test]
+fn rename_table_references() {
+    // join
+    assert_eq!(
+        rename_table("SELECT t.a, u.b FROM t JOIN u ON t.id = u.id JOIN main.T ON main.t.x = 1;"),
+        "SELECT tenant_t.a, u.b FROM tenant_t JOIN u ON tenant_t.id = u.id JOIN main.tenant_t ON main.tenant_t.x = 1;"
+    );
+    // subquery
+    assert_eq!(
+        rename_table("SELECT * FROM u WHERE a IN (SELECT a FROM \"t\") AND b IN t;"),
+        "SELECT * FROM u WHERE a IN (SELECT a FROM tenant_t) AND b IN tenant_t;"
+    );
+    // targets
+    assert_eq!(
+        rename_table("INSERT INTO t SELECT * FROM u;"),
+        "INSERT INTO tenant_t SELECT * FROM u;"
+    );
+    assert_eq!(
+        rename_table("UPDATE t SET a = (SELECT b FROM t WHERE t.c = 1);"),
+        "UPDATE tenant_t SET a = (SELECT b FROM tenant_t WHERE tenant_t.c = 1);"
+    );
+    assert_eq!(
+        rename_table("DELETE FROM t WHERE t.a = 1;"),
+        "DELETE FROM tenant_t WHERE tenant_t.a = 1;"
+    );
+    // shadowing CTE
+    assert_eq!(
+        rename_table("WITH t AS (SELECT 1) SELECT * FROM t;"),
+        "WITH t AS (SELECT 1) SELECT * FROM t;"
+    );
+    assert_eq!(
+        rename_table("SELECT * FROM t WHERE EXISTS (WITH t AS (SELECT 1) SELECT * FROM t);"),
+        "SELECT * FROM tenant_t WHERE EXISTS (WITH t AS (SELECT 1) SELECT * FROM t);"
+    );
+    assert_eq!(
+        rename_table("WITH t AS (SELECT 1) INSERT INTO t SELECT * FROM t;"),
+        "WITH t AS (SELECT 1) INSERT INTO tenant_t SELECT * FROM t;"
+    );
+    // alias shadowing the table
+    assert_eq!(
+        rename_table("SELECT t.a FROM u AS t;"),
+        "SELECT t.a FROM u AS t;"
+    );
+    assert_eq!(
+        rename_table("SELECT t.a FROM t AS t ORDER BY t.a;"),
+        "SELECT t.a FROM tenant_t AS t ORDER BY t.a;"
+    );
+    assert_eq!(
+        rename_table("SELECT t.a FROM t WHERE EXISTS (SELECT t.b, x.c FROM u t, t x WHERE x.d = t.d);"),
+        "SELECT tenant_t.a FROM tenant_t WHERE EXISTS (SELECT t.b, x.c FROM u t, tenant_t x WHERE x.d = t.d);"
+    );
+    assert_eq!(
+        rename_table("SELECT t.a FROM t WHERE EXISTS (SELECT t.b FROM u);"),
+        "SELECT tenant_t.a FROM tenant_t WHERE EXISTS (SELECT tenant_t.b FROM u);"
+    );
+    assert_eq!(
+        rename_table("UPDATE u AS t SET a = t.b + 1 WHERE t.c IN (SELECT c FROM t);"),
+        "UPDATE u AS t SET a = t.b + 1 WHERE t.c IN (SELECT c FROM tenant_t);"
+    );
+}
+
+#[test]
+fn exists() {
+    round_trip("SELECT EXISTS (SELECT 1);");
+    round_trip("SELECT * FROM t WHERE NOT EXISTS (SELECT 1 FROM u WHERE u.id = t.id);");
+    assert!(matches!(
+        where_clause("SELECT 1 WHERE NOT EXISTS (SELECT 1)"),
+        Expr::Unary(UnaryOperator::Not, ref x) if matches!(**x, Expr::Exists(_))
+    ));
+    assert_eq!(
+        syntax_error("SELECT EXISTS x"),
+        "near \"x\": syntax error, expected one of: ("
+    );
+    assert_eq!(
+        syntax_error("SELECT NOT EXISTS 1"),
+        "near \"1\": syntax error, expected one of: ("
+    );
+}
+
+#[test]
+fn collate_precedence() {
+    round_trip("SELECT a = b COLLATE NOCASE;");
+    round_trip("SELECT x COLLATE RTRIM || y;");
+    round_trip("SELECT (a = b) COLLATE nocase, - a COLLATE nocase;");
+    for (sql, expected) in [
+        ("a = b COLLATE NOCASE", "(a = (b COLLATE NOCASE))"),
+        ("x COLLATE RTRIM || y", "((x COLLATE RTRIM) || y)"),
+        ("x || y COLLATE RTRIM", "(x || (y COLLATE RTRIM))"),
+        ("-a COLLATE nocase", "((- a) COLLATE nocase)"),
+        ("~a COLLATE nocase", "((~ a) COLLATE nocase)"),
+        ("a COLLATE x COLLATE y", "((a COLLATE x) COLLATE y)"),
+    ] {
+        let expr = where_clause(&format!("SELECT 1 WHERE {}", sql));
+        assert_eq!(parenthesize(&expr), expected, "{}", sql);
+    }
+}
+
+#[test]
+fn render_in_list_incrementally() {
+    /// Writer which keeps track of the largest chunk written at once
+    #[derive(Default)]
+    struct Chunks {
+        len: usize,
+        max_chunk: usize,
+    }
+    impl std::fmt::Write for Chunks {
+        fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            self.len += s.len();
+            self.max_chunk = self.max_chunk.max(s.len());
+            Ok(())
+        }
+    }
+    let list = (0..100_000).map(|i| i.to_string()).collect::<Vec<_>>();
+    let sql = format!("SELECT * FROM t WHERE id IN ({});", list.join(", "));
+    let mut parser = Parser::new(sql.as_bytes());
+    let ast = parser.next().unwrap().unwrap();
+    let mut chunks = Chunks::default();
+    std::fmt::write(&mut chunks, format_args!("{}", ast)).unwrap();
+    assert_eq!(chunks.len, sql.len());
+    assert!(chunks.max_chunk <= "SELECT".len(), "{}", chunks.max_chunk);
+}
+
+#[test]
+fn fold_constants() {
+    fn fold(expr: &str) -> String {
+        let expr = where_clause(&format!("SELECT 1 WHERE {}", expr)).fold_constants();
+        expr.display_with(FormatOptions::default()).to_string()
+    }
+    // arithmetic
+    assert_eq!(fold("1 + 2"), "3");
+    assert_eq!(fold("2 * (3 - 10)"), "- 14");
+    assert_eq!(fold("7 / 2 + 7 % 2"), "4");
+    assert_eq!(fold("1000 * -2"), "- 2000");
+    assert_eq!(fold("1 + NULL"), "NULL");
+    assert_eq!(fold("x + (1 + 2)"), "x + 3");
+    assert_eq!(fold("abs (1 + 2)"), "abs (3)");
+    // not folded
+    assert_eq!(fold("x + 1"), "x + 1");
+    assert_eq!(fold("1 + x + 2"), "1 + x + 2");
+    assert_eq!(fold("abs (1) + 2"), "abs (1) + 2");
+    assert_eq!(fold("1.5 + 2"), "1.5 + 2");
+    assert_eq!(fold("0x10 + 1"), "0x10 + 1");
+    assert_eq!(fold("1 / 0"), "1 / 0");
+    assert_eq!(fold("9223372036854775807 + 1"), "9223372036854775807 + 1");
+    assert_eq!(
+        fold("-9223372036854775807 - 1"),
+        "- 9223372036854775807 - 1"
+    );
+    assert_eq!(fold("-5"), "- 5");
+    // concatenation
+    assert_eq!(fold("'a' || 'b'"), "'ab'");
+    assert_eq!(fold("'it''s' || ' ' || 'ok'"), "'it''s ok'");
+    assert_eq!(fold("'a' || NULL"), "NULL");
+    assert_eq!(fold("'a' || 1"), "'a' || 1");
+    assert_eq!(fold("x || 'a' || 'b'"), "x || 'a' || 'b'");
+    // boolean
+    assert_eq!(fold("NOT TRUE"), "FALSE");
+    assert_eq!(fold("NOT (NOT false)"), "FALSE");
+    assert_eq!(fold("x AND FALSE"), "FALSE");
+    assert_eq!(fold("FALSE AND f (x)"), "FALSE");
+    assert_eq!(fold("x OR NOT FALSE"), "TRUE");
+    assert_eq!(fold("TRUE AND TRUE"), "TRUE");
+    assert_eq!(fold("x AND TRUE"), "x AND TRUE");
+    assert_eq!(fold("NOT \"true\""), "NOT \"true\"");
+}
+
+#[test]
+fn multi_word_type_names() {
+    for (sql, name) in [
+        ("CREATE TABLE t (x DOUBLE PRECISION);", "DOUBLE PRECISION"),
+        ("CREATE TABLE t (x UNSIGNED BIG INT);", "UNSIGNED BIG INT"),
+        (
+            "CREATE TABLE t (x VARYING CHARACTER (255));",
+            "VARYING CHARACTER",
+        ),
+        ("CREATE TABLE t (x NUMERIC (10, 2));", "NUMERIC"),
+        ("CREATE TABLE t (x JSONB NOT NULL);", "JSONB"),
+        (
+            "CREATE TABLE t (x NATIVE CHARACTER (70) PRIMARY KEY);",
+            "NATIVE CHARACTER",
+        ),
+    ] {
+        round_trip(sql);
+        let mut parser = Parser::new(sql.as_bytes());
+        match parser.next().unwrap().unwrap() {
+            Cmd::Stmt(Stmt::CreateTable {
+                body: CreateTableBody::ColumnsAndConstraints { columns, .. },
+                ..
+            }) => assert_eq!(columns[0].col_type.as_ref().unwrap().name, name),
+            cmd => panic!("unexpected command: {}", cmd),
+        }
+    }
+    round_trip("SELECT CAST (x AS DOUBLE PRECISION);");
+    round_trip("SELECT CAST (x AS VARYING CHARACTER (255));");
+}
+
+#[test]
+fn cast_type_names() {
+    round_trip("SELECT CAST (x AS TEXT);");
+    round_trip("SELECT CAST (x AS \"custom type\");");
+    round_trip("SELECT CAST (x AS [my type] text);");
+    round_trip("SELECT CAST (x AS DOUBLE PRECISION);");
+    round_trip("SELECT CAST (x AS UNSIGNED BIG INT);");
+    round_trip("SELECT CAST (x AS DECIMAL (10, 5));");
+    // no type name
+    let msg = syntax_error("SELECT CAST(x AS)");
+    assert!(msg.starts_with("near \")\": syntax error"), "{}", msg);
+    let Expr::Cast { type_name, .. } =
+        where_clause("SELECT * FROM t WHERE CAST(x AS DOUBLE PRECISION)")
+    else {
+        panic!("CAST expected")
+    };
+    assert_eq!(type_name.name, "DOUBLE PRECISION");
+}
+
+#[test]
+fn empty_statements() {
+    for sql in [";", ";;;", " ; -- comment\n;"] {
+        let mut parser = Parser::new(sql.as_bytes());
+        assert_eq!(parser.next().unwrap(), None, "{}", sql);
+    }
+    let mut parser = Parser::new("SELECT 1;;".as_bytes());
+    assert_eq!(parser.next().unwrap().unwrap().to_string(), "SELECT 1;");
+    assert_eq!(parser.next().unwrap(), None);
+    let mut parser = Parser::new(";; SELECT 1; ; SELECT 2".as_bytes());
+    assert_eq!(parser.next().unwrap().unwrap().to_string(), "SELECT 1;");
+    assert_eq!(parser.next().unwrap().unwrap().to_string(), "SELECT 2;");
+    assert_eq!(parser.next().unwrap(), None);
+}
+
+#[test]
+fn quoted_pragma_names() {
+    round_trip("PRAGMA \"main\".\"user_version\";");
+    round_trip("PRAGMA \"main\".\"user_version\" = 3;");
+    round_trip("PRAGMA [aux].`table_info` (\"t\");");
+    round_trip("PRAGMA \"journal_mode\" = 'wal';");
+    let mut parser = Parser::new("PRAGMA \"main\".\"user_version\"".as_bytes());
+    match parser.next().unwrap().unwrap() {
+        Cmd::Stmt(Stmt::Pragma(name, None)) => {
+            assert_eq!(name.db_name, Some(Name("\"main\"".to_owned())));
+            assert_eq!(name.name, Name("\"user_version\"".to_owned()));
+        }
+        cmd => panic!("unexpected command: {}", cmd),
+    }
+}
+
+#[test]
+fn warnings() {
+    let options = ParserOptions {
+        lexer: LexerOptions {
+            nested_block_comments: true,
+            ..LexerOptions::default()
+        },
+        ..ParserOptions::default()
+    };
+    let sql = "SELECT 1;\nSELECT /* a /* b */ */ 2;\nSELECT /* a */ 3;";
+    let mut parser = Parser::with_options(sql.as_bytes(), options);
+    assert_eq!(parser.next().unwrap().unwrap().to_string(), "SELECT 1;");
+    assert!(parser.take_warnings().is_empty());
+    assert_eq!(parser.next().unwrap().unwrap().to_string(), "SELECT 2;");
+    assert_eq!(
+        parser.take_warnings(),
+        vec![Diagnostic {
+            message: "nested block comment".to_owned(),
+            table: None,
+            position: Some((2, 25)),
+        }]
+    );
+    assert_eq!(parser.next().unwrap().unwrap().to_string(), "SELECT 3;");
+    assert!(parser.take_warnings().is_empty());
+
+    let sql = "ALTER TABLE t ADD COLUMN c INTEGER PRIMARY KEY;";
+    let mut parser = Parser::new(sql.as_bytes());
+    parser.next().unwrap().unwrap();
+    let warnings = parser.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].message, "Cannot add a PRIMARY KEY column");
+    assert_eq!(
+        warnings[0].to_string(),
+        "Cannot add a PRIMARY KEY column at (1, 48)"
+    );
+    assert!(parser.take_warnings().is_empty());
+}
+
+#[test]
+fn upsert_expression_target() {
+    round_trip(
+        "INSERT INTO u (email) VALUES ('a') ON CONFLICT (lower (email)) WHERE active DO NOTHING;",
+    );
+    round_trip(
+        "INSERT INTO u (email, n) VALUES ('a', 1) ON CONFLICT (lower (email) COLLATE NOCASE, n DESC) WHERE active AND n > 0 DO UPDATE SET n = n + 1 WHERE excluded.n > 0;",
+    );
+    round_trip(
+        "INSERT INTO u (email) VALUES ('a') ON CONFLICT (lower (email)) WHERE active DO NOTHING ON CONFLICT DO NOTHING;",
+    );
+    let sql =
+        "INSERT INTO u (email) VALUES ('a') ON CONFLICT (lower(email)) WHERE active DO NOTHING";
+    let mut parser = Parser::new(sql.as_bytes());
+    match parser.next().unwrap().unwrap() {
+        Cmd::Stmt(Stmt::Insert {
+            body: InsertBody::Select(_, Some(upsert)),
+            ..
+        }) => {
+            let index = upsert.index.unwrap();
+            assert!(matches!(index.targets[0].expr, Expr::FunctionCall { .. }));
+            assert!(matches!(index.where_clause, Some(Expr::Id(_))));
+        }
+        cmd => panic!("unexpected command: {}", cmd),
+    }
+}
+
+#[test]
+fn update_delete_limit() {
+    let options = ParserOptions {
+        update_delete_limit: true,
+        ..ParserOptions::default()
+    };
+    for (sql, msg) in [
+        (
+            "DELETE FROM t WHERE a = 1 ORDER BY x LIMIT 3;",
+            "ORDER BY and LIMIT are not supported on DELETE",
+        ),
+        (
+            "DELETE FROM t LIMIT 1;",
+            "ORDER BY and LIMIT are not supported on DELETE",
+        ),
+        (
+            "UPDATE t SET a = 1 WHERE b ORDER BY x DESC LIMIT 3 OFFSET 2;",
+            "ORDER BY and LIMIT are not supported on UPDATE",
+        ),
+        (
+            "UPDATE t SET a = 1 RETURNING a ORDER BY x LIMIT 1;",
+            "ORDER BY and LIMIT are not supported on UPDATE",
+        ),
+    ] {
+        assert_eq!(parser_error(sql), msg);
+        let mut parser = Parser::with_options(sql.as_bytes(), options);
+        assert_eq!(parser.next().unwrap().unwrap().to_string(), sql);
+    }
+    let sql = "DELETE FROM t WHERE a = 1 ORDER BY x LIMIT 3";
+    let mut parser = Parser::with_options(sql.as_bytes(), options);
+    match parser.next().unwrap().unwrap() {
+        Cmd::Stmt(Stmt::Delete {
+            order_by: Some(order_by),
+            limit: Some(limit),
+            ..
+        }) => {
+            assert_eq!(order_by.len(), 1);
+            assert_eq!(limit.expr, Expr::Literal(Literal::Numeric("3".to_owned())));
+        }
+        cmd => panic!("unexpected command: {}", cmd),
+    }
+    // without the extension
+    round_trip("DELETE FROM t WHERE a = 1;");
+    round_trip("UPDATE t SET a = 1 WHERE b;");
+}
+
+#[test]
+fn find_expr() {
+    let sql = "SELECT a, b FROM t WHERE lower (c) = 'x' AND d IN (SELECT upper (e) FROM u)";
+    let mut parser = Parser::new(sql.as_bytes());
+    let cmd = parser.next().unwrap().unwrap();
+    let is_call = |name: &str, expr: &Expr| match expr {
+        Expr::FunctionCall { name: n, .. } => n.0.eq_ignore_ascii_case(name),
+        _ => false,
+    };
+    // stops at the first match
+    let mut visited = 0;
+    let found = cmd.find_expr(|expr| {
+        visited += 1;
+        is_call("lower", expr)
+    });
+    assert_eq!(found.unwrap().to_string(), "lower (c)");
+    // a, b, AND, =, lower (c)
+    assert_eq!(visited, 5);
+    // in a subquery
+    let found = cmd.find_expr(|expr| is_call("upper", expr));
+    assert_eq!(found.unwrap().to_string(), "upper (e)");
+    // no match: the whole statement is visited
+    let mut visited = 0;
+    let found = cmd.find_expr(|expr| {
+        visited += 1;
+        is_call("abs", expr)
+    });
+    assert!(found.is_none());
+    // a, b, AND, =, lower (c), c, 'x', IN, d, upper (e), e
+    assert_eq!(visited, 11);
+}
+
+#[test]
+fn having_without_group_by() {
+    round_trip("SELECT count (*) FROM t HAVING count (*) > 1;");
+    round_trip("SELECT count (*) FROM t WHERE a HAVING count (*) > 1 ORDER BY 1;");
+    round_trip("SELECT a, count (*) FROM t GROUP BY a HAVING count (*) > 1;");
+    round_trip("SELECT sum (a) OVER w FROM t HAVING sum (a) > 0 WINDOW w AS (ORDER BY b);");
+    let sql = "SELECT count(*) FROM t HAVING count(*) > 1";
+    let mut parser = Parser::new(sql.as_bytes());
+    match parser.next().unwrap().unwrap() {
+        Cmd::Stmt(Stmt::Select(select)) => match select.body.select {
+            OneSelect::Select {
+                group_by: Some(group_by),
+                ..
+            } => {
+                assert!(group_by.exprs.is_empty());
+                assert_eq!(group_by.having.unwrap().to_string(), "count (*) > 1");
+            }
+            select => panic!("unexpected select: {:?}", select),
+        },
+        cmd => panic!("unexpected command: {}", cmd),
+    }
+    assert!(
+        syntax_error("SELECT count(*) FROM t HAVING count(*) > 1 GROUP BY a").contains("GROUP")
+    );
+}
+
+#[test]
+fn cmd_as_hash_key() {
+    use std::collections::HashMap;
+    fn parse(sql: &str) -> Cmd {
+        Parser::new(sql.as_bytes()).next().unwrap().unwrap()
+    }
+    let mut cache = HashMap::new();
+    for (i, sql) in [
+        "SELECT 1.5e3 FROM t WHERE a = 0x1F",
+        "SELECT 1500.0 FROM t WHERE a = 31",
+        "EXPLAIN SELECT 1.5e3 FROM t WHERE a = 0x1F",
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        cache.insert(parse(sql), i);
+    }
+    assert_eq!(cache.len(), 3);
+    // same statement, different spacing / keyword case
+    assert_eq!(
+        cache.get(&parse("select 1.5e3\nfrom t where a=0x1F;")),
+        Some(&0)
+    );
+    // numeric literals are compared by spelling
+    assert_eq!(cache.get(&parse("SELECT 1500 FROM t WHERE a = 31")), None);
+}
+
+/// Messages of `Cmd::validate`
+fn validate(sql: &str) -> Vec<String> {
+    let cmd = Parser::new(sql.as_bytes()).next().unwrap().unwrap();
+    cmd.validate().into_iter().map(|d| d.message).collect()
+}
+
+#[test]
+fn validate_autoincrement() {
+    const NOT_IPK: &str = "AUTOINCREMENT is only allowed on an INTEGER PRIMARY KEY";
+    for sql in [
+        "CREATE TABLE t (id INTEGER PRIMARY KEY AUTOINCREMENT, x TEXT)",
+        "CREATE TABLE t (id integer NOT NULL PRIMARY KEY ASC AUTOINCREMENT)",
+        "CREATE TABLE t (x TEXT, \"id\" INTEGER, PRIMARY KEY (id AUTOINCREMENT))",
+        "CREATE TABLE t (id TEXT PRIMARY KEY)",
+        "SELECT 1",
+    ] {
+        assert!(validate(sql).is_empty(), "{}", sql);
+    }
+    for (sql, diagnostics) in [
+        (
+            "CREATE TABLE t (id TEXT PRIMARY KEY AUTOINCREMENT)",
+            vec![NOT_IPK],
+        ),
+        (
+            "CREATE TABLE t (id INT PRIMARY KEY AUTOINCREMENT)",
+            vec![NOT_IPK],
+        ),
+        (
+            "CREATE TABLE t (id PRIMARY KEY AUTOINCREMENT)",
+            vec![NOT_IPK],
+        ),
+        (
+            "CREATE TABLE t (id INTEGER PRIMARY KEY DESC AUTOINCREMENT)",
+            vec![NOT_IPK],
+        ),
+        (
+            "CREATE TABLE t (a INTEGER, b INTEGER, PRIMARY KEY (a, b AUTOINCREMENT))",
+            vec![NOT_IPK],
+        ),
+        (
+            "CREATE TABLE t (a TEXT, PRIMARY KEY (a AUTOINCREMENT))",
+            vec![NOT_IPK],
+        ),
+        (
+            "CREATE TABLE t (id INTEGER PRIMARY KEY AUTOINCREMENT) WITHOUT ROWID",
+            vec!["AUTOINCREMENT not allowed on WITHOUT ROWID tables"],
+        ),
+    ] {
+        assert_eq!(validate(sql), diagnostics, "{}", sql);
+    }
+}
+
+#[test]
+fn implicit_aliases() {
+    round_trip("SELECT x y FROM t a;");
+    round_trip("SELECT x AS y FROM t AS a;");
+    round_trip("SELECT a.x y, b.x AS z FROM t a JOIN u AS b ON a.id = b.id;");
+    round_trip("SELECT s.x FROM (SELECT 1 x) s;");
+    round_trip("SELECT x \"y z\" FROM t 'a';");
+    let mut parser = Parser::new("SELECT x y, x AS z FROM t a".as_bytes());
+    match parser.next().unwrap().unwrap() {
+        Cmd::Stmt(Stmt::Select(select)) => match select.body.select {
+            OneSelect::Select { columns, from, .. } => {
+                assert!(matches!(
+                    columns[0],
+                    ResultColumn::Expr(_, Some(As::Elided(_)))
+                ));
+                assert!(matches!(columns[1], ResultColumn::Expr(_, Some(As::As(_)))));
+                assert!(matches!(
+                    from.unwrap().select.as_deref(),
+                    Some(SelectTable::Table(_, Some(As::Elided(_)), _))
+                ));
+            }
+            select => panic!("unexpected select: {:?}", select),
+        },
+        cmd => panic!("unexpected command: {}", cmd),
+    }
+}
+
+#[test]
+fn keyword_aliases_stay_quoted() {
+    round_trip("SELECT 1 AS \"order\";");
+    round_trip("SELECT 1 AS \"select\" FROM t AS \"from\";");
+    round_trip("SELECT 1 AS [group], 2 AS `where`;");
+    // fallback keywords can be used as identifiers without quotes
+    round_trip("SELECT 1 AS KEY, REPLACE FROM t AS TEMP;");
+    // names built without quotes
+    let mut parser = Parser::new("SELECT 1 AS x FROM t AS y".as_bytes());
+    let mut cmd = parser.next().unwrap().unwrap();
+    if let Cmd::Stmt(Stmt::Select(ref mut select)) = cmd {
+        if let OneSelect::Select {
+            ref mut columns,
+            ref mut from,
+            ..
+        } = select.body.select
+        {
+            columns[0] = ResultColumn::Expr(
+                Expr::Literal(Literal::Numeric("1".to_owned())),
+                Some(As::As(Name("order".to_owned()))),
+            );
+            if let Some(SelectTable::Table(_, ref mut alias, _)) =
+                from.as_mut().unwrap().select.as_deref_mut()
+            {
+                *alias = Some(As::Elided(Name("Select".to_owned())));
+            }
+        }
+    }
+    let sql = cmd.to_string();
+    assert_eq!(sql, "SELECT 1 AS \"order\" FROM t \"Select\";");
+    round_trip(&sql);
+}
+
+#[test]
+fn attach_database() {
+    let options = FormatOptions {
+        attach_database: true,
+        ..FormatOptions::default()
+    };
+    for (sql, short, long) in [
+        (
+            "ATTACH 'x' AS a",
+            "ATTACH 'x' AS a;",
+            "ATTACH DATABASE 'x' AS a;",
+        ),
+        (
+            "ATTACH DATABASE 'x' AS a KEY 'k'",
+            "ATTACH 'x' AS a KEY 'k';",
+            "ATTACH DATABASE 'x' AS a KEY 'k';",
+        ),
+        ("DETACH a", "DETACH a;", "DETACH DATABASE a;"),
+        ("detach database a", "DETACH a;", "DETACH DATABASE a;"),
+    ] {
+        let mut parser = Parser::new(sql.as_bytes());
+        let ast = parser.next().unwrap().unwrap();
+        assert_eq!(ast.to_string(), short);
+        assert_eq!(ast.display_with(options).to_string(), long);
+    }
+    round_trip("ATTACH 'x' AS a;");
+    round_trip("DETACH a;");
+}
+
+#[test]
+fn validate_without_rowid() {
+    for sql in [
+        "CREATE TABLE t (k TEXT PRIMARY KEY, v) WITHOUT ROWID",
+        "CREATE TABLE t (a, b, c, PRIMARY KEY (a, b)) WITHOUT ROWID, STRICT",
+        "CREATE TABLE t (a, b)",
+    ] {
+        assert!(validate(sql).is_empty(), "{}", sql);
+    }
+    for sql in [
+        "CREATE TABLE t (a, b UNIQUE) WITHOUT ROWID",
+        "CREATE TABLE t (a, b, UNIQUE (a, b)) STRICT, WITHOUT ROWID",
+    ] {
+        assert_eq!(
+            validate(sql),
+            vec!["PRIMARY KEY missing on WITHOUT ROWID table"],
+            "{}",
+            sql
+        );
+    }
+    // the statement is still parsed
+    round_trip("CREATE TABLE t (a, b) WITHOUT ROWID;");
+}
+
+#[test]
+fn error_snippet() {
+    let sql = "SELECT x,\n  'abc FROM t";
+    let err = Parser::new(sql.as_bytes()).next().unwrap_err();
+    assert_eq!(err.line_column(), Some((2, 3)));
+    assert_eq!(err.offset(), Some(12));
+    assert_eq!(
+        err.snippet(sql),
+        "non-terminated literal at (2, 3)\n2 |   'abc FROM t\n  |   ^"
+    );
+
+    let sql = "SELECT 1 FROM t WHER x AND y";
+    let err = Parser::new(sql.as_bytes()).next().unwrap_err();
+    assert_eq!(err.offset(), Some(22));
+    let snippet = err.snippet(sql);
+    assert!(
+        snippet.starts_with("near \"x\": syntax error"),
+        "{}",
+        snippet
+    );
+    assert!(
+        snippet.ends_with("\n1 | SELECT 1 FROM t WHER x AND y\n  |                       ^"),
+        "{}",
+        snippet
+    );
+}
+
+#[test]
+fn select_without_from() {
+    round_trip("SELECT 1, 2, 3;");
+    round_trip("SELECT random ();");
+    round_trip("SELECT 1 + 1, 'x';");
+    round_trip("SELECT 1 WHERE 0;");
+    for sql in [
+        "SELECT 1, 2, 3",
+        "SELECT random()",
+        "SELECT 1 + 1 AS two, 'x'",
+    ] {
+        let mut parser = Parser::new(sql.as_bytes());
+        match parser.next().unwrap().unwrap() {
+            Cmd::Stmt(Stmt::Select(select)) => match select.body.select {
+                OneSelect::Select { columns, from, .. } => {
+                    assert!(from.is_none(), "{}", sql);
+                    assert!(!columns.is_empty(), "{}", sql);
+                }
+                body => panic!("unexpected body: {:?}", body),
+            },
+            ast => panic!("unexpected statement: {:?}", ast),
+        }
+    }
+}
+
+#[test]
+fn lenient_pragma() {
+    let options = ParserOptions {
+        lenient_pragma: true,
+        ..ParserOptions::default()
+    };
+    for (sql, expected) in [
+        ("PRAGMA m.foo(a b)", "PRAGMA m.foo (a b);"),
+        ("PRAGMA cipher = x'0102'", "PRAGMA cipher = X'0102';"),
+        ("PRAGMA foo = NULL", "PRAGMA foo = NULL;"),
+        ("PRAGMA foo(f(1, 2))", "PRAGMA foo (f(1, 2));"),
+        (
+            "EXPLAIN PRAGMA foo = a.b; SELECT 1",
+            "EXPLAIN PRAGMA foo = a.b;",
+        ),
+    ] {
+        syntax_error(sql);
+        let mut parser = Parser::with_options(sql.as_bytes(), options);
+        assert_eq!(parser.next().unwrap().unwrap().to_string(), expected);
+        assert_eq!(
+            parser.take_warnings()[0].message,
+            "PRAGMA argument captured as raw text"
+        );
+    }
+    // arguments which fit the grammar are parsed as usual
+    for sql in [
+        "PRAGMA optimize (0x10002);",
+        "PRAGMA foo = - 1;",
+        "PRAGMA journal_mode = wal;",
+    ] {
+        let mut parser = Parser::with_options(sql.as_bytes(), options);
+        assert_eq!(parser.next().unwrap().unwrap().to_string(), sql);
+        assert!(parser.take_warnings().is_empty());
+    }
+    let sql = "PRAGMA foo(a  /* */ b)";
+    let mut parser = Parser::with_options(sql.as_bytes(), options);
+    match parser.next().unwrap().unwrap() {
+        Cmd::Stmt(Stmt::Pragma(_, Some(PragmaBody::RawCall(raw)))) => {
+            assert_eq!(raw, "a b");
+        }
+        cmd => panic!("unexpected command: {}", cmd),
+    }
+    let sql = "PRAGMA foo = 'a' 'b'";
+    let mut parser = Parser::with_options(sql.as_bytes(), options);
+    match parser.next().unwrap().unwrap() {
+        Cmd::Stmt(Stmt::Pragma(_, Some(PragmaBody::RawEquals(raw)))) => {
+            assert_eq!(raw, "'a' 'b'");
+        }
+        cmd => panic!("unexpected command: {}", cmd),
+    }
+    let sql = "PRAGMA foo(a b";
+    let mut parser = Parser::with_options(sql.as_bytes(), options);
+    assert!(parser.next().is_err());
+    // only for PRAGMA
+    let sql = "SELECT pragma = (a b)";
+    let mut parser = Parser::with_options(sql.as_bytes(), options);
+    assert!(parser.next().is_err());
+}
+
+#[test]
+fn structural_eq() {
+    fn parse(sql: &str) -> Cmd {
+        Parser::new(sql.as_bytes()).next().unwrap().unwrap()
+    }
+    for (a, b) in [
+        ("SELECT a.x FROM t a", "SELECT b.x FROM t b"),
+        ("SELECT a.x FROM t AS a", "SELECT B.x FROM t \"b\""),
+        (
+            "SELECT a.x, b.y FROM t a JOIN u b ON a.id = b.id",
+            "SELECT c.x, d.y FROM t c JOIN u d ON c.id = d.id",
+        ),
+        (
+            "WITH c AS (SELECT 1 AS x) SELECT c.x FROM c",
+            "WITH d AS (SELECT 1 AS x) SELECT d.x FROM d",
+        ),
+        (
+            "SELECT s.x FROM (SELECT 1 AS x) s",
+            "SELECT q.x FROM (SELECT 1 AS x) q",
+        ),
+        (
+            "UPDATE t AS a SET x = 1 WHERE a.y",
+            "UPDATE t AS b SET x = 1 WHERE b.y",
+        ),
+    ] {
+        assert!(parse(a).structural_eq(&parse(b)), "{} / {}", a, b);
+    }
+    for (a, b) in [
+        ("SELECT x FROM t", "SELECT x FROM u"),
+        ("SELECT a.x FROM t a", "SELECT a.y FROM t a"),
+        // aliases swapped
+        ("SELECT a.x FROM t a, u b", "SELECT b.x FROM t a, u b"),
+        // table vs alias
+        ("SELECT t.x FROM t", "SELECT a.x FROM t a"),
+        // CTE vs table
+        (
+            "WITH c AS (SELECT 1) SELECT * FROM c",
+            "WITH d AS (SELECT 1) SELECT * FROM c",
+        ),
+    ] {
+        assert!(!parse(a).structural_eq(&parse(b)), "{} / {}", a, b);
+    }
+}
+
+#[test]
+fn pragma_names() {
+    // the PRAGMA grammar does not depend on the pragma name
+    for (sql, name) in [
+        ("PRAGMA table_xinfo(t)", "table_xinfo"),
+        ("PRAGMA main.index_xinfo('i')", "index_xinfo"),
+        ("PRAGMA foreign_key_check", "foreign_key_check"),
+        ("PRAGMA integrity_check(10)", "integrity_check"),
+        ("PRAGMA temp_store = MEMORY", "temp_store"),
+        ("PRAGMA recursive_triggers = ON", "recursive_triggers"),
+        ("PRAGMA no_such_pragma = -1", "no_such_pragma"),
+        ("PRAGMA key = 'secret'", "key"),
+        ("PRAGMA query = DELETE", "query"),
+    ] {
+        let mut parser = Parser::new(sql.as_bytes());
+        match parser.next().unwrap().unwrap() {
+            // keywords used as names are uppercased
+            Cmd::Stmt(Stmt::Pragma(pragma, _)) => {
+                assert!(pragma.name.0.eq_ignore_ascii_case(name), "{}", sql)
+            }
+            cmd => panic!("unexpected command: {}", cmd),
+        }
+    }
+}
+
+#[test]
+fn token_diffs() {
+    use DiffOp::*;
+    assert_eq!(
+        token_diff(
+            "CREATE TABLE t (a INT, b TEXT)",
+            "CREATE TABLE t (a INT, b TEXT, c BLOB)"
+        ),
+        [
+            Equal("CREATE"),
+            Equal("TABLE"),
+            Equal("t"),
+            Equal("("),
+            Equal("a"),
+            Equal("INT"),
+            Equal(","),
+            Equal("b"),
+            Equal("TEXT"),
+            Insert(","),
+            Insert("c"),
+            Insert("BLOB"),
+            Equal(")"),
+        ]
+    );
+    // whitespace and comments are ignored
+    assert_eq!(
+        token_diff(
+            "CREATE INDEX i ON t (a, b)",
+            "CREATE INDEX i ON t  -- renamed\n(a, \"B c\")"
+        ),
+        [
+            Equal("CREATE"),
+            Equal("INDEX"),
+            Equal("i"),
+            Equal("ON"),
+            Equal("t"),
+            Equal("("),
+            Equal("a"),
+            Equal(","),
+            Delete("b"),
+            Insert("\"B c\""),
+            Equal(")"),
+        ]
+    );
+    assert_eq!(token_diff("", "SELECT"), [Insert("SELECT")]);
+    assert_eq!(
+        token_diff("SELECT 'a", "SELECT"),
+        [Equal("SELECT"), Delete("'a")]
+    );
+}
+
+#[test]
+fn identity_columns() {
+    let msg = "SQLite does not support IDENTITY columns; use INTEGER PRIMARY KEY AUTOINCREMENT.";
+    assert_eq!(
+        syntax_error("CREATE TABLE t (id INTEGER GENERATED ALWAYS AS IDENTITY, x)"),
+        msg
+    );
+    assert_eq!(
+        syntax_error("CREATE TABLE t (id INT PRIMARY KEY GENERATED BY DEFAULT AS identity)"),
+        msg
+    );
+    assert_eq!(
+        syntax_error("CREATE TABLE t (id UNIQUE GENERATED ALWAYS AS IDENTITY)"),
+        msg
+    );
+    assert_eq!(syntax_error("CREATE TABLE t (id AS IDENTITY)"), msg);
+    // reported as a syntax error, with its position and expected tokens
+    let mut parser = Parser::new("CREATE TABLE t (id GENERATED ALWAYS AS IDENTITY)".as_bytes());
+    match parser.next() {
+        Err(Error::SyntaxError(m, Some((1, 48, 47)))) => assert_eq!(m, msg),
+        r => panic!("unexpected {:?}", r),
+    }
+    assert_eq!(parser.expected_at_error(), [TokenType::TK_LP]);
+    // `GENERATED BY` following the type is part of the type name:
+    // the error is detected before `IDENTITY`
+    assert_eq!(
+        syntax_error("CREATE TABLE t (id INT GENERATED BY DEFAULT AS IDENTITY)"),
+        "near \"AS\": syntax error, expected one of: (, identifier, +, -, string, NULL, blob, number"
+    );
+    assert_eq!(
+        syntax_error("CREATE TABLE t (id GENERATED ALWAYS AS seq)"),
+        "near \"seq\": syntax error, expected one of: ("
+    );
+    assert_eq!(
+        syntax_error("SELECT 1 WINDOW w AS IDENTITY"),
+        "near \"IDENTITY\": syntax error, expected one of: ("
+    );
+    round_trip("CREATE TABLE t (id INTEGER, x GENERATED ALWAYS AS (id * 2) STORED);");
+}
+
+#[test]
+fn expected_at_error() {
+    use crate::dialect::TokenType::*;
+    let mut parser = Parser::new("SELECT * FRO".as_bytes());
+    assert!(parser.next().is_err());
+    let expected = parser.expected_at_error();
+    for token_type in [TK_FROM, TK_WHERE, TK_ORDER, TK_LIMIT, TK_UNION, TK_COMMA] {
+        assert!(expected.contains(&token_type), "{:?}", token_type);
+    }
+    assert!(!expected.contains(&TK_SELECT));
+    // valid prefix
+    let mut parser = Parser::new("SELECT * FROM".as_bytes());
+    assert!(parser.next().is_err());
+    let expected = parser.expected_at_error();
+    assert!(expected.contains(&TK_ID));
+    assert!(expected.contains(&TK_LP));
+    assert!(!expected.contains(&TK_WHERE));
+    // before the reductions triggered by the unexpected token
+    let mut parser = Parser::new("SELECT * FROM t WHERE x 1".as_bytes());
+    assert!(parser.next().is_err());
+    let expected = parser.expected_at_error();
+    for token_type in [TK_DOT, TK_LP, TK_EQ, TK_AND, TK_ORDER, TK_SEMI] {
+        assert!(expected.contains(&token_type), "{:?}", token_type);
+    }
+    // reset by the next statement
+    let mut parser = Parser::new("SELECT 1 1; SELECT 2;".as_bytes());
+    assert!(parser.next().is_err());
+    assert!(!parser.expected_at_error().is_empty());
+    assert!(parser.next().unwrap().is_some());
+    assert!(parser.expected_at_error().is_empty());
+}
+
+#[test]
+fn parameter_style() {
+    fn render(sql: &str, style: ParameterStyle) -> String {
+        let options = FormatOptions {
+            parameter_style: Some(style),
+            ..FormatOptions::default()
+        };
+        let mut parser = Parser::new(sql.as_bytes());
+        let ast = parser.next().unwrap().unwrap();
+        ast.display_with(options).to_string()
+    }
+    let sql = "SELECT * FROM t WHERE a = ? AND b = ?";
+    assert_eq!(
+        render(sql, ParameterStyle::Named),
+        "SELECT * FROM t WHERE a = :p1 AND b = :p2;"
+    );
+    assert_eq!(
+        render(sql, ParameterStyle::Numbered),
+        "SELECT * FROM t WHERE a = ?1 AND b = ?2;"
+    );
+    let sql = "SELECT :x, ?5, :x, ?, @y, ?5";
+    assert_eq!(
+        render(sql, ParameterStyle::Named),
+        "SELECT :p1, :p2, :p1, :p3, :p4, :p2;"
+    );
+    assert_eq!(
+        render(sql, ParameterStyle::Numbered),
+        "SELECT ?1, ?2, ?1, ?3, ?4, ?2;"
+    );
+    assert_eq!(
+        render(sql, ParameterStyle::Question),
+        "SELECT ?, ?, ?, ?, ?, ?;"
+    );
+    // as written by default
+    round_trip("SELECT :x, ?5, ?, @y;");
+}
+
+#[test]
+fn create_index_modifiers() {
+    let sql =
+        "CREATE UNIQUE INDEX IF NOT EXISTS main.idx ON t (a, b COLLATE nocase DESC) WHERE a > 0;";
+    round_trip(sql);
+    let mut parser = Parser::new(sql.as_bytes());
+    match parser.next().unwrap().unwrap() {
+        Cmd::Stmt(Stmt::CreateIndex {
+            unique,
+            if_not_exists,
+            idx_name,
+            tbl_name,
+            columns,
+            where_clause,
+        }) => {
+            assert!(unique);
+            assert!(if_not_exists);
+            assert_eq!(idx_name.db_name, Some(Name("main".to_owned())));
+            assert_eq!(idx_name.name, Name("idx".to_owned()));
+            assert_eq!(tbl_name, Name("t".to_owned()));
+            assert_eq!(columns.len(), 2);
+            assert_eq!(columns[1].expr.to_string(), "b COLLATE nocase");
+            assert!(columns[1].order.is_some());
+            assert_eq!(where_clause.unwrap().to_string(), "a > 0");
+        }
+        cmd => panic!("unexpected command: {}", cmd),
+    }
+    round_trip("CREATE INDEX idx ON t (a);");
+    round_trip("CREATE UNIQUE INDEX main.idx ON t (a) WHERE a IS NOT NULL;");
+    round_trip("CREATE INDEX IF NOT EXISTS idx ON t (a);");
+}
+
+#[test]
+fn propagate_table_rename() {
+    let schema = "CREATE TABLE t (id INTEGER PRIMARY KEY, a);
+CREATE TABLE u (t_id REFERENCES t (id), FOREIGN KEY (t_id) REFERENCES \"t\");
+CREATE INDEX i ON t (a);
+CREATE VIEW v AS SELECT t.a, u.t_id FROM t JOIN u ON t.id = u.t_id;
+CREATE TRIGGER tr AFTER DELETE ON t BEGIN DELETE FROM u WHERE t_id = old.id; INSERT INTO t (a) SELECT a FROM t; END;
+CREATE TABLE w (a);
+CREATE TABLE aux.t (a);
+CREATE INDEX aux.i ON t (a);
+CREATE VIEW v2 AS SELECT t.a, x.b, aux.t.c FROM aux.t JOIN main.t AS x ON t.id = x.id;
+CREATE VIEW v3 AS SELECT t.a FROM u AS t;";
+    let mut parser = Parser::new(schema.as_bytes());
+    let mut statements = vec![];
+    while let Some(cmd) = parser.next().unwrap() {
+        statements.push(cmd);
+    }
+    propagate_rename(
+        &mut statements,
+        &Name("T".to_owned()),
+        &Name("t2".to_owned()),
+    );
+    let statements: Vec<_> = statements
+        .iter()
+        .map(|cmd| cmd.to_string().replace('\n', " "))
+        .collect();
+    assert_eq!(
+        statements,
+        [
+            "CREATE TABLE t2 (id INTEGER PRIMARY KEY, a);",
+            "CREATE TABLE u (t_id REFERENCES t2 (id), FOREIGN KEY (t_id) REFERENCES t2);",
+            "CREATE INDEX i ON t2 (a);",
+            "CREATE VIEW v AS SELECT t2.a, u.t_id FROM t2 JOIN u ON t2.id = u.t_id;",
+            "CREATE TRIGGER tr AFTER DELETE ON t2 BEGIN DELETE FROM u WHERE t_id = old.id; INSERT INTO t2 (a) SELECT a FROM t2; END;",
+            "CREATE TABLE w (a);",
+            "CREATE TABLE aux.t (a);",
+            "CREATE INDEX aux.i ON t (a);",
+            "CREATE VIEW v2 AS SELECT t.a, x.b, aux.t.c FROM aux.t JOIN main.t2 AS x ON t.id = x.id;",
+            "CREATE VIEW v3 AS SELECT t.a FROM u AS t;",
+        ]
+    );
+}
+
+#[test]
+fn between() {
+    round_trip("SELECT x BETWEEN 1 AND 10;");
+    round_trip("SELECT x NOT BETWEEN a AND b;");
+    round_trip("SELECT * FROM t WHERE x BETWEEN a AND b AND c;");
+    round_trip("SELECT * FROM t WHERE x NOT BETWEEN a + 1 AND b * 2 OR c;");
+    round_trip("SELECT x BETWEEN (a AND b) AND c;");
+    // the first `AND` belongs to `BETWEEN`
+    match where_clause("SELECT * FROM t WHERE x BETWEEN a AND b AND c") {
+        Expr::Binary(lhs, Operator::And, rhs) => {
+            assert_eq!(
+                *lhs,
+                Expr::Between {
+                    lhs: Box::new(Expr::Id(Id("x".to_owned()))),
+                    not: false,
+                    start: Box::new(Expr::Id(Id("a".to_owned()))),
+                    end: Box::new(Expr::Id(Id("b".to_owned()))),
+                }
+            );
+            assert_eq!(*rhs, Expr::Id(Id("c".to_owned())));
+        }
+        expr => panic!("unexpected expression: {:?}", expr),
+    }
+    // same precedence as `=`, left associative (like SQLite)
+    match where_clause("SELECT * FROM t WHERE x NOT BETWEEN 1 AND 2 = 1") {
+        Expr::Binary(lhs, Operator::Equals, _) => {
+            assert!(matches!(*lhs, Expr::Between { not: true, .. }));
+        }
+        expr => panic!("unexpected expression: {:?}", expr),
+    }
+}
+
+#[test]
+fn boolean_and_null_literals() {
+    round_trip("SELECT TRUE, FALSE, NULL;");
+    round_trip("SELECT true, False, NULL;");
+    round_trip("SELECT * FROM t WHERE a IS NOT TRUE AND b = false OR c IS NULL;");
+    // like SQLite, `TRUE` / `FALSE` are identifiers (a column named `true` wins)
+    let sql = "SELECT true, FALSE, NULL";
+    let mut parser = Parser::new(sql.as_bytes());
+    match parser.next().unwrap().unwrap() {
+        Cmd::Stmt(Stmt::Select(select)) => match select.body.select {
+            OneSelect::Select { columns, .. } => {
+                let exprs: Vec<_> = columns
+                    .into_iter()
+                    .map(|column| match column {
+                        ResultColumn::Expr(expr, None) => expr,
+                        column => panic!("unexpected column: {:?}", column),
+                    })
+                    .collect();
+                assert_eq!(
+                    exprs,
+                    [
+                        Expr::Id(Id("true".to_owned())),
+                        Expr::Id(Id("FALSE".to_owned())),
+                        Expr::Literal(Literal::Null),
+                    ]
+                );
+            }
+            body => panic!("unexpected body: {:?}", body),
+        },
+        ast => panic!("unexpected statement: {:?}", ast),
+    }
+    assert_eq!(
+        where_clause("SELECT * FROM t WHERE b = false"),
+        Expr::Binary(
+            Box::new(Expr::Id(Id("b".to_owned()))),
+            Operator::Equals,
+            Box::new(Expr::Id(Id("false".to_owned())))
+        )
+    );
+}
+
+#[test]
+fn comment_kinds() {
+    let sql = "-- line\r\nSELECT /* block\n*/ 1, '-- not a comment' --last";
+    let all: Vec<_> = comments(sql).collect();
+    assert_eq!(
+        all,
+        [
+            Comment {
+                kind: CommentKind::Line,
+                text: " line\r"
+            },
+            Comment {
+                kind: CommentKind::Block,
+                text: " block\n"
+            },
+            Comment {
+                kind: CommentKind::Line,
+                text: "last"
+            },
+        ]
+    );
+    let sources: Vec<_> = all.iter().map(Comment::to_string).collect();
+    assert_eq!(sources, ["-- line\r", "/* block\n*/", "--last"]);
+    for comment in &all {
+        assert!(sql.contains(&comment.to_string()));
+    }
+    assert_eq!(comments("SELECT 1; /**/").next().unwrap().text, "");
+    assert_eq!(comments("SELECT 'abc -- x").count(), 0);
+}
+
+#[test]
+fn validate_values_arity() {
+    for sql in [
+        "VALUES (1, 2), (3, 4), (5, 6)",
+        "INSERT INTO t (a) VALUES (1), (2)",
+        "SELECT * FROM (VALUES (1, 'a'), (2, 'b'))",
+    ] {
+        assert!(validate(sql).is_empty(), "{}", sql);
+    }
+    assert_eq!(
+        validate("VALUES (1, 2), (3, 4), (5)"),
+        vec!["all VALUES must have the same number of terms: row 3 has 1, expected 2"]
+    );
+    assert_eq!(
+        validate("INSERT INTO t VALUES (1), (2, 3), (4, 5, 6)"),
+        vec![
+            "all VALUES must have the same number of terms: row 2 has 2, expected 1",
+            "all VALUES must have the same number of terms: row 3 has 3, expected 1",
+        ]
+    );
+    assert_eq!(
+        validate("SELECT x IN (VALUES (1), (2, 3)) FROM t"),
+        vec!["all VALUES must have the same number of terms: row 2 has 2, expected 1"]
+    );
+}
+
+#[test]
+fn cmd_outlives_input() {
+    // the AST owns its data: no conversion is needed to keep it
+    let buffer = String::from("SELECT 'x', y FROM t WHERE z = ?1");
+    let cmd = Parser::new(buffer.as_bytes()).next().unwrap().unwrap();
+    drop(buffer);
+    assert_eq!(cmd.to_string(), "SELECT 'x', y FROM t WHERE z = ?1;");
+}
+
+#[test]
+fn table_schema() {
+    fn reflect(sql: &str) -> TableSchema {
+        let cmd = Parser::new(sql.as_bytes()).next().unwrap().unwrap();
+        TableSchema::from_create_table(cmd.stmt()).unwrap()
+    }
+    let schema = reflect(
+        "CREATE TABLE main.t (id INTEGER PRIMARY KEY, name TEXT NOT NULL DEFAULT 'x', \
+         price REAL, owner INT REFERENCES users (id))",
+    );
+    assert_eq!(schema.name.db_name, Some(Name("main".to_owned())));
+    assert_eq!(schema.name.name, Name("t".to_owned()));
+    assert_eq!(schema.primary_key, vec![Name("id".to_owned())]);
+    let columns: Vec<_> = schema
+        .columns
+        .iter()
+        .map(|c| {
+            (
+                c.name.0.as_str(),
+                c.type_affinity,
+                c.nullable,
+                c.primary_key,
+            )
+        })
+        .collect();
+    assert_eq!(
+        columns,
+        vec![
+            ("id", Affinity::Integer, true, true),
+            ("name", Affinity::Text, false, false),
+            ("price", Affinity::Real, true, false),
+            ("owner", Affinity::Integer, true, false),
+        ]
+    );
+    assert_eq!(
+        schema.columns[1].default,
+        Some(Expr::Literal(Literal::String("'x'".to_owned())))
+    );
+    assert_eq!(schema.foreign_keys.len(), 1);
+    assert_eq!(
+        schema.foreign_keys[0].columns,
+        vec![Name("owner".to_owned())]
+    );
+    assert_eq!(schema.foreign_keys[0].clause.tbl_name.0, "users");
+
+    let schema = reflect(
+        "CREATE TABLE t (a TEXT, B, c NUMERIC, PRIMARY KEY (b, A), \
+         FOREIGN KEY (c) REFERENCES u) WITHOUT ROWID",
+    );
+    assert_eq!(
+        schema.primary_key,
+        vec![Name("B".to_owned()), Name("a".to_owned())]
+    );
+    let columns: Vec<_> = schema
+        .columns
+        .iter()
+        .map(|c| (c.type_affinity, c.primary_key))
+        .collect();
+    assert_eq!(
+        columns,
+        vec![
+            (Affinity::Text, true),
+            (Affinity::Blob, true),
+            (Affinity::Numeric, false)
+        ]
+    );
+    assert_eq!(schema.foreign_keys[0].columns, vec![Name("c".to_owned())]);
+
+    let cmd = Parser::new("CREATE TABLE t AS SELECT 1".as_bytes())
+        .next()
+        .unwrap()
+        .unwrap();
+    assert!(TableSchema::from_create_table(cmd.stmt()).is_none());
+}
+
+#[test]
+fn identifier_quote_style() {
+    fn render(sql: &str, style: Option<QuoteStyle>) -> String {
+        let options = FormatOptions {
+            identifier_quote_style: style,
+            ..FormatOptions::default()
+        };
+        let mut parser = Parser::new(sql.as_bytes());
+        let ast = parser.next().unwrap().unwrap();
+        ast.display_with(options).to_string()
+    }
+    let sql = "SELECT [foo], `b``ar`, \"a\"\"b\", c FROM [my table];";
+    assert_eq!(render(sql, None), sql);
+    assert_eq!(
+        render(sql, Some(QuoteStyle::DoubleQuote)),
+        "SELECT \"foo\", \"b`ar\", \"a\"\"b\", c FROM \"my table\";"
+    );
+    assert_eq!(
+        render(sql, Some(QuoteStyle::Bracket)),
+        "SELECT [foo], [b`ar], [a\"b], c FROM [my table];"
+    );
+    assert_eq!(
+        render(sql, Some(QuoteStyle::Backtick)),
+        "SELECT `foo`, `b``ar`, `a\"b`, c FROM `my table`;"
+    );
+    // `]` cannot be escaped inside brackets
+    assert_eq!(
+        render("SELECT \"a]\";", Some(QuoteStyle::Bracket)),
+        "SELECT \"a]\";"
+    );
+    // reserved keywords are still quoted
+    assert_eq!(
+        render("SELECT [select];", Some(QuoteStyle::Bracket)),
+        "SELECT [select];"
+    );
+    // normalized output parses to the same names
+    let normalized = render(sql, Some(QuoteStyle::DoubleQuote));
+    assert_eq!(
+        render(&normalized, Some(QuoteStyle::Bracket)),
+        render(sql, Some(QuoteStyle::Bracket))
+    );
+}
+
+#[test]
+fn empty_argument_list() {
+    round_trip("SELECT date ();");
+    round_trip("SELECT changes ();");
+    round_trip("SELECT * FROM t WHERE d < date () AND changes () > 0;");
+    // keyword, not a function call
+    round_trip("SELECT CURRENT_TIMESTAMP;");
+    let sql = "SELECT date(), changes(), current_timestamp";
+    let cmd = Parser::new(sql.as_bytes()).next().unwrap().unwrap();
+    let Cmd::Stmt(Stmt::Select(select)) = cmd else {
+        panic!("unexpected {:?}", cmd)
+    };
+    let OneSelect::Select { columns, .. } = select.body.select else {
+        panic!()
+    };
+    assert!(matches!(
+        columns[0],
+        ResultColumn::Expr(Expr::FunctionCall { args: None, .. }, None)
+    ));
+    assert!(matches!(
+        columns[2],
+        ResultColumn::Expr(Expr::Literal(Literal::CurrentTimestamp), None)
+    ));
+}
+
+#[test]
+fn cancellation_and_budget() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let sql = format!("SELECT 1; SELECT 1{}; SELECT 2;", " + 1".repeat(100_000));
+    let flag = Arc::new(AtomicBool::new(false));
+    let mut parser = Parser::new(sql.as_bytes());
+    parser.cancel_flag(flag.clone());
+    assert!(parser.next().unwrap().is_some());
+    flag.store(true, Ordering::Relaxed);
+    let err = parser.next().unwrap_err();
+    assert!(matches!(err, Error::Cancelled(_)), "{}", err);
+    // aborted within the first tokens of the statement
+    assert!(err.offset().unwrap() < 10 + 64 * 4, "{:?}", err.offset());
+
+    let options = ParserOptions {
+        max_tokens: Some(1_000),
+        ..ParserOptions::default()
+    };
+    let mut parser = Parser::with_options(sql.as_bytes(), options);
+    assert!(parser.next().unwrap().is_some());
+    let err = parser.next().unwrap_err();
+    assert!(matches!(err, Error::BudgetExceeded(1_000, _)), "{}", err);
+    assert!(err.to_string().starts_with("too many tokens (max 1000)"));
+    let options = ParserOptions {
+        max_tokens: Some(8),
+        ..ParserOptions::default()
+    };
+    let mut parser = Parser::with_options("SELECT 1; SELECT 2;".as_bytes(), options);
+    assert!(parser.next().unwrap().is_some());
+    assert!(parser.next().unwrap().is_some());
+}
+
+#[test]
+fn max_statements() {
+    let sql = "SELECT 1;; SELECT 2;\n;SELECT 3; SELECT 4";
+    let options = ParserOptions {
+        max_statements: Some(2),
+        ..ParserOptions::default()
+    };
+    let mut parser = Parser::with_options(sql.as_bytes(), options);
+    assert!(parser.next().unwrap().is_some());
+    assert!(parser.next().unwrap().is_some());
+    let err = parser.next().unwrap_err();
+    assert!(matches!(err, Error::TooManyStatements(2, _)), "{}", err);
+    assert!(err.to_string().starts_with("too many statements (max 2)"));
+    // rejected on its first token
+    assert_eq!(err.line_column(), Some((2, 8)));
+    // unlimited by default
+    let mut parser = Parser::new(sql.as_bytes());
+    for _ in 0..4 {
+        assert!(parser.next().unwrap().is_some());
+    }
+    assert!(parser.next().unwrap().is_none());
+}
+
+#[test]
+fn parenthesized_operands() {
+    // user parentheses are kept, exactly once
+    round_trip("SELECT (a + b);");
+    round_trip("SELECT (a + b) * c;");
+    round_trip("SELECT ((a + b)) * c;");
+    round_trip("SELECT a + b * c, a - b - c, a - (b - c);");
+    round_trip("SELECT * FROM t WHERE (a OR b) AND c;");
+    // operands built without `Expr::Parenthesized` are parenthesized as needed
+    let id = |name: &str| Box::new(Expr::Id(Id(name.to_owned())));
+    let sum = Expr::Binary(id("a"), Operator::Add, id("b"));
+    let product = Expr::Binary(Box::new(sum.clone()), Operator::Multiply, id("c"));
+    assert_eq!(product.to_string(), "(a + b) * c");
+    let product = Expr::Binary(id("c"), Operator::Multiply, Box::new(sum.clone()));
+    assert_eq!(product.to_string(), "c * (a + b)");
+    let difference = Expr::Binary(id("c"), Operator::Substract, Box::new(sum.clone()));
+    assert_eq!(difference.to_string(), "c - (a + b)");
+    let sum = Expr::Binary(Box::new(sum), Operator::Substract, id("c"));
+    assert_eq!(sum.to_string(), "a + b - c");
+    let parenthesized = Expr::Parenthesized(vec![sum]);
+    let product = Expr::Binary(Box::new(parenthesized), Operator::Multiply, id("d"));
+    assert_eq!(product.to_string(), "(a + b - c) * d");
+    // operands of BETWEEN, LIKE and COLLATE
+    let reparse = |expr: &Expr| where_clause(&format!("SELECT 1 WHERE {}", expr));
+    let sum = Expr::Binary(id("a"), Operator::Add, id("b"));
+    let and = Expr::Binary(id("x"), Operator::And, id("y"));
+    let or = Expr::Binary(id("b"), Operator::Or, id("c"));
+    let between = Expr::between(sum.clone(), false, and.clone(), *id("z"));
+    assert_eq!(between.to_string(), "a + b BETWEEN (x AND y) AND z");
+    match reparse(&between) {
+        Expr::Between {
+            lhs, start, end, ..
+        } => {
+            assert_eq!(*lhs, sum);
+            assert_eq!(*start, Expr::Parenthesized(vec![and.clone()]));
+            assert_eq!(end, id("z"));
+        }
+        expr => panic!("unexpected expression: {:?}", expr),
+    }
+    let between = Expr::between(*id("a"), true, *id("x"), or.clone());
+    assert_eq!(between.to_string(), "a NOT BETWEEN x AND (b OR c)");
+    assert!(matches!(reparse(&between), Expr::Between { .. }));
+    let like = Expr::like(*id("a"), false, LikeOperator::Like, or.clone(), Some(and));
+    assert_eq!(like.to_string(), "a LIKE (b OR c) ESCAPE (x AND y)");
+    match reparse(&like) {
+        Expr::Like { rhs, escape, .. } => {
+            assert_eq!(*rhs, Expr::Parenthesized(vec![or]));
+            assert!(matches!(escape.as_deref(), Some(Expr::Parenthesized(_))));
+        }
+        expr => panic!("unexpected expression: {:?}", expr),
+    }
+    let collate = Expr::Collate(Box::new(sum.clone()), "nocase".to_owned());
+    assert_eq!(collate.to_string(), "(a + b) COLLATE nocase");
+    match reparse(&collate) {
+        Expr::Collate(expr, _) => assert_eq!(*expr, Expr::Parenthesized(vec![sum])),
+        expr => panic!("unexpected expression: {:?}", expr),
+    }
+}
+
+#[test]
+fn not_precedence() {
+    let sqls = [
+        "SELECT * FROM t WHERE NOT a = b;",
+        "SELECT * FROM t WHERE NOT (a = b);",
+        "SELECT * FROM t WHERE NOT a AND b;",
+        "SELECT * FROM t WHERE NOT (a AND b);",
+        "SELECT * FROM t WHERE NOT a OR NOT b;",
+        "SELECT * FROM t WHERE NOT NOT a;",
+        "SELECT * FROM t WHERE NOT EXISTS (SELECT 1);",
+        "SELECT * FROM t WHERE NOT EXISTS (SELECT 1) AND a;",
+        "SELECT * FROM t WHERE a NOT IN (1, 2);",
+        "SELECT * FROM t WHERE NOT a IN (1, 2);",
+        "SELECT * FROM t WHERE (NOT a) IN (1, 2);",
+        "SELECT * FROM t WHERE NOT a BETWEEN 1 AND 2;",
+        "SELECT * FROM t WHERE (NOT a) = b;",
+    ];
+    let parse_cmd = |sql: &str| Parser::new(sql.as_bytes()).next().unwrap().unwrap();
+    for sql in sqls {
+        round_trip(sql);
+        let ast = parse_cmd(sql);
+        assert_eq!(parse_cmd(&ast.to_string()), ast, "{sql}");
+    }
+    // operands built without `Expr::Parenthesized` are parenthesized as needed
+    let id = |name: &str| Box::new(Expr::Id(Id(name.to_owned())));
+    let not = |expr: Box<Expr>| Box::new(Expr::Unary(UnaryOperator::Not, expr));
+    let and = Expr::Binary(id("a"), Operator::And, id("b"));
+    assert_eq!(not(Box::new(and)).to_string(), "NOT (a AND b)");
+    let equals = Expr::Binary(id("a"), Operator::Equals, id("b"));
+    assert_eq!(not(Box::new(equals)).to_string(), "NOT a = b");
+    let equals = Expr::Binary(not(id("a")), Operator::Equals, id("b"));
+    assert_eq!(equals.to_string(), "(NOT a) = b");
+    let equals = Expr::Binary(id("a"), Operator::Equals, not(id("b")));
+    assert_eq!(equals.to_string(), "a = (NOT b)");
+    let and = Expr::Binary(not(id("a")), Operator::And, id("b"));
+    assert_eq!(and.to_string(), "NOT a AND b");
+    let in_list = Expr::in_list(
+        *not(id("a")),
+        false,
+        Some(vec![Expr::Id(Id("b".to_owned()))]),
+    );
+    assert_eq!(in_list.to_string(), "(NOT a) IN (b)");
+    let is_null = Expr::IsNull(not(id("a")));
+    assert_eq!(is_null.to_string(), "(NOT a) ISNULL");
+    let negative = Expr::Unary(
+        UnaryOperator::Negative,
+        Box::new(Expr::Binary(id("a"), Operator::Add, id("b"))),
+    );
+    assert_eq!(negative.to_string(), "- (a + b)");
+}
+
+#[test]
+fn comma_and_cross_joins() {
+    round_trip("SELECT * FROM a, b;");
+    round_trip("SELECT * FROM a CROSS JOIN b;");
+    round_trip("SELECT * FROM a, b CROSS JOIN c, d ON d.x = a.x;");
+    let options = FormatOptions {
+        cross_join: true,
+        ..FormatOptions::default()
+    };
+    let render = |sql: &str| {
+        let cmd = Parser::new(sql.as_bytes()).next().unwrap().unwrap();
+        cmd.display_with(options).to_string()
+    };
+    assert_eq!(
+        render("SELECT * FROM a, b CROSS JOIN c, d ON d.x = a.x"),
+        "SELECT * FROM a CROSS JOIN b CROSS JOIN c CROSS JOIN d ON d.x = a.x;"
+    );
+    // other commas are unchanged
+    assert_eq!(
+        render("SELECT x, y FROM a, (SELECT 1, 2) s"),
+        "SELECT x, y FROM a CROSS JOIN (SELECT 1, 2) s;"
+    );
+}
+
+#[test]
+fn operator_spacing() {
+    let render = |sql: &str, space_around_operators: bool| {
+        let options = FormatOptions {
+            space_around_operators,
+            ..FormatOptions::default()
+        };
+        let cmd = Parser::new(sql.as_bytes()).next().unwrap().unwrap();
+        cmd.display_with(options).to_string()
+    };
+    let sql = "SELECT a+b*2, x->>'$.y' FROM t WHERE a=1 AND b<>c OR d||e IS NOT f";
+    assert_eq!(
+        render(sql, true),
+        "SELECT a + b * 2, x ->> '$.y' FROM t WHERE a = 1 AND b <> c OR d || e IS NOT f;"
+    );
+    assert_eq!(
+        render(sql, false),
+        "SELECT a+b*2, x->>'$.y' FROM t WHERE a=1 AND b<>c OR d || e IS NOT f;"
+    );
+    // `--` would start a comment
+    assert_eq!(
+        render("SELECT a - -b, (a-b)*c", false),
+        "SELECT a- - b, (a-b)*c;"
+    );
+    let tight = render(sql, false);
+    assert_eq!(
+        Parser::new(tight.as_bytes()).next().unwrap(),
+        Parser::new(sql.as_bytes()).next().unwrap()
+    );
+}
+
+#[test]
+fn deterministic_statements() {
+    fn is_deterministic(sql: &str) -> bool {
+        let cmd = Parser::new(sql.as_bytes()).next().unwrap().unwrap();
+        cmd.is_deterministic()
+    }
+    assert!(is_deterministic(
+        "SELECT abs(x), upper(y) FROM t WHERE z > 1"
+    ));
+    assert!(is_deterministic("SELECT date('2024-01-01', '+1 day')"));
+    assert!(is_deterministic(
+        "SELECT strftime('%Y', d, '+1 day') FROM t"
+    ));
+    assert!(is_deterministic("SELECT timediff(a, b) FROM t"));
+    assert!(is_deterministic("SELECT my_random() FROM t"));
+    assert!(!is_deterministic("SELECT RANDOM()"));
+    assert!(!is_deterministic("SELECT * FROM t ORDER BY random()"));
+    assert!(!is_deterministic("INSERT INTO t VALUES (randomblob(16))"));
+    assert!(!is_deterministic("SELECT last_insert_rowid()"));
+    assert!(!is_deterministic("SELECT changes(), total_changes()"));
+    assert!(!is_deterministic("SELECT datetime('now')"));
+    assert!(!is_deterministic("SELECT date(d, 'LocalTime') FROM t"));
+    assert!(!is_deterministic("SELECT julianday()"));
+    // without time value
+    assert!(!is_deterministic("SELECT strftime('%Y')"));
+    assert!(!is_deterministic("SELECT STRFTIME('%s') - d FROM t"));
+    // depending on the time zone
+    assert!(!is_deterministic("SELECT strftime('%Y', d, 'utc') FROM t"));
+    assert!(!is_deterministic("SELECT unixepoch(d, ' UTC ') FROM t"));
+    assert!(!is_deterministic("SELECT strftime('%H', 'now') FROM t"));
+    assert!(!is_deterministic("SELECT timediff('now', d) FROM t"));
+    assert!(!is_deterministic("SELECT CURRENT_TIMESTAMP"));
+    // in subqueries
+    assert!(!is_deterministic(
+        "SELECT * FROM t WHERE x IN (SELECT x FROM u WHERE d < date('now'))"
+    ));
+    assert!(!is_deterministic(
+        "DELETE FROM t WHERE id = (SELECT id FROM t ORDER BY random() LIMIT 1)"
+    ));
+}
+
+#[test]
+fn signed_pragma_values() {
+    fn value(sql: &str) -> Expr {
+        let cmd = Parser::new(sql.as_bytes()).next().unwrap().unwrap();
+        let Cmd::Stmt(Stmt::Pragma(_, Some(PragmaBody::Equals(value) | PragmaBody::Call(value)))) =
+            cmd
+        else {
+            panic!("unexpected command: {:?}", cmd)
+        };
+        value
+    }
+    let number = |n: &str| Box::new(Expr::Literal(Literal::Numeric(n.to_owned())));
+    assert_eq!(
+        value("PRAGMA cache_size = -2000"),
+        Expr::Unary(UnaryOperator::Negative, number("2000"))
+    );
+    assert_eq!(
+        value("PRAGMA main.cache_size(+2000)"),
+        Expr::Unary(UnaryOperator::Positive, number("2000"))
+    );
+    assert_eq!(value("PRAGMA cache_size = 2000"), *number("2000"));
+    assert_eq!(
+        value("PRAGMA busy_timeout(-1.5)"),
+        Expr::Unary(UnaryOperator::Negative, number("1.5"))
+    );
+    // the sign is kept when rendering
+    round_trip("PRAGMA cache_size = - 2000;");
+    round_trip("PRAGMA main.cache_size (+ 2000);");
+    round_trip("PRAGMA cache_size = 2000;");
+}
+
+#[test]
+fn offset_after_statement() {
+    let sql = "SELECT 1; /* c */ SELECT 2 ;\nSELECT 3";
+    let mut parser = Parser::new(sql.as_bytes());
+    assert_eq!(parser.offset(), 0);
+    parser.next().unwrap().unwrap();
+    assert_eq!(parser.offset(), "SELECT 1;".len());
+    assert_eq!(&sql[parser.offset()..], " /* c */ SELECT 2 ;\nSELECT 3");
+    parser.next().unwrap().unwrap();
+    assert_eq!(&sql[parser.offset()..], "\nSELECT 3");
+    parser.next().unwrap().unwrap();
+    assert_eq!(parser.offset(), sql.len());
+    assert!(parser.next().unwrap().is_none());
+}
+
+#[test]
+fn expr_has_subquery() {
+    assert!(!where_clause("SELECT * FROM t WHERE a = 1 AND b IN (1, 2)").has_subquery());
+    assert!(!where_clause("SELECT * FROM t WHERE x IN u AND f(y, z + 1)").has_subquery());
+    assert!(where_clause("SELECT * FROM t WHERE a = (SELECT max(a) FROM t)").has_subquery());
+    assert!(where_clause("SELECT * FROM t WHERE NOT EXISTS (SELECT 1 FROM u)").has_subquery());
+    assert!(where_clause("SELECT * FROM t WHERE a IN (SELECT a FROM u)").has_subquery());
+    // nested
+    assert!(where_clause("SELECT * FROM t WHERE abs(1 + (SELECT 2)) > 0").has_subquery());
+    assert!(where_clause(
+        "SELECT * FROM t WHERE CASE WHEN a THEN coalesce(b, (SELECT c FROM u)) END"
+    )
+    .has_subquery());
+    // in a window frame bound
+    assert!(where_clause(
+        "SELECT * FROM t WHERE sum(a) OVER (ROWS BETWEEN 1 PRECEDING AND (SELECT n FROM u) FOLLOWING) > 0"
+    )
+    .has_subquery());
+    assert!(!where_clause(
+        "SELECT * FROM t WHERE sum(a) OVER (ROWS BETWEEN n PRECEDING AND CURRENT ROW) > 0"
+    )
+    .has_subquery());
+    // visitors traverse frame bounds
+    let sql = "SELECT sum(a) OVER (ROWS BETWEEN t.n PRECEDING AND abs(m) FOLLOWING) FROM t";
+    let cmd = Parser::new(sql.as_bytes()).next().unwrap().unwrap();
+    let found =
+        cmd.find_expr(|expr| matches!(expr, Expr::FunctionCall { name, .. } if name.0 == "abs"));
+    assert_eq!(found.unwrap().to_string(), "abs (m)");
+    assert_eq!(
+        rename_table(sql),
+        "SELECT sum (a) OVER (ROWS BETWEEN tenant_t.n PRECEDING AND abs (m) FOLLOWING) FROM tenant_t;"
+    );
+}
+
+#[test]
+fn bare_percent() {
+    fn parse(sql: &str, reject_bare_percent: bool) -> Result<Option<Cmd>, Error> {
+        let options = ParserOptions {
+            lexer: LexerOptions {
+                reject_bare_percent,
+                ..LexerOptions::default()
+            },
+            ..ParserOptions::default()
+        };
+        Parser::with_options(sql.as_bytes(), options).next()
+    }
+    // modulo
+    for sql in [
+        "SELECT a % 2, 7%3, (a + 1) % b, 'x' % 2, ?1 % 2 FROM t",
+        "SELECT * FROM t WHERE key % 2 = 0 AND CASE WHEN a THEN 1 END % 2",
+    ] {
+        let cmd = parse(sql, false).unwrap().unwrap();
+        assert_eq!(parse(sql, true).unwrap().unwrap(), cmd);
+    }
+    // templating placeholders
+    for sql in [
+        "SELECT * FROM t WHERE a = %s",
+        "INSERT INTO t VALUES (%s, %(name)s)",
+        "SELECT * FROM t WHERE a = 100 %% 7",
+        "SELECT * FROM t LIMIT %d",
+    ] {
+        // a syntax error near the next token by default
+        assert!(matches!(parse(sql, false), Err(Error::SyntaxError(..))));
+        let err = parse(sql, true).unwrap_err();
+        assert!(matches!(err, Error::UnrecognizedToken(Some(_))), "{}", sql);
+        assert_eq!(&sql[err.offset().unwrap()..][..1], "%");
+    }
+}
+
+#[test]
+fn canonical_table_definitions() {
+    fn canonical(sql: &str) -> Cmd {
+        let mut cmd = Parser::new(sql.as_bytes()).next().unwrap().unwrap();
+        canonicalize(&mut cmd);
+        cmd
+    }
+    let equivalent = [
+        (
+            "CREATE TABLE t (a INTEGER PRIMARY KEY, b TEXT) STRICT, WITHOUT ROWID",
+            "CREATE TABLE t (a integer PRIMARY KEY ASC, b text) WITHOUT ROWID, STRICT",
+        ),
+        (
+            "CREATE TABLE t (a NOT NULL DEFAULT 0 UNIQUE CHECK (a > 0))",
+            "CREATE TABLE t (a CHECK (a > 0) DEFAULT 0 UNIQUE ON CONFLICT ABORT NOT NULL)",
+        ),
+        (
+            "CREATE TABLE t (a REFERENCES p ON DELETE CASCADE ON UPDATE SET NULL DEFERRABLE NOT NULL)",
+            "CREATE TABLE t (a NOT NULL REFERENCES p ON UPDATE SET NULL ON DELETE CASCADE ON UPDATE NO ACTION DEFERRABLE)",
+        ),
+        (
+            "CREATE TABLE t (a, b, PRIMARY KEY (a, b), CHECK (a <> b), FOREIGN KEY (b) REFERENCES p)",
+            "CREATE TABLE t (a, b, FOREIGN KEY (b) REFERENCES p, CHECK (a <> b), PRIMARY KEY (a ASC, b))",
+        ),
+        ("ALTER TABLE t ADD c int DEFAULT 1 NOT NULL", "ALTER TABLE t ADD c INT NOT NULL DEFAULT 1"),
+    ];
+    for (a, b) in equivalent {
+        assert_ne!(
+            Parser::new(a.as_bytes()).next().unwrap(),
+            Parser::new(b.as_bytes()).next().unwrap()
+        );
+        assert_eq!(canonical(a), canonical(b), "{} / {}", a, b);
+    }
+    assert_eq!(
+        canonical("CREATE TABLE t (a REFERENCES p DEFERRABLE INITIALLY DEFERRED DEFAULT 1 PRIMARY KEY DESC)")
+            .to_string(),
+        "CREATE TABLE t (a PRIMARY KEY DESC DEFAULT 1 REFERENCES p DEFERRABLE INITIALLY DEFERRED);"
+    );
+    // not equivalent
+    assert_ne!(
+        canonical("CREATE TABLE t (a PRIMARY KEY DESC)"),
+        canonical("CREATE TABLE t (a PRIMARY KEY)")
+    );
+    assert_ne!(
+        canonical("CREATE TABLE t (a UNIQUE ON CONFLICT IGNORE)"),
+        canonical("CREATE TABLE t (a UNIQUE)")
+    );
+}
+
+#[test]
+fn cte_circular_references() {
+    // recursive CTEs, with or without `RECURSIVE`
+    assert!(validate(
+        "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 3) SELECT x FROM c"
+    )
+    .is_empty());
+    assert!(validate(
+        "WITH c(x) AS (SELECT 1 UNION SELECT x + 1 FROM t, c UNION SELECT x FROM c) SELECT x FROM c"
+    )
+    .is_empty());
+    // references to other CTEs, even defined later
+    assert!(validate("WITH a AS (SELECT * FROM b), b AS (SELECT 1) SELECT * FROM a").is_empty());
+    assert!(validate("WITH a AS (SELECT 1) DELETE FROM t WHERE x IN a").is_empty());
+    // qualified names are tables
+    assert!(validate("WITH t AS (SELECT * FROM main.t) SELECT * FROM t").is_empty());
+
+    assert_eq!(
+        validate("WITH a AS (SELECT * FROM a) SELECT * FROM a"),
+        ["circular reference: a"]
+    );
+    assert_eq!(
+        validate("WITH RECURSIVE a(x) AS (SELECT x FROM a UNION ALL SELECT 1) SELECT * FROM a"),
+        ["circular reference: a"]
+    );
+    assert_eq!(
+        validate("WITH a(x) AS (SELECT 1 UNION ALL SELECT (SELECT x FROM a)) SELECT * FROM a"),
+        ["circular reference: a"]
+    );
+    assert_eq!(
+        validate("WITH a(x) AS (SELECT 1 INTERSECT SELECT x FROM a) SELECT * FROM a"),
+        ["circular reference: a"]
+    );
+    assert_eq!(
+        validate("WITH a AS (SELECT * FROM b), b AS (SELECT * FROM \"A\") SELECT * FROM a"),
+        ["circular reference: a", "circular reference: b"]
+    );
+    // nested `WITH` clauses
+    assert_eq!(
+        validate("INSERT INTO t WITH a AS (SELECT * FROM a) SELECT * FROM a"),
+        ["circular reference: a"]
+    );
+    assert_eq!(
+        validate("SELECT * FROM (WITH a AS (SELECT * FROM a) SELECT * FROM a)"),
+        ["circular reference: a"]
+    );
+}
+
+#[test]
+fn debug_tree() {
+    let sql = "WITH r AS (SELECT id FROM u) \
+        SELECT DISTINCT t.a AS x, count(*), f(b + 1) \
+        FROM t JOIN r ON r.id = t.id LEFT JOIN (SELECT 1) s \
+        WHERE t.c BETWEEN 1 AND 2 AND t.d NOT IN (1, 2) \
+        GROUP BY 1 HAVING sum(b) > 0 \
+        UNION ALL SELECT 1, 2, 3 ORDER BY 1 DESC LIMIT 10";
+    let cmd = Parser::new(sql.as_bytes()).next().unwrap().unwrap();
+    assert_eq!(
+        cmd.debug_tree(),
+        r"Select
+  With
+    Cte r
+      Select
+        Columns
+          Column
+            Id id
+        From
+          Table u
+  Distinct
+  Columns
+    Column AS x
+      Qualified t.a
+    Column
+      FunctionCallStar count
+    Column
+      FunctionCall f
+        Binary Add
+          Id b
+          Literal 1
+  From
+    Table t
+    Join
+      Table r
+      On
+        Binary Equals
+          Qualified r.id
+          Qualified t.id
+    Join LEFT
+      Subquery AS s
+        Select
+          Columns
+            Column
+              Literal 1
+  Where
+    Binary And
+      Between
+        Qualified t.c
+        Literal 1
+        Literal 2
+      InList NOT
+        Qualified t.d
+        List
+          Literal 1
+          Literal 2
+  GroupBy
+    Literal 1
+  Having
+    Binary Greater
+      FunctionCall sum
+        Id b
+      Literal 0
+  UnionAll
+    Columns
+      Column
+        Literal 1
+      Column
+        Literal 2
+      Column
+        Literal 3
+  OrderBy
+    Sort DESC
+      Literal 1
+  Limit
+    Literal 10
+"
+    );
+    let cmd = Parser::new(b"EXPLAIN CREATE INDEX i ON t (a)".as_slice())
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        cmd.debug_tree(),
+        "Explain\n  CreateIndex CREATE INDEX i ON t (a)\n"
+    );
+}
+
+#[test]
+fn union_column_counts() {
+    let counts = |sql: &str| match Parser::new(sql.as_bytes()).next().unwrap().unwrap() {
+        Cmd::Stmt(Stmt::Select(select)) => select.union_column_counts(),
+        cmd => panic!("unexpected {cmd:?}"),
+    };
+    assert_eq!(counts("SELECT a, b FROM t"), [2]);
+    assert_eq!(
+        counts("SELECT a, b FROM t UNION SELECT c, d FROM u UNION ALL VALUES (1, 2), (3, 4)"),
+        [2, 2, 2]
+    );
+    // unbalanced
+    assert_eq!(
+        counts("SELECT * FROM t UNION SELECT a, b FROM u EXCEPT SELECT t.*, 1 FROM t"),
+        [1, 2, 2]
+    );
+}
+
+#[test]
+fn cartesian_products() {
+    fn lint(sql: &str) -> Vec<String> {
+        let cmd = Parser::new(sql.as_bytes()).next().unwrap().unwrap();
+        let Cmd::Stmt(Stmt::Select(select)) = cmd else {
+            panic!("unexpected {:?}", cmd)
+        };
+        lint_cartesian(&select)
+            .into_iter()
+            .map(|d| d.message)
+            .collect()
+    }
+    for sql in [
+        "SELECT * FROM a JOIN b ON a.id = b.a_id",
+        "SELECT * FROM a LEFT JOIN b USING (id)",
+        "SELECT * FROM a CROSS JOIN b",
+        "SELECT * FROM a NATURAL JOIN b",
+        "SELECT * FROM a, b WHERE a.id = b.a_id",
+        "SELECT * FROM a x, b y WHERE x.n > 0 AND (y.a_id = x.id)",
+        "SELECT * FROM a, b WHERE a.id = a_id",
+        "SELECT * FROM t",
+    ] {
+        assert!(lint(sql).is_empty(), "{}", sql);
+    }
+    assert_eq!(
+        lint("SELECT * FROM a JOIN b"),
+        vec!["no join condition for b: Cartesian product"]
+    );
+    assert_eq!(
+        lint("SELECT * FROM a, b x WHERE a.n > 0 AND x.n = 1"),
+        vec!["no join condition for x: Cartesian product"]
+    );
+    assert_eq!(
+        lint("SELECT * FROM a JOIN b ON a.id = b.id, c"),
+        vec!["no join condition for c: Cartesian product"]
+    );
+    assert_eq!(
+        lint("SELECT * FROM t WHERE x IN (SELECT 1 FROM a INNER JOIN b)"),
+        vec!["no join condition for b: Cartesian product"]
+    );
+}
+
+#[test]
+fn statement_ranges() {
+    let sql =
+        "-- migration\nCREATE TABLE t (x);\n\n;INSERT INTO t\nVALUES ('a\nb');  SELECT *\n  FROM t";
+    let mut parser = Parser::new(sql.as_bytes());
+    let mut statements = vec![];
+    while let Some((cmd, range, line)) = parser.next_with_range().unwrap() {
+        statements.push((cmd.to_string(), &sql[range], line));
+    }
+    assert_eq!(
+        statements,
+        vec![
+            ("CREATE TABLE t (x);".to_owned(), "CREATE TABLE t (x);", 2),
+            (
+                "INSERT INTO t VALUES ('a\nb');".to_owned(),
+                "INSERT INTO t\nVALUES ('a\nb');",
+                4
+            ),
+            ("SELECT * FROM t;".to_owned(), "SELECT *\n  FROM t", 6),
+        ]
+    );
+}
+
+#[test]
+fn structural_eq_options() {
+    fn eq(a: &str, b: &str, options: CompareOptions) -> bool {
+        let a = Parser::new(a.as_bytes()).next().unwrap().unwrap();
+        let b = Parser::new(b.as_bytes()).next().unwrap().unwrap();
+        a.structural_eq_with(&b, options)
+    }
+    let default = CompareOptions::default();
+    let collated = (
+        "SELECT * FROM t WHERE x COLLATE nocase = 'a'",
+        "SELECT * FROM t WHERE x = 'a'",
+    );
+    assert!(!eq(collated.0, collated.1, default));
+    let options = CompareOptions {
+        collation: false,
+        ..default
+    };
+    assert!(eq(collated.0, collated.1, options));
+
+    let parenthesized = ("SELECT (a + b) * ((c))", "SELECT (a + b) * c");
+    assert!(!eq(parenthesized.0, parenthesized.1, default));
+    let options = CompareOptions {
+        parentheses: false,
+        ..default
+    };
+    assert!(eq(parenthesized.0, parenthesized.1, options));
+    // precedence is still significant
+    assert!(!eq("SELECT (a + b) * c", "SELECT a + b * c", options));
+    assert!(eq("SELECT (a * b) + c", "SELECT a * b + c", options));
+    assert!(!eq("SELECT (x COLLATE nocase)", "SELECT x", options));
+    let options = CompareOptions {
+        parentheses: false,
+        collation: false,
+        ..default
+    };
+    assert!(eq("SELECT (x COLLATE nocase)", "SELECT x", options));
+
+    let aliased = ("SELECT a.x FROM t a", "SELECT b.x FROM t b");
+    assert!(eq(aliased.0, aliased.1, default));
+    let options = CompareOptions {
+        aliases: true,
+        ..default
+    };
+    assert!(!eq(aliased.0, aliased.1, options));
+    assert!(eq(aliased.0, aliased.0, options));
+}
+
+#[test]
+fn like_prefix() {
+    fn prefix(sql: &str) -> Option<(String, String)> {
+        let expr = where_clause(sql);
+        expr.like_prefix()
+            .map(|(column, prefix)| (column.to_string(), prefix))
+    }
+    let some = |column: &str, prefix: &str| Some((column.to_owned(), prefix.to_owned()));
+    assert_eq!(
+        prefix("SELECT * FROM t WHERE name LIKE 'abc%'"),
+        some("name", "abc")
+    );
+    assert_eq!(
+        prefix("SELECT * FROM t WHERE t.name LIKE 'it''s_%'"),
+        some("t.name", "it's")
+    );
+    assert_eq!(
+        prefix("SELECT * FROM t WHERE name LIKE '10\\%\\_off%' ESCAPE '\\'"),
+        some("name", "10%_off")
+    );
+    assert_eq!(
+        prefix("SELECT * FROM t WHERE path GLOB '/usr/*'"),
+        some("path", "/usr/")
+    );
+    // GLOB wildcards are literal in a LIKE pattern
+    assert_eq!(
+        prefix("SELECT * FROM t WHERE name LIKE 'a*b%'"),
+        some("name", "a*b")
+    );
+    assert_eq!(
+        prefix("SELECT * FROM t WHERE name LIKE '[ab]%'"),
+        some("name", "[ab]")
+    );
+    assert_eq!(
+        prefix("SELECT * FROM t WHERE name LIKE 'what?%'"),
+        some("name", "what?")
+    );
+    assert_eq!(
+        prefix("SELECT * FROM t WHERE path GLOB 'a%b_c?'"),
+        some("path", "a%b_c")
+    );
+    for sql in [
+        "SELECT * FROM t WHERE name LIKE '%abc'",
+        "SELECT * FROM t WHERE name LIKE '_bc%'",
+        "SELECT * FROM t WHERE name NOT LIKE 'abc%'",
+        "SELECT * FROM t WHERE name LIKE ?",
+        "SELECT * FROM t WHERE name LIKE 'a' || '%'",
+        "SELECT * FROM t WHERE lower(name) LIKE 'abc%'",
+        "SELECT * FROM t WHERE name LIKE 'abc%' ESCAPE 'ab'",
+        "SELECT * FROM t WHERE path GLOB '[a-z]*'",
+        "SELECT * FROM t WHERE name = 'abc'",
+    ] {
+        assert_eq!(prefix(sql), None, "{}", sql);
+    }
+}
+
+#[test]
+fn synthetic_tokens() {
+    use crate::lexer::Scanner;
+
+    let sql = "SELECT x'ab', name FROM t WHERE id > ?1 ORDER BY 2;";
+    let mut scanner = Scanner::new(sql.as_bytes(), Tokenizer::new());
+    let mut scanned: Vec<(Vec<u8>, TokenType)> = vec![];
+    while let Some((text, token_type)) = scanner.scan().unwrap() {
+        scanned.push((text.to_vec(), token_type));
+    }
+    // splice `LIMIT 10` before the `;`
+    let mut tokens: Vec<Token> = scanned.iter().map(|(t, ty)| (t.as_slice(), *ty)).collect();
+    let semi = tokens.pop().unwrap();
+    tokens.extend([
+        (&b"LIMIT"[..], TokenType::TK_LIMIT),
+        (b"10", TokenType::TK_INTEGER),
+    ]);
+    tokens.push(semi);
+    let spliced = tokens_to_sql(tokens);
+    assert_eq!(
+        spliced,
+        "SELECT X'ab' , name FROM t WHERE id > ?1 ORDER BY 2 LIMIT 10 ;"
+    );
+    let cmd = Parser::new(spliced.as_bytes()).next().unwrap().unwrap();
+    assert_eq!(
+        cmd.to_string(),
+        "SELECT X'ab', name FROM t WHERE id > ?1 ORDER BY 2 LIMIT 10;"
+    );
+}
+
+#[test]
+fn cst_round_trip() {
+    use super::cst::{self, Element, NodeKind, TriviaKind};
+
+    let sql = "-- header\r\nselect  A.x, (1 + (2))  /* two */ FROM t a;;\n\
+               CREATE TRIGGER tr AFTER INSERT ON t BEGIN\n  DELETE FROM u;\nEND;\n\
+               insert into t values (x'AB', ?1)  -- trailing\n";
+    let script = cst::parse(sql).unwrap();
+    assert_eq!(script.kind, NodeKind::Script);
+    assert_eq!(script.to_string(), sql);
+    assert_eq!(
+        script.children[0],
+        Element::Trivia(TriviaKind::Comment, "-- header\r\n".to_owned())
+    );
+    let statements: Vec<_> = script.statements().collect();
+    assert_eq!(statements.len(), 4);
+    assert_eq!(
+        statements[0].to_string(),
+        "select  A.x, (1 + (2))  /* two */ FROM t a;"
+    );
+    assert_eq!(statements[1].to_string(), ";");
+    assert!(statements[2].to_string().ends_with("END;"));
+    assert_eq!(
+        statements[3].to_string(),
+        "insert into t values (x'AB', ?1)"
+    );
+    // nested parentheses
+    let groups: Vec<_> = statements[0]
+        .children
+        .iter()
+        .filter_map(|child| match child {
+            Element::Node(node) => Some(node),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].kind, NodeKind::Parenthesized);
+    assert_eq!(groups[0].to_string(), "(1 + (2))");
+    assert_eq!(groups[0].tokens().len(), 7);
+    // projection onto the AST
+    assert_eq!(
+        statements[0].to_ast().unwrap().unwrap().to_string(),
+        "SELECT A.x, (1 + (2)) FROM t a;"
+    );
+    assert_eq!(statements[1].to_ast().unwrap(), None);
+    assert!(matches!(
+        statements[2].to_ast().unwrap(),
+        Some(Cmd::Stmt(Stmt::CreateTrigger { .. }))
+    ));
+    assert!(cst::parse("SELECT 'a").is_err());
+}
+
+#[test]
+fn validate_insert_arity() {
+    for sql in [
+        "INSERT INTO t (a, b) VALUES (1, 2)",
+        "INSERT INTO t (a, b) VALUES (1, 2), (3, 4)",
+        "INSERT INTO t VALUES (1)",
+        "INSERT INTO t (a) SELECT 1, 2",
+        "INSERT INTO t (a) DEFAULT VALUES",
+    ] {
+        assert!(validate(sql).is_empty(), "{}", sql);
+    }
+    assert_eq!(
+        validate("INSERT INTO t (a, b) VALUES (1)"),
+        vec!["1 values for 2 columns (row 1)"]
+    );
+    assert_eq!(
+        validate("INSERT INTO t (a) VALUES (1), (2, 3)"),
+        vec![
+            "all VALUES must have the same number of terms: row 2 has 2, expected 1",
+            "2 values for 1 columns (row 2)",
+        ]
+    );
+    // the table concerned
+    let sql = "INSERT INTO main.t (a, b) VALUES (1)";
+    let cmd = Parser::new(sql.as_bytes()).next().unwrap().unwrap();
+    assert_eq!(
+        cmd.validate(),
+        vec![Diagnostic {
+            message: "1 values for 2 columns (row 1)".to_owned(),
+            table: Some(Name("t".to_owned())),
+            position: None,
+        }]
+    );
+}
+
+#[test]
+fn qualify_table_references() {
+    fn qualify(sql: &str) -> String {
+        let mut cmd = Parser::new(sql.as_bytes()).next().unwrap().unwrap();
+        qualify_tables(&mut cmd, &Name("main".to_owned()));
+        cmd.to_string()
+    }
+    assert_eq!(
+        qualify("SELECT a.x FROM t a JOIN u ON a.id = u.id, aux.v WHERE x IN w"),
+        "SELECT a.x FROM main.t a JOIN main.u ON a.id = u.id, aux.v WHERE x IN main.w;"
+    );
+    assert_eq!(
+        qualify("WITH c AS (SELECT * FROM t) SELECT * FROM c, (SELECT 1 FROM u) s"),
+        "WITH c AS (SELECT * FROM main.t) SELECT * FROM c, (SELECT 1 FROM main.u) s;"
+    );
+    // CTE scope ends with its statement
+    assert_eq!(
+        qualify("SELECT * FROM (WITH c AS (SELECT 1) SELECT * FROM c) JOIN c"),
+        "SELECT * FROM (WITH c AS (SELECT 1) SELECT * FROM c) JOIN main.c;"
+    );
+    assert_eq!(
+        qualify("WITH c AS (SELECT 1) INSERT INTO t SELECT * FROM c"),
+        "WITH c AS (SELECT 1) INSERT INTO main.t SELECT * FROM c;"
+    );
+    assert_eq!(
+        qualify("UPDATE t SET x = (SELECT y FROM u) WHERE 1"),
+        "UPDATE main.t SET x = (SELECT y FROM main.u) WHERE 1;"
+    );
+    assert_eq!(
+        qualify("DELETE FROM t WHERE id IN (SELECT id FROM json_each(?))"),
+        "DELETE FROM main.t WHERE id IN (SELECT id FROM json_each (?));"
+    );
+}
+
+#[test]
+fn keywords_sorted_and_unique() {
+    let keywords = keywords();
+    assert!(keywords.windows(2).all(|w| w[0] < w[1]));
+    assert!(!is_keyword("foo"));
+    assert!(is_keyword("select"));
+}
+
+#[test]
+fn keywords_match_lexer() {
+    for keyword in keywords() {
+        assert!(is_keyword(keyword));
+        assert!(is_keyword(&keyword.to_lowercase()));
+        let mut s = Scanner::new(keyword.as_bytes(), Tokenizer::new());
+        let (_, token_type) = s.scan().unwrap().unwrap();
+        assert_ne!(TokenType::TK_ID, token_type, "{}", keyword);
+        assert_eq!(keyword_token(keyword.as_bytes()), Some(token_type));
+    }
+}
+
+#[test]
+fn sample_lexemes() {
+    let mut without_sample = vec![];
+    for code in 0..=TokenType::TK_FILTER as u16 {
+        let token_type = TokenType::from_code(code).unwrap();
+        let Some(lexeme) = token_type.sample_lexeme() else {
+            without_sample.push(token_type);
+            continue;
+        };
+        let mut s = Scanner::new(lexeme.as_bytes(), Tokenizer::new());
+        let (value, scanned) = s.scan().unwrap().unwrap();
+        assert_eq!(scanned, token_type, "{}", lexeme);
+        assert_eq!(value, lexeme.as_bytes());
+        assert!(s.scan().unwrap().is_none(), "{}", lexeme);
+    }
+    for keyword in keywords() {
+        let token_type = keyword_token(keyword.as_bytes()).unwrap();
+        assert!(token_type.sample_lexeme().is_some(), "{}", keyword);
+    }
+    assert_eq!(
+        without_sample,
+        [
+            TokenType::TK_EOF,
+            TokenType::TK_ID,
+            TokenType::TK_ANY,
+            TokenType::TK_STRING,
+            TokenType::TK_BLOB,
+            TokenType::TK_FLOAT,
+            TokenType::TK_INTEGER,
+            TokenType::TK_VARIABLE,
+        ]
+    );
+}
+
+#[test]
+fn token_text() {
+    let sql = "select \"Foo\", 'bar' || x'00' FROM t WHERE a->>'$.b' >= ?1 AND c NOT glob 1.5;";
+    let mut s = Scanner::new(sql.as_bytes(), Tokenizer::new());
+    let mut texts = vec![];
+    let mut names = vec![];
+    while let Some((value, token_type)) = s.scan().unwrap() {
+        let token = token_type.to_token(value);
+        texts.push(token_type.text(token.as_deref()).to_owned());
+        names.push(token_type.to_string());
+    }
+    assert_eq!(
+        texts,
+        [
+            "SELECT", "\"Foo\"", ",", "'bar'", "||", "00", "FROM", "t", "WHERE", "a", "->>",
+            "'$.b'", ">=", "1", "AND", "c", "NOT", "glob", "1.5", ";"
+        ]
+    );
+    assert_eq!(
+        names,
+        [
+            "SELECT",
+            "identifier",
+            ",",
+            "string",
+            "||",
+            "blob",
+            "FROM",
+            "identifier",
+            "WHERE",
+            "identifier",
+            "->",
+            "string",
+            ">=",
+            "variable",
+            "AND",
+            "identifier",
+            "NOT",
+            "LIKE",
+            "float",
+            ";"
+        ]
+    );
+    assert_eq!(TokenType::TK_EOF.to_string(), "end of input");
+}
+
+#[test]
+fn values_rows_reserved() {
+    fn rows(sql: &str) -> Vec<Vec<Expr>> {
+        match Parser::new(sql.as_bytes()).next().unwrap().unwrap() {
+            Cmd::Stmt(Stmt::Select(select)) => match select.body.select {
+                OneSelect::Values(values) => values,
+                _ => panic!("{}", sql),
+            },
+            _ => panic!("{}", sql),
+        }
+    }
+    let values = rows("VALUES (1, 2), (3, 4), (5, 6)");
+    assert_eq!(values.len(), 3);
+    assert_eq!(values.capacity(), 3);
+    // a string literal with parentheses only over-estimates
+    let values = rows("VALUES ('(', 1), (')', 2); VALUES (3)");
+    assert_eq!(values.len(), 2);
+    assert!(values.capacity() >= 2);
+}
+
+#[cfg(feature = "arena")]
+#[test]
+fn arena_expr() {
+    use super::parse_arena_expr;
+    use crate::parser::ast::arena;
+    use bumpalo::Bump;
+
+    let bump = Bump::new();
+    for sql in [
+        "a.b BETWEEN -1 AND ?2 OR name NOT LIKE 'x%' ESCAPE '\\'",
+        "CASE WHEN c IN (1, 2) THEN f(DISTINCT c) END COLLATE nocase",
+        "CASE x WHEN 1 THEN 'a' ELSE NULL END || main.t.c",
+        "NOT a = b AND c IS NOT DISTINCT FROM d OR e IS NOT NULL",
+        "a NOT NULL AND b ISNULL AND c NOT IN () AND d NOTNULL",
+        "- a * b COLLATE x + ~c - +d << 2 & 3 | 4 >= 5 != 6",
+        "a = b NOT LIKE c AND x BETWEEN a = b AND c = d",
+        "x LIKE y ESCAPE z = w AND j ->> '$.a' -> 1",
+        "count(*) > 0 AND max() AND key = replace AND \"a b\" = [c] AND 'tbl'.col",
+        "(a, b) = (1, 2) AND X'01' AND 1.5e3 AND current_date AND :name AND $x",
+        "like(a, b) AND glob REGEXP match",
+    ] {
+        let expr = parse_arena_expr(&bump, sql).unwrap();
+        assert_eq!(
+            expr.to_boxed(),
+            where_clause(&format!("SELECT 1 WHERE {sql}")),
+            "{sql}"
+        );
+    }
+    assert!(matches!(
+        parse_arena_expr(&bump, "a + 1").unwrap(),
+        arena::Expr::Binary(arena::Expr::Id("a"), Operator::Add, _)
+    ));
+
+    for (sql, msg) in [
+        (
+            "a IN (SELECT 1)",
+            "subquery not supported in arena expressions",
+        ),
+        (
+            "EXISTS (VALUES (1))",
+            "subquery not supported in arena expressions",
+        ),
+        ("CAST(a AS TEXT)", "CAST not supported in arena expressions"),
+        (
+            "count(*) OVER ()",
+            "window function not supported in arena expressions",
+        ),
+    ] {
+        match parse_arena_expr(&bump, sql).unwrap_err() {
+            Error::ParserError(m, Some(_)) => assert_eq!(m, msg),
+            err => panic!("{sql}: {err}"),
+        }
+    }
+    for (sql, msg, offset) in [
+        ("a +", "incomplete input", 3),
+        ("a b", "near \"b\": syntax error", 3),
+        ("f(a,)", "near \")\": syntax error", 5),
+        ("CASE WHEN THEN", "near \"THEN\": syntax error", 14),
+    ] {
+        match parse_arena_expr(&bump, sql).unwrap_err() {
+            Error::SyntaxError(m, Some((1, _, o))) => {
+                assert_eq!((m.as_str(), o), (msg, offset), "{sql}")
+            }
+            err => panic!("{sql}: {err}"),
+        }
+    }
+    let deep = format!("{}1{}", "(".repeat(200), ")".repeat(200));
+    assert!(parse_arena_expr(&bump, &deep).is_err());
+}
@@ -1,3 +1,8 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+// Without `std`, the parser is not reachable (no `lexer::sql::Parser`)
+#![cfg_attr(not(feature = "std"), allow(dead_code))]
+extern crate alloc;
+
 pub mod dialect;
 // In Lemon, the tokenizer calls the parser.
 pub mod lexer;
@@ -0,0 +1,165 @@
+//! Arena-allocated expressions
+//!
+//! Parallel to [`ast::Expr`](super::Expr) but nodes are allocated in a
+//! caller-owned [`Bump`](bumpalo::Bump) arena and borrow from it and from the
+//! parsed SQL: building or dropping a tree is a few pointer bumps instead of
+//! one allocation per `Box` / `Vec` / `String`.
+//! See `lexer::sql::parse_arena_expr`.
+//! Only expressions without subqueries, `CAST`, `RAISE` or window functions
+//! are supported.
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+
+use super::{Distinctness, Id, LikeOperator, Name, Operator, UnaryOperator};
+use crate::parser::ast;
+
+/// Arena-allocated expression (see [`ast::Expr`])
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Expr<'a> {
+    Between {
+        lhs: &'a Expr<'a>,
+        not: bool,
+        start: &'a Expr<'a>,
+        end: &'a Expr<'a>,
+    },
+    Binary(&'a Expr<'a>, Operator, &'a Expr<'a>),
+    Case {
+        base: Option<&'a Expr<'a>>,
+        when_then_pairs: &'a [(Expr<'a>, Expr<'a>)],
+        else_expr: Option<&'a Expr<'a>>,
+    },
+    Collate(&'a Expr<'a>, &'a str),
+    DoublyQualified(&'a str, &'a str, &'a str),
+    /// Function call without `FILTER` / `OVER` clause
+    FunctionCall {
+        name: &'a str,
+        distinctness: Option<Distinctness>,
+        args: Option<&'a [Expr<'a>]>,
+    },
+    /// `name(*)` without `FILTER` / `OVER` clause
+    FunctionCallStar {
+        name: &'a str,
+    },
+    Id(&'a str),
+    InList {
+        lhs: &'a Expr<'a>,
+        not: bool,
+        rhs: Option<&'a [Expr<'a>]>,
+    },
+    IsNull(&'a Expr<'a>),
+    Like {
+        lhs: &'a Expr<'a>,
+        not: bool,
+        op: LikeOperator,
+        rhs: &'a Expr<'a>,
+        escape: Option<&'a Expr<'a>>,
+    },
+    Literal(Literal<'a>),
+    NotNull(&'a Expr<'a>),
+    Parenthesized(&'a [Expr<'a>]),
+    Qualified(&'a str, &'a str),
+    Unary(UnaryOperator, &'a Expr<'a>),
+    Variable(&'a str),
+}
+
+/// Arena-allocated literal (see [`ast::Literal`])
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Literal<'a> {
+    Numeric(&'a str),
+    String(&'a str),
+    Blob(&'a str),
+    Null,
+    CurrentDate,
+    CurrentTime,
+    CurrentTimestamp,
+}
+
+impl Expr<'_> {
+    /// Copy into a boxed AST
+    pub fn to_boxed(&self) -> ast::Expr {
+        let node = |expr: &Expr| Box::new(expr.to_boxed());
+        let list = |exprs: &[Expr]| exprs.iter().map(Expr::to_boxed).collect();
+        match *self {
+            Expr::Between {
+                lhs,
+                not,
+                start,
+                end,
+            } => ast::Expr::Between {
+                lhs: node(lhs),
+                not,
+                start: node(start),
+                end: node(end),
+            },
+            Expr::Binary(lhs, op, rhs) => ast::Expr::Binary(node(lhs), op, node(rhs)),
+            Expr::Case {
+                base,
+                when_then_pairs,
+                else_expr,
+            } => ast::Expr::Case {
+                base: base.map(node),
+                when_then_pairs: when_then_pairs
+                    .iter()
+                    .map(|(when, then)| (when.to_boxed(), then.to_boxed()))
+                    .collect(),
+                else_expr: else_expr.map(node),
+            },
+            Expr::Collate(expr, collation) => ast::Expr::Collate(node(expr), collation.to_owned()),
+            Expr::DoublyQualified(db_name, tbl_name, col_name) => ast::Expr::DoublyQualified(
+                Name(db_name.to_owned()),
+                Name(tbl_name.to_owned()),
+                Name(col_name.to_owned()),
+            ),
+            Expr::FunctionCall {
+                name,
+                distinctness,
+                args,
+            } => ast::Expr::FunctionCall {
+                name: Id(name.to_owned()),
+                distinctness,
+                args: args.map(list),
+                filter_over: None,
+            },
+            Expr::FunctionCallStar { name } => ast::Expr::FunctionCallStar {
+                name: Id(name.to_owned()),
+                filter_over: None,
+            },
+            Expr::Id(id) => ast::Expr::Id(Id(id.to_owned())),
+            Expr::InList { lhs, not, rhs } => ast::Expr::InList {
+                lhs: node(lhs),
+                not,
+                rhs: rhs.map(list),
+            },
+            Expr::IsNull(expr) => ast::Expr::IsNull(node(expr)),
+            Expr::Like {
+                lhs,
+                not,
+                op,
+                rhs,
+                escape,
+            } => ast::Expr::Like {
+                lhs: node(lhs),
+                not,
+                op,
+                rhs: node(rhs),
+                escape: escape.map(node),
+            },
+            Expr::Literal(literal) => ast::Expr::Literal(match literal {
+                Literal::Numeric(s) => ast::Literal::Numeric(s.to_owned()),
+                Literal::String(s) => ast::Literal::String(s.to_owned()),
+                Literal::Blob(s) => ast::Literal::Blob(s.to_owned()),
+                Literal::Null => ast::Literal::Null,
+                Literal::CurrentDate => ast::Literal::CurrentDate,
+                Literal::CurrentTime => ast::Literal::CurrentTime,
+                Literal::CurrentTimestamp => ast::Literal::CurrentTimestamp,
+            }),
+            Expr::NotNull(expr) => ast::Expr::NotNull(node(expr)),
+            Expr::Parenthesized(exprs) => ast::Expr::Parenthesized(list(exprs)),
+            Expr::Qualified(tbl_name, col_name) => {
+                ast::Expr::Qualified(Name(tbl_name.to_owned()), Name(col_name.to_owned()))
+            }
+            Expr::Unary(op, expr) => ast::Expr::Unary(op, node(expr)),
+            Expr::Variable(var) => ast::Expr::Variable(var.to_owned()),
+        }
+    }
+}
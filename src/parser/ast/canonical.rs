@@ -0,0 +1,134 @@
+//! Canonical form of table definitions
+use super::*;
+
+/// Rewrite the table definition of a `CREATE TABLE` (or the column definition
+/// of an `ALTER TABLE ... ADD COLUMN`) in a canonical form, such that two
+/// definitions which differ only by cosmetic choices become equal:
+/// - type names are uppercased,
+/// - column constraints, table constraints and foreign key actions are sorted
+///   by kind (a `DEFERRABLE` clause stays after its `REFERENCES` clause),
+/// - defaults spelled out are removed (`ASC`, `ON CONFLICT ABORT`,
+///   `ON DELETE NO ACTION`, `ON UPDATE NO ACTION`).
+///
+/// Table options (`STRICT`, `WITHOUT ROWID`) are already stored as a set.
+/// Other statements are left unchanged.
+pub fn canonicalize(cmd: &mut Cmd) {
+    match cmd.stmt_mut() {
+        Stmt::CreateTable {
+            body:
+                CreateTableBody::ColumnsAndConstraints {
+                    columns,
+                    constraints,
+                    ..
+                },
+            ..
+        } => {
+            columns.iter_mut().for_each(column_definition);
+            if let Some(constraints) = constraints {
+                constraints.iter_mut().for_each(table_constraint);
+                constraints.sort_by_key(|c| table_constraint_rank(&c.constraint));
+            }
+        }
+        Stmt::AlterTable(_, AlterTableBody::AddColumn(column)) => column_definition(column),
+        _ => {}
+    }
+}
+
+fn column_definition(column: &mut ColumnDefinition) {
+    if let Some(ref mut col_type) = column.col_type {
+        col_type.name.make_ascii_uppercase();
+    }
+    for named in column.constraints.iter_mut() {
+        match named.constraint {
+            ColumnConstraint::PrimaryKey {
+                ref mut order,
+                ref mut conflict_clause,
+                ..
+            } => {
+                if *order == Some(SortOrder::Asc) {
+                    *order = None;
+                }
+                default_conflict_clause(conflict_clause);
+            }
+            ColumnConstraint::NotNull {
+                ref mut conflict_clause,
+                ..
+            }
+            | ColumnConstraint::Unique(ref mut conflict_clause) => {
+                default_conflict_clause(conflict_clause)
+            }
+            ColumnConstraint::ForeignKey { ref mut clause, .. } => foreign_key_clause(clause),
+            _ => {}
+        }
+    }
+    column
+        .constraints
+        .sort_by_key(|c| column_constraint_rank(&c.constraint));
+}
+
+fn table_constraint(named: &mut NamedTableConstraint) {
+    match named.constraint {
+        TableConstraint::PrimaryKey {
+            ref mut columns,
+            ref mut conflict_clause,
+            ..
+        }
+        | TableConstraint::Unique {
+            ref mut columns,
+            ref mut conflict_clause,
+        } => {
+            for column in columns.iter_mut() {
+                if column.order == Some(SortOrder::Asc) {
+                    column.order = None;
+                }
+            }
+            default_conflict_clause(conflict_clause);
+        }
+        TableConstraint::ForeignKey { ref mut clause, .. } => foreign_key_clause(clause),
+        TableConstraint::Check(_) => {}
+    }
+}
+
+fn foreign_key_clause(clause: &mut ForeignKeyClause) {
+    clause.args.retain(|arg| {
+        !matches!(
+            arg,
+            RefArg::OnDelete(RefAct::NoAction) | RefArg::OnUpdate(RefAct::NoAction)
+        )
+    });
+    clause.args.sort_by_key(|arg| match arg {
+        RefArg::OnDelete(_) => 0,
+        RefArg::OnUpdate(_) => 1,
+        RefArg::OnInsert(_) => 2,
+        RefArg::Match(_) => 3,
+    });
+}
+
+fn default_conflict_clause(conflict_clause: &mut Option<ResolveType>) {
+    if *conflict_clause == Some(ResolveType::Abort) {
+        *conflict_clause = None;
+    }
+}
+
+fn column_constraint_rank(constraint: &ColumnConstraint) -> u8 {
+    match constraint {
+        ColumnConstraint::PrimaryKey { .. } => 0,
+        ColumnConstraint::NotNull { .. } => 1,
+        ColumnConstraint::Unique(_) => 2,
+        ColumnConstraint::Check(_) => 3,
+        ColumnConstraint::Default(_) => 4,
+        ColumnConstraint::Collate { .. } => 5,
+        // `DEFERRABLE` applies to the preceding `REFERENCES` (stable sort)
+        ColumnConstraint::ForeignKey { .. } | ColumnConstraint::Defer(_) => 6,
+        ColumnConstraint::Generated { .. } => 7,
+    }
+}
+
+fn table_constraint_rank(constraint: &TableConstraint) -> u8 {
+    match constraint {
+        TableConstraint::PrimaryKey { .. } => 0,
+        TableConstraint::Unique { .. } => 1,
+        TableConstraint::Check(_) => 2,
+        TableConstraint::ForeignKey { .. } => 3,
+    }
+}
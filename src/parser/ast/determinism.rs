@@ -0,0 +1,96 @@
+//! Detection of non-deterministic function calls
+use super::visit::{walk_expr, Visitor};
+use super::*;
+
+// Built-in functions whose result may change between two evaluations
+// with the same arguments
+const NON_DETERMINISTIC: [&str; 5] = [
+    "changes",
+    "last_insert_rowid",
+    "random",
+    "randomblob",
+    "total_changes",
+];
+
+// Date and time functions, non-deterministic without time value or with
+// `'now'` / `'localtime'` / `'utc'` (which depend on the current time zone)
+const DATE_FUNCTIONS: [&str; 7] = [
+    "date",
+    "datetime",
+    "julianday",
+    "strftime",
+    "time",
+    "timediff",
+    "unixepoch",
+];
+
+impl Cmd {
+    /// Whether this statement calls no known non-deterministic built-in
+    /// function (`random()`, `last_insert_rowid()`, ...), nor reads the current
+    /// time or time zone (`CURRENT_TIMESTAMP`, date functions without time value,
+    /// like `strftime('%Y')`, or with a `'now'` / `'localtime'` / `'utc'` argument).
+    /// Function names are compared case-insensitively; user-defined functions and
+    /// arguments which are not literals (like parameters) are assumed deterministic.
+    pub fn is_deterministic(&self) -> bool {
+        let mut v = DeterminismVisitor {
+            deterministic: true,
+        };
+        v.visit_stmt(self.stmt());
+        v.deterministic
+    }
+}
+
+struct DeterminismVisitor {
+    deterministic: bool,
+}
+
+impl Visitor<'_> for DeterminismVisitor {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if !self.deterministic {
+            return;
+        }
+        match expr {
+            Expr::FunctionCall { name, args, .. } if !is_deterministic(name, args.as_deref()) => {
+                self.deterministic = false
+            }
+            Expr::FunctionCallStar { name, .. } if !is_deterministic(name, None) => {
+                self.deterministic = false
+            }
+            Expr::Literal(
+                Literal::CurrentDate | Literal::CurrentTime | Literal::CurrentTimestamp,
+            ) => self.deterministic = false,
+            _ => walk_expr(self, expr),
+        }
+    }
+}
+
+fn is_deterministic(name: &Id, args: Option<&[Expr]>) -> bool {
+    let name = unquote(&name.0);
+    let is = |f: &&str| f.eq_ignore_ascii_case(name);
+    if NON_DETERMINISTIC.iter().any(is) {
+        return false;
+    }
+    if !DATE_FUNCTIONS.iter().any(is) {
+        return true;
+    }
+    // the time value follows the format of `strftime`
+    let time_value = if name.eq_ignore_ascii_case("strftime") {
+        1
+    } else {
+        0
+    };
+    let Some(args) = args.filter(|args| args.len() > time_value) else {
+        // current date / time
+        return false;
+    };
+    !args.iter().any(|arg| match arg {
+        Expr::Literal(Literal::String(s)) => {
+            let value = unescape(s);
+            let value = value.trim();
+            ["now", "localtime", "utc"]
+                .iter()
+                .any(|v| value.eq_ignore_ascii_case(v))
+        }
+        _ => false,
+    })
+}
@@ -0,0 +1,63 @@
+//! Short-circuiting expression search
+use super::visit::{walk_expr, walk_select, walk_window, Visitor};
+use super::*;
+
+impl Cmd {
+    /// First expression (in pre-order, including subexpressions and subqueries)
+    /// for which `f` holds. The traversal stops at the first match.
+    pub fn find_expr<F: FnMut(&Expr) -> bool>(&self, f: F) -> Option<&Expr> {
+        let mut v = FindExpr {
+            f,
+            subqueries: true,
+            found: None,
+        };
+        v.visit_stmt(self.stmt());
+        v.found
+    }
+}
+
+impl Expr {
+    /// First subexpression (in pre-order, including this one) for which `f`
+    /// holds. Subqueries are not entered. The traversal stops at the first match.
+    pub(crate) fn find<'a, F: FnMut(&'a Expr) -> bool>(&'a self, f: F) -> Option<&'a Expr> {
+        let mut v = FindExpr {
+            f,
+            subqueries: false,
+            found: None,
+        };
+        v.visit_expr(self);
+        v.found
+    }
+}
+
+struct FindExpr<'a, F> {
+    f: F,
+    // whether subqueries are searched
+    subqueries: bool,
+    found: Option<&'a Expr>,
+}
+
+impl<'a, F: FnMut(&'a Expr) -> bool> Visitor<'a> for FindExpr<'a, F> {
+    fn visit_select(&mut self, select: &'a Select) {
+        if self.subqueries && self.found.is_none() {
+            walk_select(self, select);
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        if self.found.is_some() {
+            return;
+        }
+        if (self.f)(expr) {
+            self.found = Some(expr);
+        } else {
+            walk_expr(self, expr);
+        }
+    }
+
+    fn visit_window(&mut self, window: &'a Window) {
+        if self.found.is_none() {
+            walk_window(self, window);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Constant folding
+use alloc::string::ToString;
+
+use super::visit::{walk_expr_mut, VisitorMut};
+use super::*;
+
+impl Expr {
+    /// Evaluate literal-only subexpressions:
+    /// integer arithmetic (`1 + 2` -> `3`), string concatenation
+    /// (`'a' || 'b'` -> `'ab'`), `NOT TRUE` -> `FALSE`, and
+    /// `x AND FALSE` -> `FALSE` / `x OR TRUE` -> `TRUE`.
+    /// Expressions involving a column, a function call or a real number, and
+    /// operations which would overflow, are kept as is.
+    pub fn fold_constants(mut self) -> Expr {
+        FoldConstants.visit_expr(&mut self);
+        self
+    }
+}
+
+struct FoldConstants;
+
+impl VisitorMut for FoldConstants {
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+        if let Some(folded) = fold(expr) {
+            *expr = folded;
+        }
+    }
+}
+
+/// Constant value of a literal-only expression
+enum Value {
+    Null,
+    Integer(i64),
+    Text(String),
+    Bool(bool),
+}
+
+impl Value {
+    fn of(expr: &Expr) -> Option<Value> {
+        match expr {
+            Expr::Literal(Literal::Null) => Some(Value::Null),
+            Expr::Literal(Literal::Numeric(n)) => integer(n, false).map(Value::Integer),
+            Expr::Literal(Literal::String(s)) => Some(Value::Text(unescape(s))),
+            Expr::Id(Id(id)) if id.eq_ignore_ascii_case("TRUE") => Some(Value::Bool(true)),
+            Expr::Id(Id(id)) if id.eq_ignore_ascii_case("FALSE") => Some(Value::Bool(false)),
+            Expr::Unary(UnaryOperator::Negative, expr) => match expr.as_ref() {
+                Expr::Literal(Literal::Numeric(n)) => integer(n, true).map(Value::Integer),
+                _ => None,
+            },
+            Expr::Parenthesized(exprs) if exprs.len() == 1 => Value::of(&exprs[0]),
+            _ => None,
+        }
+    }
+
+    fn into_expr(self) -> Option<Expr> {
+        Some(match self {
+            Value::Null => Expr::Literal(Literal::Null),
+            Value::Integer(i) if i < 0 => Expr::Unary(
+                UnaryOperator::Negative,
+                // i64::MIN cannot be spelled as a negated integer literal
+                Box::new(Expr::Literal(Literal::Numeric(
+                    i.checked_neg()?.to_string(),
+                ))),
+            ),
+            Value::Integer(i) => Expr::Literal(Literal::Numeric(i.to_string())),
+            Value::Text(s) => {
+                Expr::Literal(Literal::String(format!("'{}'", s.replace('\'', "''"))))
+            }
+            Value::Bool(b) => Expr::Id(Id(if b { "TRUE" } else { "FALSE" }.to_owned())),
+        })
+    }
+}
+
+/// Decimal integer literal value
+fn integer(n: &str, negative: bool) -> Option<i64> {
+    if !n.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if negative {
+        format!("-{}", n).parse().ok()
+    } else {
+        n.parse().ok()
+    }
+}
+
+fn fold(expr: &Expr) -> Option<Expr> {
+    match expr {
+        Expr::Binary(lhs, op, rhs) => fold_binary(lhs, *op, rhs),
+        Expr::Unary(UnaryOperator::Not, expr) => match Value::of(expr)? {
+            Value::Bool(b) => Value::Bool(!b).into_expr(),
+            _ => None,
+        },
+        Expr::Parenthesized(exprs) if exprs.len() == 1 => match &exprs[0] {
+            e @ Expr::Literal(_) => Some(e.clone()),
+            e @ Expr::Id(_) if Value::of(e).is_some() => Some(e.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn fold_binary(lhs: &Expr, op: Operator, rhs: &Expr) -> Option<Expr> {
+    let (l, r) = (Value::of(lhs), Value::of(rhs));
+    let value = match (op, l, r) {
+        // FALSE (resp. TRUE) whatever the other operand is, even NULL
+        (Operator::And, Some(Value::Bool(false)), _)
+        | (Operator::And, _, Some(Value::Bool(false))) => Value::Bool(false),
+        (Operator::Or, Some(Value::Bool(true)), _) | (Operator::Or, _, Some(Value::Bool(true))) => {
+            Value::Bool(true)
+        }
+        (Operator::And, Some(Value::Bool(true)), Some(Value::Bool(true))) => Value::Bool(true),
+        (Operator::Or, Some(Value::Bool(false)), Some(Value::Bool(false))) => Value::Bool(false),
+        (
+            Operator::Add
+            | Operator::Substract
+            | Operator::Multiply
+            | Operator::Divide
+            | Operator::Modulus,
+            Some(Value::Integer(l)),
+            Some(Value::Integer(r)),
+        ) => Value::Integer(match op {
+            Operator::Add => l.checked_add(r),
+            Operator::Substract => l.checked_sub(r),
+            Operator::Multiply => l.checked_mul(r),
+            // division by zero and overflow are left to SQLite
+            Operator::Divide => l.checked_div(r),
+            _ => l.checked_rem(r),
+        }?),
+        (
+            Operator::Add
+            | Operator::Substract
+            | Operator::Multiply
+            | Operator::Divide
+            | Operator::Modulus,
+            Some(Value::Integer(_) | Value::Null),
+            Some(Value::Integer(_) | Value::Null),
+        ) => Value::Null,
+        (Operator::Concat, Some(Value::Text(l)), Some(Value::Text(r))) => Value::Text(l + &r),
+        (
+            Operator::Concat,
+            Some(Value::Text(_) | Value::Null),
+            Some(Value::Text(_) | Value::Null),
+        ) => Value::Null,
+        _ => return None,
+    };
+    value.into_expr()
+}
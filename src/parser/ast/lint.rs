@@ -0,0 +1,142 @@
+//! Best-effort detection of suspicious queries
+use alloc::format;
+
+use super::visit::{walk_one_select, Visitor};
+use super::*;
+
+/// Joins without `ON` / `USING` condition (explicit `CROSS JOIN` and
+/// `NATURAL` joins excepted) which are not linked to another table of the
+/// `FROM` clause by a `WHERE` term: probably accidental Cartesian products.
+/// Subqueries are analyzed too.
+///
+/// A `WHERE` term links a table if it references a column of this table and a
+/// column of another table (unqualified columns may belong to any table).
+pub fn lint_cartesian(select: &Select) -> Vec<Diagnostic> {
+    let mut lint = CartesianLint::default();
+    lint.visit_select(select);
+    lint.diagnostics
+}
+
+#[derive(Default)]
+struct CartesianLint {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Visitor<'a> for CartesianLint {
+    fn visit_one_select(&mut self, select: &'a OneSelect) {
+        if let OneSelect::Select {
+            from: Some(from),
+            where_clause,
+            ..
+        } = select
+        {
+            self.check(from, where_clause.as_ref());
+        }
+        walk_one_select(self, select);
+    }
+}
+
+impl CartesianLint {
+    fn check(&mut self, from: &FromClause, where_clause: Option<&Expr>) {
+        let (Some(first), Some(joins)) = (&from.select, &from.joins) else {
+            return;
+        };
+        let tables: Vec<Option<&Name>> = core::iter::once(first.as_ref())
+            .chain(joins.iter().map(|join| &join.table))
+            .map(qualifier)
+            .collect();
+        let mut terms = vec![];
+        if let Some(expr) = where_clause {
+            conjuncts(expr, &mut terms);
+        }
+        for (i, join) in joins.iter().enumerate() {
+            if join.constraint.is_some() {
+                continue;
+            }
+            match join.operator {
+                JoinOperator::TypedJoin { natural: true, .. }
+                | JoinOperator::TypedJoin {
+                    join_type: Some(JoinType::Cross),
+                    ..
+                } => continue,
+                _ => {}
+            }
+            let table = tables[i + 1];
+            if terms.iter().any(|term| links(term, table, &tables)) {
+                continue;
+            }
+            self.diagnostics.push(match table {
+                Some(name) => {
+                    Diagnostic::new(format!("no join condition for {}: Cartesian product", name))
+                        .on_table(name)
+                }
+                None => Diagnostic::new("no join condition: Cartesian product"),
+            });
+        }
+    }
+}
+
+// Name by which the columns of `table` can be qualified
+fn qualifier(table: &SelectTable) -> Option<&Name> {
+    match table {
+        SelectTable::Table(_, Some(As::As(alias) | As::Elided(alias)), _)
+        | SelectTable::TableCall(_, _, Some(As::As(alias) | As::Elided(alias)))
+        | SelectTable::Select(_, Some(As::As(alias) | As::Elided(alias)))
+        | SelectTable::Sub(_, Some(As::As(alias) | As::Elided(alias))) => Some(alias),
+        SelectTable::Table(name, None, _) | SelectTable::TableCall(name, _, None) => {
+            Some(&name.name)
+        }
+        _ => None,
+    }
+}
+
+// Split `expr` on `AND`
+fn conjuncts<'a>(expr: &'a Expr, terms: &mut Vec<&'a Expr>) {
+    match expr {
+        Expr::Binary(lhs, Operator::And, rhs) => {
+            conjuncts(lhs, terms);
+            conjuncts(rhs, terms);
+        }
+        Expr::Parenthesized(exprs) if exprs.len() == 1 => conjuncts(&exprs[0], terms),
+        _ => terms.push(expr),
+    }
+}
+
+fn same(a: &Name, b: &Name) -> bool {
+    unquote(&a.0).eq_ignore_ascii_case(unquote(&b.0))
+}
+
+// Whether `term` references columns of `table` and of another of `tables`
+fn links(term: &Expr, table: Option<&Name>, tables: &[Option<&Name>]) -> bool {
+    // qualifiers of the column references (`None` when unqualified)
+    let mut columns: Vec<Option<&Name>> = vec![];
+    term.find(|expr| {
+        match expr {
+            Expr::Id(_) | Expr::Name(_) => columns.push(None),
+            Expr::Qualified(tbl_name, _) | Expr::DoublyQualified(_, tbl_name, _) => {
+                columns.push(Some(tbl_name))
+            }
+            _ => {}
+        }
+        false
+    });
+    let of_table = |column: &Option<&Name>| match (column, table) {
+        (None, _) => true,
+        (Some(q), Some(t)) => same(q, t),
+        (Some(_), None) => false,
+    };
+    let of_other = |column: &Option<&Name>| match column {
+        None => tables.len() > 1,
+        Some(q) => tables
+            .iter()
+            .flatten()
+            .any(|t| same(q, t) && !table.is_some_and(|table| same(q, table))),
+    };
+    columns.iter().enumerate().any(|(i, a)| {
+        of_table(a)
+            && columns
+                .iter()
+                .enumerate()
+                .any(|(j, b)| i != j && of_other(b))
+    })
+}
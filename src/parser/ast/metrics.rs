@@ -0,0 +1,101 @@
+//! Statement complexity metrics
+use super::visit::{walk_expr, walk_from_clause, walk_select, walk_select_table, Visitor};
+use super::*;
+use alloc::vec::Vec;
+
+/// Complexity metrics of a statement (see [`Cmd::metrics`])
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StmtMetrics {
+    /// Number of joined tables (`FROM a, b JOIN c` has 2 joins)
+    pub joins: usize,
+    /// Number of subqueries (in expressions or `FROM` clauses)
+    pub subqueries: usize,
+    /// Number of compound operators (`UNION`, `INTERSECT`, ...)
+    pub compound_selects: usize,
+    /// Number of aggregate function calls
+    pub aggregate_functions: usize,
+    /// Maximum depth of an expression tree (`1 + 2 * 3` has a depth of 3)
+    pub max_expr_depth: usize,
+}
+
+impl Cmd {
+    /// Compute complexity metrics
+    pub fn metrics(&self) -> StmtMetrics {
+        let mut v = MetricsVisitor::default();
+        v.visit_stmt(self.stmt());
+        v.metrics
+    }
+}
+
+#[derive(Default)]
+struct MetricsVisitor {
+    metrics: StmtMetrics,
+    depth: usize,
+}
+
+impl Visitor<'_> for MetricsVisitor {
+    fn visit_select(&mut self, select: &Select) {
+        if let Some(ref compounds) = select.body.compounds {
+            self.metrics.compound_selects += compounds.len();
+        }
+        // expression depth is reset inside subqueries
+        let depth = core::mem::take(&mut self.depth);
+        walk_select(self, select);
+        self.depth = depth;
+    }
+
+    fn visit_from_clause(&mut self, from: &FromClause) {
+        if let Some(ref joins) = from.joins {
+            self.metrics.joins += joins.len();
+        }
+        walk_from_clause(self, from);
+    }
+
+    fn visit_select_table(&mut self, table: &SelectTable) {
+        if let SelectTable::Select(..) = table {
+            self.metrics.subqueries += 1;
+        }
+        walk_select_table(self, table);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Exists(_) | Expr::InSelect { .. } | Expr::Subquery(_) => {
+                self.metrics.subqueries += 1
+            }
+            Expr::FunctionCall { name, args, .. } if is_aggregate(name, args) => {
+                self.metrics.aggregate_functions += 1
+            }
+            Expr::FunctionCallStar { name, .. } if name.0.eq_ignore_ascii_case("count") => {
+                self.metrics.aggregate_functions += 1
+            }
+            _ => {}
+        };
+        self.depth += 1;
+        self.metrics.max_expr_depth = self.metrics.max_expr_depth.max(self.depth);
+        walk_expr(self, expr);
+        self.depth -= 1;
+    }
+}
+
+// https://sqlite.org/lang_aggfunc.html
+fn is_aggregate(name: &Id, args: &Option<Vec<Expr>>) -> bool {
+    const AGGREGATES: [&str; 10] = [
+        "avg",
+        "count",
+        "group_concat",
+        "json_group_array",
+        "json_group_object",
+        "jsonb_group_array",
+        "jsonb_group_object",
+        "string_agg",
+        "sum",
+        "total",
+    ];
+    let name = name.0.as_str();
+    if name.eq_ignore_ascii_case("min") || name.eq_ignore_ascii_case("max") {
+        // multi-argument min/max are scalar functions
+        return args.as_ref().map_or(0, Vec::len) == 1;
+    }
+    AGGREGATES.iter().any(|agg| agg.eq_ignore_ascii_case(name))
+}
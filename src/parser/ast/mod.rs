@@ -1,23 +1,217 @@
 //! Abstract Syntax Tree
 
-use std::fmt::{self, Display, Formatter, Write};
-use std::num::ParseIntError;
-use std::str::FromStr;
-
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt::{self, Display, Formatter, Write};
+#[cfg(feature = "std")]
+use core::num::ParseIntError;
+#[cfg(feature = "std")]
+use core::str::FromStr;
+
+#[cfg(feature = "std")]
 use indexmap::IndexSet;
 
 use crate::dialect::TokenType::{self, *};
 use crate::dialect::{from_token, is_identifier, Token};
 use crate::parser::{parse::YYCODETYPE, ParserError};
 
+#[cfg(feature = "arena")]
+pub mod arena;
+mod canonical;
+mod determinism;
+mod find;
+mod fold;
+mod lint;
+mod metrics;
+mod qualify;
+mod rename;
+mod schema;
+mod structural;
+mod tree;
+mod validate;
+pub mod visit;
+
+pub use canonical::canonicalize;
+pub use lint::lint_cartesian;
+pub use metrics::StmtMetrics;
+pub use qualify::qualify_tables;
+pub use rename::propagate_rename;
+pub use schema::{Affinity, ColumnInfo, ForeignKey, TableSchema};
+pub use structural::CompareOptions;
+pub use validate::Diagnostic;
+
+/// Formatting options
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Spell `TEMP` keyword as `TEMPORARY`
+    pub temporary: bool,
+    /// Spell `ATTACH` / `DETACH` as `ATTACH DATABASE` / `DETACH DATABASE`
+    pub attach_database: bool,
+    /// Render on a single line: line breaks between tokens (like in trigger bodies)
+    /// are replaced by a single space. String literals are kept intact.
+    pub single_line: bool,
+    /// Rewrite parameters (`?`, `?NNN`, `:AAA`, ...) in a specific style
+    /// (as written by default)
+    pub parameter_style: Option<ParameterStyle>,
+    /// Rewrite quoted identifiers (`"foo"`, `[foo]`, `` `foo` ``) in a specific
+    /// style (as written by default)
+    pub identifier_quote_style: Option<QuoteStyle>,
+    /// Spell comma joins (`FROM a, b`) as `FROM a CROSS JOIN b`
+    pub cross_join: bool,
+    /// Surround symbolic binary operators with spaces (`a = b` versus `a=b`).
+    /// `||` and keyword operators (`AND`, `IS`, ...) are always spaced.
+    /// Enabled by default.
+    pub space_around_operators: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            temporary: false,
+            attach_database: false,
+            single_line: false,
+            parameter_style: None,
+            identifier_quote_style: None,
+            cross_join: false,
+            space_around_operators: true,
+        }
+    }
+}
+
+/// Style of rewritten parameters (see [`FormatOptions::parameter_style`])
+///
+/// Parameters are numbered in order of first appearance: each `?` is a new
+/// parameter whereas repeated `?NNN` / named parameters share their number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParameterStyle {
+    /// `?` (repeated parameters become distinct ones)
+    Question,
+    /// `?1`, `?2`, ...
+    Numbered,
+    /// `:p1`, `:p2`, ...
+    Named,
+}
+
+/// Delimiters of quoted identifiers (see [`FormatOptions::identifier_quote_style`])
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// `"foo"` (SQL standard)
+    DoubleQuote,
+    /// `[foo]` (MS Access / SQL Server), or `"foo"` when the identifier
+    /// contains a `]`
+    Bracket,
+    /// `` `foo` `` (MySQL)
+    Backtick,
+}
+
+impl QuoteStyle {
+    // Rewrite `quoted` identifier in this style (`None` if not quoted)
+    fn requote(self, quoted: &str) -> Option<String> {
+        let (open, close) = match quoted.as_bytes() {
+            [b'"', .., b'"'] => ('"', '"'),
+            [b'`', .., b'`'] => ('`', '`'),
+            [b'[', .., b']'] => ('[', ']'),
+            _ => return None,
+        };
+        let inner = &quoted[1..quoted.len() - 1];
+        let name = if open == '[' {
+            inner.to_owned()
+        } else {
+            inner.replace(&format!("{0}{0}", close), close.encode_utf8(&mut [0; 4]))
+        };
+        let (open, close) = match self {
+            QuoteStyle::Bracket if !name.contains(']') => return Some(format!("[{}]", name)),
+            QuoteStyle::DoubleQuote | QuoteStyle::Bracket => ('"', "\"\""),
+            QuoteStyle::Backtick => ('`', "``"),
+        };
+        let mut requoted = String::with_capacity(name.len() + 2);
+        requoted.push(open);
+        requoted.push_str(&name.replace(open, close));
+        requoted.push(open);
+        Some(requoted)
+    }
+}
+
+impl FormatOptions {
+    // Spelling of keywords / operators / punctuations
+    fn spelling(&self, ty: TokenType) -> Option<&'static str> {
+        match ty {
+            TK_TEMP if self.temporary => Some("TEMPORARY"),
+            TK_ATTACH if self.attach_database => Some("ATTACH DATABASE"),
+            TK_DETACH if self.attach_database => Some("DETACH DATABASE"),
+            _ => ty.as_str(),
+        }
+    }
+}
+
 struct FmtTokenStream<'a, 'b> {
     f: &'a mut Formatter<'b>,
     spaced: bool,
+    options: FormatOptions,
+    /// Parameters rendered so far (see `FormatOptions::parameter_style`)
+    parameters: Vec<String>,
+    /// Operator just rendered without trailing space
+    /// (see `FormatOptions::space_around_operators`)
+    tight: Option<TokenType>,
 }
 impl<'a, 'b> TokenStream for FmtTokenStream<'a, 'b> {
     type Error = fmt::Error;
 
     fn append(&mut self, ty: TokenType, value: Option<&str>) -> fmt::Result {
+        if let (TK_VARIABLE, Some(style), Some(variable)) =
+            (ty, self.options.parameter_style, value)
+        {
+            let variable = self.parameter(style, variable);
+            return self.write(ty, Some(&variable));
+        }
+        if let (TK_ID, Some(style), Some(id)) = (ty, self.options.identifier_quote_style, value) {
+            if let Some(id) = style.requote(id) {
+                return self.write(ty, Some(&id));
+            }
+        }
+        self.write(ty, value)
+    }
+
+    fn append_operator(&mut self, ty: TokenType, value: Option<&str>) -> fmt::Result {
+        if self.options.space_around_operators {
+            return self.append(ty, value);
+        }
+        self.spaced = true;
+        self.append(ty, value)?;
+        self.spaced = true;
+        self.tight = Some(ty);
+        Ok(())
+    }
+
+    fn format_options(&self) -> FormatOptions {
+        self.options
+    }
+}
+impl<'a, 'b> FmtTokenStream<'a, 'b> {
+    // Rewrite `variable` in `style`
+    fn parameter(&mut self, style: ParameterStyle, variable: &str) -> String {
+        let n = match self.parameters.iter().position(|p| p == variable) {
+            Some(i) if variable != "?" => i + 1,
+            _ => {
+                self.parameters.push(variable.to_owned());
+                self.parameters.len()
+            }
+        };
+        match style {
+            ParameterStyle::Question => "?".to_owned(),
+            ParameterStyle::Numbered => format!("?{}", n),
+            ParameterStyle::Named => format!(":p{}", n),
+        }
+    }
+
+    fn write(&mut self, ty: TokenType, value: Option<&str>) -> fmt::Result {
+        if self.tight.take() == Some(TK_MINUS) && ty == TK_MINUS {
+            // `a--b` would start a comment
+            self.f.write_char(' ')?;
+        }
         if !self.spaced {
             match ty {
                 TK_COMMA | TK_SEMI | TK_RP | TK_DOT => {}
@@ -34,13 +228,23 @@ impl<'a, 'b> TokenStream for FmtTokenStream<'a, 'b> {
                 self.f.write_str(str)?;
             }
             return self.f.write_char('\'');
-        } else if let Some(str) = ty.as_str() {
+        } else if let Some(str) = self
+            .options
+            .spelling(ty)
+            .filter(|_| ty != TK_ANY || value.is_none())
+        {
+            // (`TK_ANY` with a value is raw text, like a `PRAGMA` argument)
             self.f.write_str(str)?;
             self.spaced = ty == TK_LP || ty == TK_DOT; // str should not be whitespace
         }
         if let Some(str) = value {
             // trick for pretty-print
             self.spaced = str.bytes().all(|b| b.is_ascii_whitespace());
+            if self.spaced && self.options.single_line {
+                // next token will be preceded by a single space
+                self.spaced = false;
+                return Ok(());
+            }
             /*if !self.spaced {
                 self.f.write_char(' ')?;
             }*/
@@ -51,6 +255,7 @@ impl<'a, 'b> TokenStream for FmtTokenStream<'a, 'b> {
     }
 }
 
+#[cfg(feature = "std")]
 #[derive(Default)]
 pub struct ParameterInfo {
     pub count: u32,
@@ -58,6 +263,7 @@ pub struct ParameterInfo {
 }
 
 // https://sqlite.org/lang_expr.html#parameters
+#[cfg(feature = "std")]
 impl TokenStream for ParameterInfo {
     type Error = ParseIntError;
 
@@ -84,15 +290,57 @@ pub trait TokenStream {
     type Error;
 
     fn append(&mut self, ty: TokenType, value: Option<&str>) -> Result<(), Self::Error>;
+
+    /// Append a symbolic binary operator, which may be rendered without
+    /// surrounding spaces (see [`FormatOptions::space_around_operators`])
+    fn append_operator(&mut self, ty: TokenType, value: Option<&str>) -> Result<(), Self::Error> {
+        self.append(ty, value)
+    }
+
+    /// Options of the rendering (for choices which are not a matter of
+    /// token spelling)
+    fn format_options(&self) -> FormatOptions {
+        FormatOptions::default()
+    }
 }
 
 pub trait ToTokens {
     fn to_tokens<S: TokenStream>(&self, s: &mut S) -> Result<(), S::Error>;
 
     fn to_fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let mut s = FmtTokenStream { f, spaced: true };
+        self.to_fmt_with_options(f, FormatOptions::default())
+    }
+
+    fn to_fmt_with_options(&self, f: &mut Formatter<'_>, options: FormatOptions) -> fmt::Result {
+        let mut s = FmtTokenStream {
+            f,
+            spaced: true,
+            options,
+            parameters: Vec::new(),
+            tight: None,
+        };
         self.to_tokens(&mut s)
     }
+
+    /// Render with specific `options`
+    fn display_with(&self, options: FormatOptions) -> DisplayWith<'_, Self> {
+        DisplayWith {
+            node: self,
+            options,
+        }
+    }
+}
+
+/// Display a node with specific [`FormatOptions`] (see [`ToTokens::display_with`])
+pub struct DisplayWith<'a, T: ?Sized> {
+    node: &'a T,
+    options: FormatOptions,
+}
+
+impl<'a, T: ?Sized + ToTokens> Display for DisplayWith<'a, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.node.to_fmt_with_options(f, self.options)
+    }
 }
 
 impl<T: ?Sized + ToTokens> ToTokens for &T {
@@ -113,7 +361,7 @@ impl Display for dyn ToTokens {
 */
 
 // https://sqlite.org/syntax/sql-stmt.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Cmd {
     Explain(Stmt),
     ExplainQueryPlan(Stmt),
@@ -147,20 +395,55 @@ impl Display for Cmd {
     }
 }
 
+impl Cmd {
+    /// Statement (without `EXPLAIN` / `EXPLAIN QUERY PLAN`)
+    pub fn stmt(&self) -> &Stmt {
+        match self {
+            Cmd::Explain(stmt) | Cmd::ExplainQueryPlan(stmt) | Cmd::Stmt(stmt) => stmt,
+        }
+    }
+
+    /// Mutable statement (without `EXPLAIN` / `EXPLAIN QUERY PLAN`)
+    pub fn stmt_mut(&mut self) -> &mut Stmt {
+        match self {
+            Cmd::Explain(stmt) | Cmd::ExplainQueryPlan(stmt) | Cmd::Stmt(stmt) => stmt,
+        }
+    }
+
+    /// Semantic errors which SQLite would report when preparing this statement
+    /// (see [`CreateTableBody::validate`] and [`With::validate`]), plus `VALUES`
+    /// rows whose number of terms differs from the first row or from the column
+    /// list of an `INSERT` (1-based row index in the message)
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut errors = match self.stmt() {
+            Stmt::CreateTable { tbl_name, body, .. } => body
+                .validate()
+                .into_iter()
+                .map(|diagnostic| diagnostic.on_table(&tbl_name.name))
+                .collect(),
+            _ => vec![],
+        };
+        errors.extend(validate::values_arity(self.stmt()));
+        errors.extend(validate::insert_arity(self.stmt()));
+        errors.extend(validate::cte_references(self.stmt()));
+        errors
+    }
+}
+
 pub(crate) enum ExplainKind {
     Explain,
     QueryPlan,
 }
 
 // https://sqlite.org/syntax/sql-stmt.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Stmt {
     // table name, body
     AlterTable(QualifiedName, AlterTableBody),
     // object name
     Analyze(Option<QualifiedName>),
     Attach {
-        // TODO distinction between ATTACH and ATTACH DATABASE
+        // `DATABASE` keyword is not recorded (see `FormatOptions::attach_database`)
         expr: Expr,
         db_name: Expr,
         key: Option<Expr>,
@@ -178,7 +461,7 @@ pub enum Stmt {
         where_clause: Option<Expr>,
     },
     CreateTable {
-        temporary: bool, // TODO distinction between TEMP and TEMPORARY
+        temporary: bool,
         if_not_exists: bool,
         tbl_name: QualifiedName,
         body: CreateTableBody,
@@ -217,7 +500,7 @@ pub enum Stmt {
         limit: Option<Limit>,
     },
     // db name
-    Detach(Expr), // TODO distinction between DETACH and DETACH DATABASE
+    Detach(Expr), // `DATABASE` keyword is not recorded
     DropIndex {
         if_exists: bool,
         idx_name: QualifiedName,
@@ -685,7 +968,7 @@ impl ToTokens for Stmt {
 }
 
 // https://sqlite.org/syntax/expr.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Expr {
     Between {
         lhs: Box<Expr>,
@@ -757,8 +1040,8 @@ pub enum Expr {
     // Parenthesized subexpression
     Parenthesized(Vec<Expr>),
     Qualified(Name, Name),
-    // RAISE function call
-    Raise(ResolveType, Option<Name>),
+    // RAISE function call (action, error message)
+    Raise(ResolveType, Option<Box<Expr>>),
     // Subquery expression
     Subquery(Box<Select>),
     // Unary expression
@@ -849,6 +1132,72 @@ impl Expr {
     pub fn sub_query(query: Select) -> Expr {
         Expr::Subquery(Box::new(query))
     }
+
+    /// Check if this expression contains a subquery
+    /// (`(SELECT ...)`, `EXISTS (SELECT ...)` or `x IN (SELECT ...)`),
+    /// at any depth
+    pub fn has_subquery(&self) -> bool {
+        self.find(|expr| {
+            matches!(
+                expr,
+                Expr::Exists(_) | Expr::InSelect { .. } | Expr::Subquery(_)
+            )
+        })
+        .is_some()
+    }
+
+    /// Column and constant prefix of a `col LIKE 'abc%'` (or `col GLOB 'abc*'`)
+    /// pattern: the characters before the first wildcard, taking the `ESCAPE`
+    /// character into account. `None` if the pattern is not a string literal
+    /// or starts with a wildcard.
+    /// The prefix of a `LIKE` is matched case-insensitively (for ASCII).
+    pub fn like_prefix(&self) -> Option<(&Expr, String)> {
+        let Expr::Like {
+            lhs,
+            not: false,
+            op: op @ (LikeOperator::Like | LikeOperator::Glob),
+            rhs,
+            escape,
+        } = self
+        else {
+            return None;
+        };
+        if !matches!(
+            **lhs,
+            Expr::Id(_) | Expr::Name(_) | Expr::Qualified(..) | Expr::DoublyQualified(..)
+        ) {
+            return None;
+        }
+        let Expr::Literal(Literal::String(ref pattern)) = **rhs else {
+            return None;
+        };
+        let escape = match (op, escape.as_deref()) {
+            (_, None) => None,
+            (LikeOperator::Like, Some(Expr::Literal(Literal::String(escape)))) => {
+                let escape = unescape(escape);
+                let mut chars = escape.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some(c),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        let mut prefix = String::new();
+        let pattern = unescape(pattern);
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            match (op, c) {
+                (_, c) if Some(c) == escape => prefix.push(chars.next()?),
+                (LikeOperator::Like, '%' | '_') | (LikeOperator::Glob, '*' | '?' | '[') => break,
+                _ => prefix.push(c),
+            }
+        }
+        if prefix.is_empty() {
+            return None;
+        }
+        Some((lhs, prefix))
+    }
 }
 impl ToTokens for Expr {
     fn to_tokens<S: TokenStream>(&self, s: &mut S) -> Result<(), S::Error> {
@@ -859,19 +1208,22 @@ impl ToTokens for Expr {
                 start,
                 end,
             } => {
-                lhs.to_tokens(s)?;
+                postfix_operand(lhs, s)?;
                 if *not {
                     s.append(TK_NOT, None)?;
                 }
                 s.append(TK_BETWEEN, None)?;
-                start.to_tokens(s)?;
+                postfix_rhs_operand(start, s)?;
                 s.append(TK_AND, None)?;
-                end.to_tokens(s)
+                postfix_rhs_operand(end, s)
             }
             Expr::Binary(lhs, op, rhs) => {
-                lhs.to_tokens(s)?;
+                // operands built without `Expr::Parenthesized` (not parsed)
+                // but binding less tightly than `op` (left-associative)
+                let precedence = op.precedence();
+                operand(lhs, binding(lhs).is_some_and(|p| p < precedence), s)?;
                 op.to_tokens(s)?;
-                rhs.to_tokens(s)
+                operand(rhs, binding(rhs).is_some_and(|p| p <= precedence), s)
             }
             Expr::Case {
                 base,
@@ -903,7 +1255,8 @@ impl ToTokens for Expr {
                 s.append(TK_RP, None)
             }
             Expr::Collate(expr, collation) => {
-                expr.to_tokens(s)?;
+                // `COLLATE` binds more tightly than any binary operator
+                operand(expr, binding(expr).is_some(), s)?;
                 s.append(TK_COLLATE, None)?;
                 double_quote(collation, s)
             }
@@ -952,7 +1305,7 @@ impl ToTokens for Expr {
             }
             Expr::Id(id) => id.to_tokens(s),
             Expr::InList { lhs, not, rhs } => {
-                lhs.to_tokens(s)?;
+                postfix_operand(lhs, s)?;
                 if *not {
                     s.append(TK_NOT, None)?;
                 }
@@ -964,7 +1317,7 @@ impl ToTokens for Expr {
                 s.append(TK_RP, None)
             }
             Expr::InSelect { lhs, not, rhs } => {
-                lhs.to_tokens(s)?;
+                postfix_operand(lhs, s)?;
                 if *not {
                     s.append(TK_NOT, None)?;
                 }
@@ -979,7 +1332,7 @@ impl ToTokens for Expr {
                 rhs,
                 args,
             } => {
-                lhs.to_tokens(s)?;
+                postfix_operand(lhs, s)?;
                 if *not {
                     s.append(TK_NOT, None)?;
                 }
@@ -993,7 +1346,7 @@ impl ToTokens for Expr {
                 Ok(())
             }
             Expr::IsNull(sub_expr) => {
-                sub_expr.to_tokens(s)?;
+                postfix_operand(sub_expr, s)?;
                 s.append(TK_ISNULL, None)
             }
             Expr::Like {
@@ -1003,22 +1356,22 @@ impl ToTokens for Expr {
                 rhs,
                 escape,
             } => {
-                lhs.to_tokens(s)?;
+                postfix_operand(lhs, s)?;
                 if *not {
                     s.append(TK_NOT, None)?;
                 }
                 op.to_tokens(s)?;
-                rhs.to_tokens(s)?;
+                postfix_rhs_operand(rhs, s)?;
                 if let Some(escape) = escape {
                     s.append(TK_ESCAPE, None)?;
-                    escape.to_tokens(s)?;
+                    postfix_rhs_operand(escape, s)?;
                 }
                 Ok(())
             }
             Expr::Literal(lit) => lit.to_tokens(s),
             Expr::Name(name) => name.to_tokens(s),
             Expr::NotNull(sub_expr) => {
-                sub_expr.to_tokens(s)?;
+                postfix_operand(sub_expr, s)?;
                 s.append(TK_NOTNULL, None)
             }
             Expr::Parenthesized(exprs) => {
@@ -1048,7 +1401,13 @@ impl ToTokens for Expr {
             }
             Expr::Unary(op, sub_expr) => {
                 op.to_tokens(s)?;
-                sub_expr.to_tokens(s)
+                // `NOT` binds less tightly than comparisons but more than `AND`,
+                // other unary operators more tightly than any binary operator
+                let parenthesize = match op {
+                    UnaryOperator::Not => binding(sub_expr).is_some_and(|p| p < 3),
+                    _ => binding(sub_expr).is_some(),
+                };
+                operand(sub_expr, parenthesize, s)
             }
             Expr::Variable(var) => match var.chars().next() {
                 Some(c) if c == '$' || c == '@' || c == '#' || c == ':' => {
@@ -1067,7 +1426,7 @@ impl Display for Expr {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Literal {
     Numeric(String),
     // TODO Check that string is already quoted and correctly escaped
@@ -1113,7 +1472,7 @@ impl ToTokens for Literal {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum LikeOperator {
     Glob,
     Like,
@@ -1153,7 +1512,7 @@ impl ToTokens for LikeOperator {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Operator {
     Add,
     And,
@@ -1167,7 +1526,9 @@ pub enum Operator {
     Greater,
     GreaterEquals,
     Is,
+    IsDistinctFrom,
     IsNot,
+    IsNotDistinctFrom,
     LeftShift,
     Less,
     LessEquals,
@@ -1206,39 +1567,116 @@ impl From<YYCODETYPE> for Operator {
         }
     }
 }
+impl Operator {
+    // Binding power (see `%left` declarations of the grammar)
+    fn precedence(&self) -> u8 {
+        match self {
+            Operator::Or => 1,
+            Operator::And => 2,
+            Operator::Equals
+            | Operator::NotEquals
+            | Operator::Is
+            | Operator::IsNot
+            | Operator::IsDistinctFrom
+            | Operator::IsNotDistinctFrom => 4,
+            Operator::Greater | Operator::GreaterEquals | Operator::Less | Operator::LessEquals => {
+                5
+            }
+            Operator::BitwiseAnd
+            | Operator::BitwiseOr
+            | Operator::LeftShift
+            | Operator::RightShift => 7,
+            Operator::Add | Operator::Substract => 8,
+            Operator::Multiply | Operator::Divide | Operator::Modulus => 9,
+            Operator::Concat | Operator::ArrowRight | Operator::ArrowRightShift => 10,
+        }
+    }
+}
+
+// Binding power of an operand of a binary operator
+// (`None` when it cannot be split by a binary operator)
+fn binding(expr: &Expr) -> Option<u8> {
+    match expr {
+        Expr::Binary(_, op, _) => Some(op.precedence()),
+        Expr::Between { .. }
+        | Expr::InList { .. }
+        | Expr::InSelect { .. }
+        | Expr::InTable { .. }
+        | Expr::IsNull(_)
+        | Expr::NotNull(_)
+        | Expr::Like { .. } => Some(4),
+        // `NOT a = b` is `NOT (a = b)`
+        Expr::Unary(UnaryOperator::Not, _) => Some(3),
+        _ => None,
+    }
+}
+
+// Render the left operand of a postfix operator (`IN`, `BETWEEN`, `LIKE`, ...):
+// `NOT a IN (...)` is `NOT (a IN (...))`
+fn postfix_operand<S: TokenStream>(lhs: &Expr, s: &mut S) -> Result<(), S::Error> {
+    operand(lhs, binding(lhs).is_some_and(|p| p < 4), s)
+}
+
+// Render the other operands of a postfix operator (`BETWEEN x AND y`,
+// `LIKE x ESCAPE y`): `a LIKE b OR c` is `(a LIKE b) OR c`
+fn postfix_rhs_operand<S: TokenStream>(expr: &Expr, s: &mut S) -> Result<(), S::Error> {
+    operand(expr, binding(expr).is_some_and(|p| p <= 4), s)
+}
+
+fn operand<S: TokenStream>(expr: &Expr, parenthesize: bool, s: &mut S) -> Result<(), S::Error> {
+    if parenthesize {
+        s.append(TK_LP, None)?;
+        expr.to_tokens(s)?;
+        s.append(TK_RP, None)
+    } else {
+        expr.to_tokens(s)
+    }
+}
+
 impl ToTokens for Operator {
     fn to_tokens<S: TokenStream>(&self, s: &mut S) -> Result<(), S::Error> {
         match self {
-            Operator::Add => s.append(TK_PLUS, None),
+            Operator::Add => s.append_operator(TK_PLUS, None),
             Operator::And => s.append(TK_AND, None),
-            Operator::ArrowRight => s.append(TK_PTR, Some("->")),
-            Operator::ArrowRightShift => s.append(TK_PTR, Some("->>")),
-            Operator::BitwiseAnd => s.append(TK_BITAND, None),
-            Operator::BitwiseOr => s.append(TK_BITOR, None),
+            Operator::ArrowRight => s.append_operator(TK_PTR, Some("->")),
+            Operator::ArrowRightShift => s.append_operator(TK_PTR, Some("->>")),
+            Operator::BitwiseAnd => s.append_operator(TK_BITAND, None),
+            Operator::BitwiseOr => s.append_operator(TK_BITOR, None),
             Operator::Concat => s.append(TK_CONCAT, None),
-            Operator::Equals => s.append(TK_EQ, None),
-            Operator::Divide => s.append(TK_SLASH, None),
-            Operator::Greater => s.append(TK_GT, None),
-            Operator::GreaterEquals => s.append(TK_GE, None),
+            Operator::Equals => s.append_operator(TK_EQ, None),
+            Operator::Divide => s.append_operator(TK_SLASH, None),
+            Operator::Greater => s.append_operator(TK_GT, None),
+            Operator::GreaterEquals => s.append_operator(TK_GE, None),
             Operator::Is => s.append(TK_IS, None),
+            Operator::IsDistinctFrom => {
+                s.append(TK_IS, None)?;
+                s.append(TK_DISTINCT, None)?;
+                s.append(TK_FROM, None)
+            }
             Operator::IsNot => {
                 s.append(TK_IS, None)?;
                 s.append(TK_NOT, None)
             }
-            Operator::LeftShift => s.append(TK_LSHIFT, None),
-            Operator::Less => s.append(TK_LT, None),
-            Operator::LessEquals => s.append(TK_LE, None),
-            Operator::Modulus => s.append(TK_REM, None),
-            Operator::Multiply => s.append(TK_STAR, None),
-            Operator::NotEquals => s.append(TK_NE, None),
+            Operator::IsNotDistinctFrom => {
+                s.append(TK_IS, None)?;
+                s.append(TK_NOT, None)?;
+                s.append(TK_DISTINCT, None)?;
+                s.append(TK_FROM, None)
+            }
+            Operator::LeftShift => s.append_operator(TK_LSHIFT, None),
+            Operator::Less => s.append_operator(TK_LT, None),
+            Operator::LessEquals => s.append_operator(TK_LE, None),
+            Operator::Modulus => s.append_operator(TK_REM, None),
+            Operator::Multiply => s.append_operator(TK_STAR, None),
+            Operator::NotEquals => s.append_operator(TK_NE, None),
             Operator::Or => s.append(TK_OR, None),
-            Operator::RightShift => s.append(TK_RSHIFT, None),
-            Operator::Substract => s.append(TK_MINUS, None),
+            Operator::RightShift => s.append_operator(TK_RSHIFT, None),
+            Operator::Substract => s.append_operator(TK_MINUS, None),
         }
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum UnaryOperator {
     // bitwise negation (~)
     BitwiseNot,
@@ -1277,7 +1715,7 @@ impl ToTokens for UnaryOperator {
 
 // https://sqlite.org/lang_select.html
 // https://sqlite.org/syntax/factored-select-stmt.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Select {
     pub with: Option<With>,
     pub body: SelectBody,
@@ -1302,7 +1740,24 @@ impl ToTokens for Select {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+impl Select {
+    /// Number of result columns of each select of the compound (`UNION`,
+    /// `EXCEPT`, ...), in order: SQLite requires them to be equal.
+    /// `*` and `t.*` are not expanded and count as one column.
+    /// A `VALUES` clause counts the values of its first row.
+    pub fn union_column_counts(&self) -> Vec<usize> {
+        let count = |select: &OneSelect| match select {
+            OneSelect::Select { columns, .. } => columns.len(),
+            OneSelect::Values(values) => values.first().map_or(0, Vec::len),
+        };
+        core::iter::once(&self.body.select)
+            .chain(self.body.compounds.iter().flatten().map(|c| &c.select))
+            .map(count)
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct SelectBody {
     pub select: OneSelect,
     pub compounds: Option<Vec<CompoundSelect>>,
@@ -1329,7 +1784,7 @@ impl ToTokens for SelectBody {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct CompoundSelect {
     pub operator: CompoundOperator,
     pub select: OneSelect,
@@ -1342,7 +1797,7 @@ impl ToTokens for CompoundSelect {
 }
 
 // https://sqlite.org/syntax/compound-operator.html
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum CompoundOperator {
     Union,
     UnionAll,
@@ -1364,7 +1819,7 @@ impl ToTokens for CompoundOperator {
 }
 
 // https://sqlite.org/syntax/select-core.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum OneSelect {
     Select {
         distinctness: Option<Distinctness>,
@@ -1426,8 +1881,215 @@ impl ToTokens for OneSelect {
     }
 }
 
+impl OneSelect {
+    /// Check whether this subquery references a column of one of the `outer_tables`
+    /// (table names or aliases of the enclosing query).
+    ///
+    /// Only qualified column references (`t.c`, `s.t.c`) are considered,
+    /// unqualified ones cannot be resolved without the schema.
+    /// Tables of this subquery (or of a nested one) shadow outer tables with the same name.
+    pub fn is_correlated(&self, outer_tables: &[Name]) -> bool {
+        let mut c = Correlation {
+            outer: outer_tables.iter().map(|name| unquote(&name.0)).collect(),
+            locals: vec![],
+        };
+        c.one_select(self)
+    }
+}
+
+/// Resolve qualified column references against outer and local tables
+struct Correlation<'a> {
+    outer: Vec<&'a str>,
+    // tables in scope, from the outermost subquery to the innermost one
+    locals: Vec<&'a str>,
+}
+
+impl<'a> Correlation<'a> {
+    fn is_outer(&self, tbl_name: &Name) -> bool {
+        let tbl_name = unquote(&tbl_name.0);
+        let eq = |name: &&str| name.eq_ignore_ascii_case(tbl_name);
+        !self.locals.iter().any(eq) && self.outer.iter().any(eq)
+    }
+
+    fn select(&mut self, select: &'a Select) -> bool {
+        let len = self.locals.len();
+        let correlated = self.select_in_scope(select);
+        self.locals.truncate(len);
+        correlated
+    }
+
+    fn select_in_scope(&mut self, select: &'a Select) -> bool {
+        if let Some(ref with) = select.with {
+            for cte in &with.ctes {
+                self.locals.push(unquote(&cte.tbl_name.0));
+            }
+            if with.ctes.iter().any(|cte| self.select(&cte.select)) {
+                return true;
+            }
+        }
+        if self.one_select(&select.body.select) {
+            return true;
+        }
+        if let Some(ref compounds) = select.body.compounds {
+            if compounds.iter().any(|c| self.one_select(&c.select)) {
+                return true;
+            }
+        }
+        if let Some(ref order_by) = select.order_by {
+            if order_by.iter().any(|sc| self.expr(&sc.expr)) {
+                return true;
+            }
+        }
+        if let Some(ref limit) = select.limit {
+            if self.expr(&limit.expr) || self.opt_expr(limit.offset.as_ref()) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn one_select(&mut self, select: &'a OneSelect) -> bool {
+        match select {
+            OneSelect::Select {
+                columns,
+                from,
+                where_clause,
+                group_by,
+                window_clause,
+                ..
+            } => {
+                let len = self.locals.len();
+                let correlated = from.as_ref().is_some_and(|from| self.from(from))
+                    || columns.iter().any(|col| match col {
+                        ResultColumn::Expr(expr, _) => self.expr(expr),
+                        _ => false,
+                    })
+                    || self.opt_expr(where_clause.as_ref())
+                    || group_by.as_ref().is_some_and(|group_by| {
+                        self.exprs(&group_by.exprs) || self.opt_expr(group_by.having.as_ref())
+                    })
+                    || window_clause.as_ref().is_some_and(|window_clause| {
+                        window_clause.iter().any(|def| self.window(&def.window))
+                    });
+                self.locals.truncate(len);
+                correlated
+            }
+            OneSelect::Values(values) => values.iter().any(|row| self.exprs(row)),
+        }
+    }
+
+    // Push the tables of `from` into the current scope
+    fn from(&mut self, from: &'a FromClause) -> bool {
+        let tables = from
+            .select
+            .iter()
+            .map(|table| &**table)
+            .chain(from.joins.iter().flatten().map(|join| &join.table));
+        for table in tables {
+            if self.select_table(table) {
+                return true;
+            }
+        }
+        from.joins
+            .iter()
+            .flatten()
+            .any(|join| match join.constraint {
+                Some(JoinConstraint::On(ref expr)) => self.expr(expr),
+                _ => false,
+            })
+    }
+
+    fn select_table(&mut self, table: &'a SelectTable) -> bool {
+        let (name, alias) = match table {
+            SelectTable::Table(name, alias, _) => (Some(name), alias),
+            SelectTable::TableCall(name, args, alias) => {
+                if self.opt_exprs(args.as_ref()) {
+                    return true;
+                }
+                (Some(name), alias)
+            }
+            SelectTable::Select(select, alias) => {
+                if self.select(select) {
+                    return true;
+                }
+                (None, alias)
+            }
+            SelectTable::Sub(from, alias) => {
+                if self.from(from) {
+                    return true;
+                }
+                (None, alias)
+            }
+        };
+        match (alias, name) {
+            (Some(As::As(alias)), _) | (Some(As::Elided(alias)), _) => {
+                self.locals.push(unquote(&alias.0))
+            }
+            (None, Some(name)) => self.locals.push(unquote(&name.name.0)),
+            (None, None) => {}
+        };
+        false
+    }
+
+    fn window(&mut self, window: &'a Window) -> bool {
+        window
+            .partition_by
+            .iter()
+            .flatten()
+            .any(|expr| self.expr(expr))
+            || window
+                .order_by
+                .iter()
+                .flatten()
+                .any(|sc| self.expr(&sc.expr))
+    }
+
+    fn opt_expr(&mut self, expr: Option<&'a Expr>) -> bool {
+        expr.is_some_and(|expr| self.expr(expr))
+    }
+
+    fn exprs(&mut self, exprs: &'a [Expr]) -> bool {
+        exprs.iter().any(|expr| self.expr(expr))
+    }
+
+    fn opt_exprs(&mut self, exprs: Option<&'a Vec<Expr>>) -> bool {
+        exprs.is_some_and(|exprs| self.exprs(exprs))
+    }
+
+    fn expr(&mut self, expr: &'a Expr) -> bool {
+        expr.find(|expr| match expr {
+            Expr::DoublyQualified(_, tbl_name, _) | Expr::Qualified(tbl_name, _) => {
+                self.is_outer(tbl_name)
+            }
+            Expr::Exists(select) | Expr::Subquery(select) => self.select(select),
+            Expr::InSelect { rhs, .. } => self.select(rhs),
+            _ => false,
+        })
+        .is_some()
+    }
+}
+
+// Value of a string literal
+fn unescape(s: &str) -> String {
+    s[1..s.len() - 1].replace("''", "'")
+}
+
+// Strip delimiters from a quoted identifier
+fn unquote(name: &str) -> &str {
+    let bytes = name.as_bytes();
+    if bytes.len() >= 2 {
+        match (bytes[0], bytes[bytes.len() - 1]) {
+            (b'"', b'"') | (b'\'', b'\'') | (b'`', b'`') | (b'[', b']') => {
+                return &name[1..name.len() - 1];
+            }
+            _ => {}
+        }
+    }
+    name
+}
+
 // https://sqlite.org/syntax/join-clause.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct FromClause {
     pub select: Option<Box<SelectTable>>, // FIXME mandatory
     pub joins: Option<Vec<JoinedSelectTable>>,
@@ -1479,7 +2141,7 @@ impl ToTokens for FromClause {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Distinctness {
     Distinct,
     All,
@@ -1497,7 +2159,7 @@ impl ToTokens for Distinctness {
 }
 
 // https://sqlite.org/syntax/result-column.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ResultColumn {
     Expr(Expr, Option<As>),
     Star,
@@ -1524,7 +2186,7 @@ impl ToTokens for ResultColumn {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum As {
     As(Name),
     Elided(Name), // FIXME Ids
@@ -1542,7 +2204,7 @@ impl ToTokens for As {
 }
 
 // https://sqlite.org/syntax/join-clause.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct JoinedSelectTable {
     pub operator: JoinOperator,
     pub table: SelectTable,
@@ -1560,7 +2222,7 @@ impl ToTokens for JoinedSelectTable {
 }
 
 // https://sqlite.org/syntax/table-or-subquery.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum SelectTable {
     Table(QualifiedName, Option<As>, Option<Indexed>),
     TableCall(QualifiedName, Option<Vec<Expr>>, Option<As>),
@@ -1615,7 +2277,7 @@ impl ToTokens for SelectTable {
 }
 
 // https://sqlite.org/syntax/join-operator.html
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum JoinOperator {
     Comma,
     TypedJoin {
@@ -1750,6 +2412,10 @@ impl JoinOperator {
 impl ToTokens for JoinOperator {
     fn to_tokens<S: TokenStream>(&self, s: &mut S) -> Result<(), S::Error> {
         match self {
+            JoinOperator::Comma if s.format_options().cross_join => {
+                JoinType::Cross.to_tokens(s)?;
+                s.append(TK_JOIN, None)
+            }
             JoinOperator::Comma => s.append(TK_COMMA, None),
             JoinOperator::TypedJoin { natural, join_type } => {
                 if *natural {
@@ -1764,7 +2430,7 @@ impl ToTokens for JoinOperator {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum JoinType {
     Left,
     LeftOuter,
@@ -1793,7 +2459,7 @@ impl ToTokens for JoinType {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum JoinConstraint {
     On(Expr),
     // col names
@@ -1817,16 +2483,19 @@ impl ToTokens for JoinConstraint {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct GroupBy {
+    /// empty for an aggregate query with a `HAVING` clause but without `GROUP BY`
     pub exprs: Vec<Expr>,
     pub having: Option<Expr>,
 }
 impl ToTokens for GroupBy {
     fn to_tokens<S: TokenStream>(&self, s: &mut S) -> Result<(), S::Error> {
-        s.append(TK_GROUP, None)?;
-        s.append(TK_BY, None)?;
-        comma(&self.exprs, s)?;
+        if !self.exprs.is_empty() {
+            s.append(TK_GROUP, None)?;
+            s.append(TK_BY, None)?;
+            comma(&self.exprs, s)?;
+        }
         if let Some(ref having) = self.having {
             s.append(TK_HAVING, None)?;
             having.to_tokens(s)?;
@@ -1836,7 +2505,7 @@ impl ToTokens for GroupBy {
 }
 
 /// identifier or one of several keywords or `INDEXED`
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Id(pub String);
 
 impl Id {
@@ -1853,7 +2522,7 @@ impl ToTokens for Id {
 // TODO ids (identifier or string)
 
 /// identifier or string or `CROSS` or `FULL` or `INNER` or `LEFT` or `NATURAL` or `OUTER` or `RIGHT`.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Name(pub String); // TODO distinction between Name and "Name"/[Name]/`Name`
 
 impl Name {
@@ -1873,7 +2542,7 @@ impl Display for Name {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct QualifiedName {
     pub db_name: Option<Name>,
     pub name: Name,
@@ -1926,7 +2595,7 @@ impl ToTokens for QualifiedName {
 }
 
 // https://sqlite.org/lang_altertable.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum AlterTableBody {
     // new table name
     RenameTo(Name),
@@ -1964,7 +2633,7 @@ impl ToTokens for AlterTableBody {
 
 // https://sqlite.org/lang_createtable.html
 // https://sqlite.org/syntax/create-table-stmt.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum CreateTableBody {
     ColumnsAndConstraints {
         columns: Vec<ColumnDefinition>,
@@ -1986,6 +2655,94 @@ impl CreateTableBody {
             options,
         })
     }
+
+    /// Semantic errors reported by SQLite when the table is created
+    /// (misplaced `AUTOINCREMENT`, `WITHOUT ROWID` table without `PRIMARY KEY`)
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        let CreateTableBody::ColumnsAndConstraints {
+            columns,
+            constraints,
+            options,
+        } = self
+        else {
+            return diagnostics;
+        };
+        // a rowid alias: INTEGER PRIMARY KEY (but not DESC)
+        let rowid_alias = |column: &ColumnDefinition, order: &Option<SortOrder>| {
+            column
+                .col_type
+                .as_ref()
+                .is_some_and(|t| t.name.eq_ignore_ascii_case("INTEGER"))
+                && *order != Some(SortOrder::Desc)
+        };
+        let mut auto_increment = false;
+        for column in columns {
+            for constraint in column.constraints.iter() {
+                if let ColumnConstraint::PrimaryKey {
+                    ref order,
+                    auto_increment: true,
+                    ..
+                } = constraint.constraint
+                {
+                    auto_increment = true;
+                    if !rowid_alias(column, order) {
+                        diagnostics.push(Diagnostic::new(
+                            "AUTOINCREMENT is only allowed on an INTEGER PRIMARY KEY",
+                        ));
+                    }
+                }
+            }
+        }
+        for constraint in constraints.iter().flatten() {
+            if let TableConstraint::PrimaryKey {
+                columns: ref pk,
+                auto_increment: true,
+                ..
+            } = constraint.constraint
+            {
+                auto_increment = true;
+                let valid = match pk.as_slice() {
+                    [SortedColumn {
+                        expr: Expr::Id(Id(name)),
+                        order,
+                        ..
+                    }] => columns
+                        .iter()
+                        .find(|c| unquote(&c.col_name.0).eq_ignore_ascii_case(unquote(name)))
+                        .is_some_and(|c| rowid_alias(c, order)),
+                    _ => false,
+                };
+                if !valid {
+                    diagnostics.push(Diagnostic::new(
+                        "AUTOINCREMENT is only allowed on an INTEGER PRIMARY KEY",
+                    ));
+                }
+            }
+        }
+        if options.contains(TableOptions::WITHOUT_ROWID) {
+            if auto_increment {
+                diagnostics.push(Diagnostic::new(
+                    "AUTOINCREMENT not allowed on WITHOUT ROWID tables",
+                ));
+            }
+            let primary_key = columns.iter().any(|column| {
+                column
+                    .constraints
+                    .iter()
+                    .any(|c| matches!(c.constraint, ColumnConstraint::PrimaryKey { .. }))
+            }) || constraints
+                .iter()
+                .flatten()
+                .any(|c| matches!(c.constraint, TableConstraint::PrimaryKey { .. }));
+            if !primary_key {
+                diagnostics.push(Diagnostic::new(
+                    "PRIMARY KEY missing on WITHOUT ROWID table",
+                ));
+            }
+        }
+        diagnostics
+    }
 }
 
 impl ToTokens for CreateTableBody {
@@ -2021,7 +2778,7 @@ impl ToTokens for CreateTableBody {
 }
 
 // https://sqlite.org/syntax/column-def.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ColumnDefinition {
     pub col_name: Name,
     pub col_type: Option<Type>,
@@ -2040,6 +2797,44 @@ impl ToTokens for ColumnDefinition {
     }
 }
 impl ColumnDefinition {
+    /// Constraints that SQLite rejects in `ALTER TABLE ... ADD COLUMN`
+    /// (see <https://sqlite.org/lang_altertable.html#altertabaddcol>)
+    pub fn add_column_warnings(&self) -> Vec<Diagnostic> {
+        let mut warnings = vec![];
+        let mut not_null = false;
+        let mut default_null = true;
+        for constraint in self.constraints.iter() {
+            match constraint.constraint {
+                ColumnConstraint::PrimaryKey { .. } => {
+                    warnings.push(Diagnostic::new("Cannot add a PRIMARY KEY column"))
+                }
+                ColumnConstraint::Unique(..) => {
+                    warnings.push(Diagnostic::new("Cannot add a UNIQUE column"))
+                }
+                ColumnConstraint::NotNull {
+                    nullable: false, ..
+                } => not_null = true,
+                ColumnConstraint::Default(ref expr) => {
+                    default_null = matches!(expr, Expr::Literal(Literal::Null))
+                }
+                ColumnConstraint::Generated { ref typ, .. }
+                    if typ
+                        .as_ref()
+                        .is_some_and(|t| t.0.eq_ignore_ascii_case("STORED")) =>
+                {
+                    warnings.push(Diagnostic::new("cannot add a STORED column"))
+                }
+                _ => {}
+            }
+        }
+        if not_null && default_null {
+            warnings.push(Diagnostic::new(
+                "Cannot add a NOT NULL column with default value NULL",
+            ));
+        }
+        warnings
+    }
+
     pub fn add_column(
         columns: &mut Vec<ColumnDefinition>,
         cd: ColumnDefinition,
@@ -2058,8 +2853,17 @@ impl ColumnDefinition {
     }
 }
 
+fn check_constraint_expr(expr: &Expr) -> Result<(), ParserError> {
+    if expr.has_subquery() {
+        return Err(ParserError(
+            "subqueries prohibited in CHECK constraints".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
 // https://sqlite.org/syntax/column-constraint.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct NamedColumnConstraint {
     pub name: Option<Name>,
     pub constraint: ColumnConstraint,
@@ -2075,7 +2879,7 @@ impl ToTokens for NamedColumnConstraint {
 }
 
 // https://sqlite.org/syntax/column-constraint.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ColumnConstraint {
     PrimaryKey {
         order: Option<SortOrder>,
@@ -2102,6 +2906,12 @@ pub enum ColumnConstraint {
         typ: Option<Id>,
     },
 }
+impl ColumnConstraint {
+    pub fn check(expr: Expr) -> Result<ColumnConstraint, ParserError> {
+        check_constraint_expr(&expr)?;
+        Ok(ColumnConstraint::Check(expr))
+    }
+}
 impl ToTokens for ColumnConstraint {
     fn to_tokens<S: TokenStream>(&self, s: &mut S) -> Result<(), S::Error> {
         match self {
@@ -2190,7 +3000,7 @@ impl ToTokens for ColumnConstraint {
 }
 
 // https://sqlite.org/syntax/table-constraint.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct NamedTableConstraint {
     pub name: Option<Name>,
     pub constraint: TableConstraint,
@@ -2206,7 +3016,7 @@ impl ToTokens for NamedTableConstraint {
 }
 
 // https://sqlite.org/syntax/table-constraint.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum TableConstraint {
     PrimaryKey {
         columns: Vec<SortedColumn>,
@@ -2224,6 +3034,12 @@ pub enum TableConstraint {
         deref_clause: Option<DeferSubclause>,
     },
 }
+impl TableConstraint {
+    pub fn check(expr: Expr) -> Result<TableConstraint, ParserError> {
+        check_constraint_expr(&expr)?;
+        Ok(TableConstraint::Check(expr))
+    }
+}
 impl ToTokens for TableConstraint {
     fn to_tokens<S: TokenStream>(&self, s: &mut S) -> Result<(), S::Error> {
         match self {
@@ -2297,7 +3113,7 @@ bitflags::bitflags! {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum SortOrder {
     Asc,
     Desc,
@@ -2314,7 +3130,7 @@ impl ToTokens for SortOrder {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum NullsOrder {
     First,
     Last,
@@ -2333,7 +3149,7 @@ impl ToTokens for NullsOrder {
 }
 
 // https://sqlite.org/syntax/foreign-key-clause.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ForeignKeyClause {
     pub tbl_name: Name,
     pub columns: Option<Vec<IndexedColumn>>,
@@ -2354,7 +3170,7 @@ impl ToTokens for ForeignKeyClause {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum RefArg {
     OnDelete(RefAct),
     OnInsert(RefAct),
@@ -2387,7 +3203,7 @@ impl ToTokens for RefArg {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum RefAct {
     SetNull,
     SetDefault,
@@ -2416,7 +3232,7 @@ impl ToTokens for RefAct {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct DeferSubclause {
     pub deferrable: bool,
     pub init_deferred: Option<InitDeferredPred>,
@@ -2434,7 +3250,7 @@ impl ToTokens for DeferSubclause {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum InitDeferredPred {
     InitiallyDeferred,
     InitiallyImmediate, // default
@@ -2453,7 +3269,7 @@ impl ToTokens for InitDeferredPred {
 }
 
 // https://sqlite.org/syntax/indexed-column.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct IndexedColumn {
     pub col_name: Name,
     pub collation_name: Option<Name>, // FIXME Ids
@@ -2473,7 +3289,7 @@ impl ToTokens for IndexedColumn {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Indexed {
     // idx name
     IndexedBy(Name),
@@ -2495,7 +3311,7 @@ impl ToTokens for Indexed {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct SortedColumn {
     pub expr: Expr,
     pub order: Option<SortOrder>,
@@ -2514,7 +3330,7 @@ impl ToTokens for SortedColumn {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Limit {
     pub expr: Expr,
     pub offset: Option<Expr>, // TODO distinction between LIMIT offset, count and LIMIT count OFFSET offset
@@ -2533,7 +3349,7 @@ impl ToTokens for Limit {
 
 // https://sqlite.org/lang_insert.html
 // https://sqlite.org/syntax/insert-stmt.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum InsertBody {
     Select(Select, Option<Upsert>),
     DefaultValues,
@@ -2556,7 +3372,7 @@ impl ToTokens for InsertBody {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Set {
     pub col_names: Vec<Name>,
     pub expr: Expr,
@@ -2576,10 +3392,15 @@ impl ToTokens for Set {
 }
 
 // https://sqlite.org/syntax/pragma-stmt.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum PragmaBody {
     Equals(PragmaValue),
     Call(PragmaValue),
+    /// `= ...` with an argument which does not fit the value grammar
+    /// (raw text captured with `ParserOptions::lenient_pragma`)
+    RawEquals(String),
+    /// `(...)` with an argument which does not fit the value grammar
+    RawCall(String),
 }
 impl ToTokens for PragmaBody {
     fn to_tokens<S: TokenStream>(&self, s: &mut S) -> Result<(), S::Error> {
@@ -2593,6 +3414,15 @@ impl ToTokens for PragmaBody {
                 value.to_tokens(s)?;
                 s.append(TK_RP, None)
             }
+            PragmaBody::RawEquals(raw) => {
+                s.append(TK_EQ, None)?;
+                s.append(TK_ANY, Some(raw))
+            }
+            PragmaBody::RawCall(raw) => {
+                s.append(TK_LP, None)?;
+                s.append(TK_ANY, Some(raw))?;
+                s.append(TK_RP, None)
+            }
         }
     }
 }
@@ -2600,7 +3430,7 @@ impl ToTokens for PragmaBody {
 // https://sqlite.org/syntax/pragma-value.html
 pub type PragmaValue = Expr; // TODO
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum TriggerTime {
     Before, // default
     After,
@@ -2619,7 +3449,7 @@ impl ToTokens for TriggerTime {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum TriggerEvent {
     Delete,
     Insert,
@@ -2644,7 +3474,7 @@ impl ToTokens for TriggerEvent {
 
 // https://sqlite.org/lang_createtrigger.html
 // https://sqlite.org/syntax/create-trigger-stmt.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum TriggerCmd {
     Update {
         or_conflict: Option<ResolveType>,
@@ -2747,7 +3577,7 @@ impl ToTokens for TriggerCmd {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ResolveType {
     Rollback,
     Abort, // default
@@ -2772,7 +3602,7 @@ impl ToTokens for ResolveType {
 
 // https://sqlite.org/lang_with.html
 // https://sqlite.org/syntax/with-clause.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct With {
     pub recursive: bool,
     pub ctes: Vec<CommonTableExpr>,
@@ -2786,8 +3616,19 @@ impl ToTokens for With {
         comma(&self.ctes, s)
     }
 }
+impl With {
+    /// CTEs which SQLite rejects as circular references: a CTE referencing
+    /// itself other than directly in the `FROM` clause of a recursive select
+    /// (one following the first select of a `UNION` / `UNION ALL` compound),
+    /// or referencing itself through other CTEs.
+    /// Like SQLite, the `RECURSIVE` keyword is not required and a CTE may
+    /// reference a CTE defined later in the same clause.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        validate::with_cycles(self)
+    }
+}
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Materialized {
     Any,
     Yes,
@@ -2795,7 +3636,7 @@ pub enum Materialized {
 }
 
 // https://sqlite.org/syntax/common-table-expression.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct CommonTableExpr {
     pub tbl_name: Name,
     pub columns: Option<Vec<IndexedColumn>>,
@@ -2848,7 +3689,7 @@ impl CommonTableExpr {
 }
 
 // https://sqlite.org/syntax/type-name.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Type {
     pub name: String, // TODO Validate: Ids+
     pub size: Option<TypeSize>,
@@ -2868,7 +3709,7 @@ impl ToTokens for Type {
 }
 
 // https://sqlite.org/syntax/type-name.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum TypeSize {
     MaxSize(Box<Expr>),
     TypeSize(Box<Expr>, Box<Expr>),
@@ -2887,7 +3728,7 @@ impl ToTokens for TypeSize {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum TransactionType {
     Deferred, // default
     Immediate,
@@ -2908,7 +3749,7 @@ impl ToTokens for TransactionType {
 
 // https://sqlite.org/lang_upsert.html
 // https://sqlite.org/syntax/upsert-clause.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Upsert {
     pub index: Option<UpsertIndex>,
     pub do_clause: UpsertDo,
@@ -2930,7 +3771,7 @@ impl ToTokens for Upsert {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct UpsertIndex {
     pub targets: Vec<SortedColumn>,
     pub where_clause: Option<Expr>,
@@ -2949,7 +3790,7 @@ impl ToTokens for UpsertIndex {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum UpsertDo {
     Set {
         sets: Vec<Set>,
@@ -2980,7 +3821,7 @@ impl ToTokens for UpsertDo {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct FunctionTail {
     pub filter_clause: Option<Box<Expr>>,
     pub over_clause: Option<Box<Over>>,
@@ -3003,7 +3844,7 @@ impl ToTokens for FunctionTail {
 }
 
 // https://sqlite.org/syntax/over-clause.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Over {
     Window(Window),
     Name(Name),
@@ -3018,7 +3859,7 @@ impl ToTokens for Over {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct WindowDef {
     pub name: Name,
     pub window: Window,
@@ -3032,7 +3873,7 @@ impl ToTokens for WindowDef {
 }
 
 // https://sqlite.org/syntax/window-defn.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Window {
     pub base: Option<Name>,
     pub partition_by: Option<Vec<Expr>>,
@@ -3064,7 +3905,7 @@ impl ToTokens for Window {
 }
 
 // https://sqlite.org/syntax/frame-spec.html
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct FrameClause {
     pub mode: FrameMode,
     pub start: FrameBound,
@@ -3091,7 +3932,7 @@ impl ToTokens for FrameClause {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum FrameMode {
     Groups,
     Range,
@@ -3111,7 +3952,7 @@ impl ToTokens for FrameMode {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum FrameBound {
     CurrentRow,
     Following(Expr),
@@ -3147,7 +3988,7 @@ impl ToTokens for FrameBound {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum FrameExclude {
     NoOthers,
     CurrentRow,
@@ -3188,17 +4029,31 @@ where
 }
 
 // TK_ID: [...] / `...` / "..." / some keywords / non keywords
+/// Check if `name` is a keyword which cannot be used as an identifier without quotes
+/// (like `SELECT` or `ORDER`, but not `KEY` or `REPLACE`, see `%fallback` in parse.y)
+fn is_reserved_keyword(name: &str) -> bool {
+    use crate::dialect::keyword_token;
+    use crate::parser::parse::yyParser;
+    keyword_token(name.as_bytes()).is_some_and(|ty| {
+        !(ty == TK_JOIN_KW
+            || ty >= TK_WINDOW
+            || yyParser::parse_fallback(ty as YYCODETYPE) == TK_ID as YYCODETYPE)
+    })
+}
+
 fn double_quote<S: TokenStream>(name: &str, s: &mut S) -> Result<(), S::Error> {
     if name.is_empty() {
         return s.append(TK_ID, Some("\"\""));
     }
     if is_identifier(name) {
-        // identifier must be quoted when they match a keyword...
-        /*if is_keyword(name) {
-            f.write_char('`')?;
-            f.write_str(name)?;
-            return f.write_char('`');
-        }*/
+        // identifier must be quoted when they match a reserved keyword
+        if is_reserved_keyword(name) {
+            let mut quoted = String::with_capacity(name.len() + 2);
+            quoted.push('"');
+            quoted.push_str(name);
+            quoted.push('"');
+            return s.append(TK_ID, Some(&quoted));
+        }
         return s.append(TK_ID, Some(name));
     }
     /*f.write_char('"')?;
@@ -0,0 +1,86 @@
+//! Schema qualification of table references
+use super::visit::{
+    walk_expr_mut, walk_select_mut, walk_select_table_mut, walk_stmt_mut, VisitorMut,
+};
+use super::*;
+
+/// Qualify every unqualified base table reference of `cmd` (`FROM` clauses,
+/// joins, `IN table`, `INSERT` / `UPDATE` / `DELETE` targets, subqueries,
+/// trigger tables) with `schema`.
+/// CTE references, table-valued functions and trigger bodies (where qualified
+/// names are not allowed) are left unchanged.
+pub fn qualify_tables(cmd: &mut Cmd, schema: &Name) {
+    let mut v = QualifyTables {
+        schema,
+        ctes: vec![],
+    };
+    v.visit_stmt(cmd.stmt_mut());
+}
+
+struct QualifyTables<'a> {
+    schema: &'a Name,
+    /// (unquoted) names of the CTEs in scope
+    ctes: Vec<String>,
+}
+
+impl QualifyTables<'_> {
+    fn qualify(&self, name: &mut QualifiedName) {
+        if name.db_name.is_some() {
+            return;
+        }
+        let table = unquote(&name.name.0);
+        if !self.ctes.iter().any(|cte| cte.eq_ignore_ascii_case(table)) {
+            name.db_name = Some(self.schema.clone());
+        }
+    }
+
+    // Bring the CTEs of `with` into scope, returning the previous scope length
+    fn enter(&mut self, with: &Option<With>) -> usize {
+        let len = self.ctes.len();
+        for cte in with.iter().flat_map(|with| &with.ctes) {
+            self.ctes.push(unquote(&cte.tbl_name.0).to_owned());
+        }
+        len
+    }
+}
+
+impl VisitorMut for QualifyTables<'_> {
+    fn visit_stmt(&mut self, stmt: &mut Stmt) {
+        let len = match stmt {
+            Stmt::Delete { with, tbl_name, .. }
+            | Stmt::Insert { with, tbl_name, .. }
+            | Stmt::Update { with, tbl_name, .. } => {
+                // a CTE cannot be modified
+                self.qualify(tbl_name);
+                self.enter(with)
+            }
+            Stmt::CreateTrigger { tbl_name, .. } => {
+                self.qualify(tbl_name);
+                return;
+            }
+            _ => self.ctes.len(),
+        };
+        walk_stmt_mut(self, stmt);
+        self.ctes.truncate(len);
+    }
+
+    fn visit_select(&mut self, select: &mut Select) {
+        let len = self.enter(&select.with);
+        walk_select_mut(self, select);
+        self.ctes.truncate(len);
+    }
+
+    fn visit_select_table(&mut self, table: &mut SelectTable) {
+        if let SelectTable::Table(name, ..) = table {
+            self.qualify(name);
+        }
+        walk_select_table_mut(self, table);
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        if let Expr::InTable { rhs, .. } = expr {
+            self.qualify(rhs);
+        }
+        walk_expr_mut(self, expr);
+    }
+}
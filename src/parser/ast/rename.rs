@@ -0,0 +1,256 @@
+//! Table renaming
+use super::visit::{
+    walk_expr_mut, walk_one_select_mut, walk_select_mut, walk_select_table_mut, walk_stmt_mut,
+    VisitorMut,
+};
+use super::*;
+
+impl Cmd {
+    /// Rename every reference to the `from` table (`FROM` clauses, joins,
+    /// qualified columns, `INSERT` / `UPDATE` / `DELETE` targets, subqueries,
+    /// trigger and index tables, foreign keys).
+    /// References in the scope of a CTE named `from` are kept, like columns
+    /// qualified by a table alias named `from`.
+    /// `from` is a table of the `main` schema: references qualified by another
+    /// schema (`aux.from`) and objects created in another schema are kept.
+    pub fn rename_table(&mut self, from: &Name, to: Name) {
+        let mut v = RenameTable {
+            from: unquote(&from.0),
+            to,
+            scopes: vec![],
+        };
+        v.visit_stmt(self.stmt_mut());
+    }
+}
+
+/// Rename the `from` table in a schema, like `ALTER TABLE from RENAME TO to`
+/// does: its `CREATE TABLE` statement and every reference to it in
+/// `statements` (views, triggers, indexes, foreign keys, ...).
+/// Like [`Cmd::rename_table`], `from` is a table of the `main` schema.
+pub fn propagate_rename(statements: &mut [Cmd], from: &Name, to: &Name) {
+    for cmd in statements {
+        if let Stmt::CreateTable { tbl_name, .. } = cmd.stmt_mut() {
+            if main_schema(tbl_name.db_name.as_ref())
+                && unquote(&tbl_name.name.0).eq_ignore_ascii_case(unquote(&from.0))
+            {
+                tbl_name.name = to.clone();
+            }
+        }
+        cmd.rename_table(from, to.clone());
+    }
+}
+
+// Whether a table qualified by `db_name` belongs to the `main` schema
+fn main_schema(db_name: Option<&Name>) -> bool {
+    match db_name {
+        Some(db_name) => unquote(&db_name.0).eq_ignore_ascii_case("main"),
+        None => true,
+    }
+}
+
+struct RenameTable<'a> {
+    from: &'a str,
+    to: Name,
+    // for each enclosing statement / select, whether a column qualified by
+    // `from` refers to the table (`Some(true)`) or to an alias (`Some(false)`),
+    // `None` when `from` is not bound by its `FROM` clause
+    scopes: Vec<Option<bool>>,
+}
+
+impl RenameTable<'_> {
+    fn matches(&self, name: &Name) -> bool {
+        unquote(&name.0).eq_ignore_ascii_case(self.from)
+    }
+
+    fn rename(&self, name: &mut Name) {
+        if self.matches(name) {
+            *name = self.to.clone();
+        }
+    }
+
+    fn rename_qualified(&self, name: &mut QualifiedName) {
+        if main_schema(name.db_name.as_ref()) {
+            self.rename(&mut name.name);
+        }
+    }
+
+    // How `from` is bound by a table of a `FROM` clause
+    fn table_binding(&self, table: &SelectTable) -> Option<bool> {
+        match table {
+            SelectTable::Table(_, Some(As::As(alias) | As::Elided(alias)), _)
+            | SelectTable::TableCall(_, _, Some(As::As(alias) | As::Elided(alias)))
+            | SelectTable::Select(_, Some(As::As(alias) | As::Elided(alias)))
+            | SelectTable::Sub(_, Some(As::As(alias) | As::Elided(alias))) => {
+                self.matches(alias).then_some(false)
+            }
+            SelectTable::Table(name, None, _) | SelectTable::TableCall(name, _, None) => {
+                self.target_binding(name)
+            }
+            SelectTable::Sub(from, None) => self.clause_binding(from),
+            SelectTable::Select(_, None) => None,
+        }
+    }
+
+    fn clause_binding(&self, from: &FromClause) -> Option<bool> {
+        from.select
+            .as_deref()
+            .into_iter()
+            .chain(from.joins.iter().flatten().map(|join| &join.table))
+            .find_map(|table| self.table_binding(table))
+    }
+
+    // How `from` is bound by a table name (and its alias)
+    fn target_binding(&self, name: &QualifiedName) -> Option<bool> {
+        match name.alias {
+            Some(ref alias) => self.matches(alias).then_some(false),
+            None => self
+                .matches(&name.name)
+                .then(|| main_schema(name.db_name.as_ref())),
+        }
+    }
+
+    fn one_select_binding(&self, select: &OneSelect) -> Option<bool> {
+        match select {
+            OneSelect::Select {
+                from: Some(from), ..
+            } => self.clause_binding(from),
+            _ => None,
+        }
+    }
+
+    // Check if a CTE shadows the table
+    fn shadowed(&self, with: &Option<With>) -> bool {
+        with.as_ref().is_some_and(|with| {
+            with.ctes
+                .iter()
+                .any(|cte| unquote(&cte.tbl_name.0).eq_ignore_ascii_case(self.from))
+        })
+    }
+}
+
+impl VisitorMut for RenameTable<'_> {
+    fn visit_stmt(&mut self, stmt: &mut Stmt) {
+        // tables referenced by an object of another schema belong to this schema
+        if let Stmt::CreateIndex { idx_name: name, .. }
+        | Stmt::CreateTable { tbl_name: name, .. }
+        | Stmt::CreateTrigger {
+            trigger_name: name, ..
+        }
+        | Stmt::CreateView {
+            view_name: name, ..
+        } = stmt
+        {
+            if !main_schema(name.db_name.as_ref()) {
+                return;
+            }
+        }
+        let mut binding = None;
+        let shadowed = match stmt {
+            Stmt::Delete { with, tbl_name, .. } | Stmt::Insert { with, tbl_name, .. } => {
+                binding = self.target_binding(tbl_name);
+                // a CTE cannot be modified
+                self.rename_qualified(tbl_name);
+                self.shadowed(with)
+            }
+            Stmt::Update {
+                with,
+                tbl_name,
+                from,
+                ..
+            } => {
+                binding = self
+                    .target_binding(tbl_name)
+                    .or_else(|| from.as_ref().and_then(|from| self.clause_binding(from)));
+                self.rename_qualified(tbl_name);
+                self.shadowed(with)
+            }
+            Stmt::CreateIndex { tbl_name, .. } => {
+                self.rename(tbl_name);
+                false
+            }
+            Stmt::CreateTrigger {
+                tbl_name, commands, ..
+            } => {
+                self.rename_qualified(tbl_name);
+                for cmd in commands {
+                    match cmd {
+                        TriggerCmd::Update { tbl_name, .. }
+                        | TriggerCmd::Insert { tbl_name, .. }
+                        | TriggerCmd::Delete { tbl_name, .. } => self.rename(tbl_name),
+                        TriggerCmd::Select(_) => {}
+                    }
+                }
+                false
+            }
+            Stmt::CreateTable {
+                body:
+                    CreateTableBody::ColumnsAndConstraints {
+                        columns,
+                        constraints,
+                        ..
+                    },
+                ..
+            } => {
+                let column_constraints = columns.iter_mut().flat_map(|c| &mut c.constraints);
+                for constraint in column_constraints {
+                    if let ColumnConstraint::ForeignKey { clause, .. } = &mut constraint.constraint
+                    {
+                        self.rename(&mut clause.tbl_name);
+                    }
+                }
+                for constraint in constraints.iter_mut().flatten() {
+                    if let TableConstraint::ForeignKey { clause, .. } = &mut constraint.constraint {
+                        self.rename(&mut clause.tbl_name);
+                    }
+                }
+                false
+            }
+            _ => false,
+        };
+        if !shadowed {
+            self.scopes.push(binding);
+            walk_stmt_mut(self, stmt);
+            self.scopes.pop();
+        }
+    }
+
+    fn visit_select(&mut self, select: &mut Select) {
+        if !self.shadowed(&select.with) {
+            // `ORDER BY` is in the scope of the first select
+            self.scopes
+                .push(self.one_select_binding(&select.body.select));
+            walk_select_mut(self, select);
+            self.scopes.pop();
+        }
+    }
+
+    fn visit_one_select(&mut self, select: &mut OneSelect) {
+        self.scopes.push(self.one_select_binding(select));
+        walk_one_select_mut(self, select);
+        self.scopes.pop();
+    }
+
+    fn visit_select_table(&mut self, table: &mut SelectTable) {
+        if let SelectTable::Table(name, ..) = table {
+            self.rename_qualified(name);
+        }
+        walk_select_table_mut(self, table);
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        match expr {
+            // the innermost binding wins
+            Expr::Qualified(tbl_name, _)
+                if self.scopes.iter().rev().find_map(|b| *b).unwrap_or(true) =>
+            {
+                self.rename(tbl_name)
+            }
+            Expr::DoublyQualified(db_name, tbl_name, _) if main_schema(Some(db_name)) => {
+                self.rename(tbl_name)
+            }
+            Expr::InTable { rhs, .. } => self.rename_qualified(rhs),
+            _ => {}
+        }
+        walk_expr_mut(self, expr);
+    }
+}
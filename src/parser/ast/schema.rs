@@ -0,0 +1,158 @@
+//! Schema reflection of `CREATE TABLE` statements
+use alloc::vec::Vec;
+
+use super::*;
+
+/// Column type affinity
+/// (see <https://sqlite.org/datatype3.html#determination_of_column_affinity>)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Affinity {
+    Integer,
+    Text,
+    Blob,
+    Real,
+    Numeric,
+}
+
+impl Affinity {
+    /// Affinity of a column declared with `col_type`
+    pub fn of(col_type: Option<&Type>) -> Affinity {
+        let Some(col_type) = col_type else {
+            return Affinity::Blob;
+        };
+        let name = col_type.name.to_ascii_uppercase();
+        if name.contains("INT") {
+            Affinity::Integer
+        } else if name.contains("CHAR") || name.contains("CLOB") || name.contains("TEXT") {
+            Affinity::Text
+        } else if name.contains("BLOB") || name.is_empty() {
+            Affinity::Blob
+        } else if name.contains("REAL") || name.contains("FLOA") || name.contains("DOUB") {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+}
+
+/// Column of a [`TableSchema`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnInfo {
+    pub name: Name,
+    pub type_affinity: Affinity,
+    /// No `NOT NULL` constraint
+    pub nullable: bool,
+    /// Part of the primary key (declared by a column or a table constraint)
+    pub primary_key: bool,
+    /// `DEFAULT` value
+    pub default: Option<Expr>,
+}
+
+/// Foreign key of a [`TableSchema`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForeignKey {
+    /// Child columns
+    pub columns: Vec<Name>,
+    /// Parent table and columns
+    pub clause: ForeignKeyClause,
+}
+
+/// Normalized view of a `CREATE TABLE` statement: column and table
+/// constraints are flattened
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableSchema {
+    pub name: QualifiedName,
+    pub columns: Vec<ColumnInfo>,
+    /// Primary key columns (column names as declared in the column definitions)
+    pub primary_key: Vec<Name>,
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+impl TableSchema {
+    /// Schema of a `CREATE TABLE` statement with column definitions
+    /// (`None` for other statements and `CREATE TABLE ... AS SELECT`)
+    pub fn from_create_table(stmt: &Stmt) -> Option<TableSchema> {
+        let Stmt::CreateTable {
+            tbl_name,
+            body:
+                CreateTableBody::ColumnsAndConstraints {
+                    columns: definitions,
+                    constraints,
+                    ..
+                },
+            ..
+        } = stmt
+        else {
+            return None;
+        };
+        // column definition named `name`
+        let column = |name: &str| {
+            definitions
+                .iter()
+                .find(|c| unquote(&c.col_name.0).eq_ignore_ascii_case(unquote(name)))
+                .map_or_else(|| Name(name.to_owned()), |c| c.col_name.clone())
+        };
+        let mut primary_key = vec![];
+        let mut foreign_keys = vec![];
+        let mut columns = Vec::with_capacity(definitions.len());
+        for definition in definitions {
+            let mut info = ColumnInfo {
+                name: definition.col_name.clone(),
+                type_affinity: Affinity::of(definition.col_type.as_ref()),
+                nullable: true,
+                primary_key: false,
+                default: None,
+            };
+            for constraint in definition.constraints.iter() {
+                match constraint.constraint {
+                    ColumnConstraint::PrimaryKey { .. } => {
+                        primary_key.push(definition.col_name.clone())
+                    }
+                    ColumnConstraint::NotNull {
+                        nullable: false, ..
+                    } => info.nullable = false,
+                    ColumnConstraint::Default(ref expr) => info.default = Some(expr.clone()),
+                    ColumnConstraint::ForeignKey { ref clause, .. } => {
+                        foreign_keys.push(ForeignKey {
+                            columns: vec![definition.col_name.clone()],
+                            clause: clause.clone(),
+                        })
+                    }
+                    _ => {}
+                }
+            }
+            columns.push(info);
+        }
+        for constraint in constraints.iter().flatten() {
+            match constraint.constraint {
+                TableConstraint::PrimaryKey {
+                    columns: ref pk, ..
+                } => {
+                    for sorted in pk {
+                        if let Expr::Id(Id(ref name)) | Expr::Name(Name(ref name)) = sorted.expr {
+                            primary_key.push(column(name));
+                        }
+                    }
+                }
+                TableConstraint::ForeignKey {
+                    columns: ref fk,
+                    ref clause,
+                    ..
+                } => foreign_keys.push(ForeignKey {
+                    columns: fk.iter().map(|c| column(&c.col_name.0)).collect(),
+                    clause: clause.clone(),
+                }),
+                _ => {}
+            }
+        }
+        for info in columns.iter_mut() {
+            info.primary_key = primary_key.contains(&info.name);
+        }
+        Some(TableSchema {
+            name: tbl_name.clone(),
+            columns,
+            primary_key,
+            foreign_keys,
+        })
+    }
+}
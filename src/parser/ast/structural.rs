@@ -0,0 +1,214 @@
+//! Structural equality (modulo aliases, collations, parentheses)
+use alloc::string::ToString;
+
+use super::visit::{
+    walk_expr_mut, walk_select, walk_select_mut, walk_select_table, walk_select_table_mut,
+    walk_stmt, walk_stmt_mut, Visitor, VisitorMut,
+};
+use super::*;
+
+/// Differences which are significant for [`Cmd::structural_eq_with`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompareOptions {
+    /// `x COLLATE nocase` differs from `x` (`true` by default)
+    pub collation: bool,
+    /// `(x)` differs from `x` (`true` by default)
+    pub parentheses: bool,
+    /// Names of table aliases and CTEs (`false` by default)
+    pub aliases: bool,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        CompareOptions {
+            collation: true,
+            parentheses: true,
+            aliases: false,
+        }
+    }
+}
+
+impl Cmd {
+    /// Whether `self` and `other` are identical up to the names of their
+    /// table aliases and CTEs: `SELECT a.x FROM t a` and `SELECT b.x FROM t AS b`
+    /// are structurally equal but `SELECT x FROM t` and `SELECT x FROM u` are not.
+    /// Aliases are matched by order of definition.
+    pub fn structural_eq(&self, other: &Cmd) -> bool {
+        self.structural_eq_with(other, CompareOptions::default())
+    }
+
+    /// Like [`Cmd::structural_eq`] but with the significant differences
+    /// chosen by `options`
+    pub fn structural_eq_with(&self, other: &Cmd, options: CompareOptions) -> bool {
+        canonical(self, options) == canonical(other, options)
+    }
+}
+
+/// Copy of `cmd` with insignificant differences removed: aliases and CTEs
+/// renamed after their definition order, `COLLATE` / parentheses stripped
+fn canonical(cmd: &Cmd, options: CompareOptions) -> Cmd {
+    let mut cmd = cmd.clone();
+    if !options.aliases {
+        let mut aliases = Aliases::default();
+        Visitor::visit_stmt(&mut aliases, cmd.stmt());
+        VisitorMut::visit_stmt(&mut aliases, cmd.stmt_mut());
+    }
+    if !(options.collation && options.parentheses) {
+        Strip(options).visit_stmt(cmd.stmt_mut());
+    }
+    cmd
+}
+
+// Remove insignificant `COLLATE` and parentheses
+struct Strip(CompareOptions);
+
+impl VisitorMut for Strip {
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        loop {
+            let inner = match expr {
+                Expr::Collate(inner, _) if !self.0.collation => inner.as_mut(),
+                Expr::Parenthesized(exprs) if !self.0.parentheses && exprs.len() == 1 => {
+                    &mut exprs[0]
+                }
+                _ => break,
+            };
+            *expr = core::mem::replace(inner, Expr::Literal(Literal::Null));
+        }
+        walk_expr_mut(self, expr);
+    }
+}
+
+#[derive(Default)]
+struct Aliases {
+    /// (unquoted lowercase alias, is a CTE)
+    names: Vec<(String, bool)>,
+}
+
+impl Aliases {
+    fn define(&mut self, name: &Name, cte: bool) {
+        let key = unquote(&name.0).to_ascii_lowercase();
+        if !self.names.iter().any(|(n, _)| *n == key) {
+            self.names.push((key, cte));
+        }
+    }
+
+    fn define_with(&mut self, with: &Option<With>) {
+        for cte in with.iter().flat_map(|with| &with.ctes) {
+            self.define(&cte.tbl_name, true);
+        }
+    }
+
+    // Placeholder of an alias (digits cannot start an unquoted identifier)
+    fn rename(&self, name: &mut Name, table: bool) {
+        let key = unquote(&name.0).to_ascii_lowercase();
+        if let Some(i) = self
+            .names
+            .iter()
+            .position(|(n, cte)| *n == key && (*cte || !table))
+        {
+            *name = Name(i.to_string());
+        }
+    }
+
+    fn rename_with(&self, with: &mut Option<With>) {
+        for cte in with.iter_mut().flat_map(|with| &mut with.ctes) {
+            self.rename(&mut cte.tbl_name, false);
+        }
+    }
+
+    fn rename_as(&self, alias: &mut Option<As>) {
+        if let Some(As::As(name) | As::Elided(name)) = alias {
+            let mut name = name.clone();
+            self.rename(&mut name, false);
+            *alias = Some(As::As(name));
+        }
+    }
+
+    fn rename_table(&self, name: &mut QualifiedName) {
+        if name.db_name.is_none() {
+            self.rename(&mut name.name, true);
+        }
+        if let Some(alias) = &mut name.alias {
+            self.rename(alias, false);
+        }
+    }
+}
+
+// first pass: definitions
+impl<'a> Visitor<'a> for Aliases {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::Delete { with, tbl_name, .. }
+            | Stmt::Insert { with, tbl_name, .. }
+            | Stmt::Update { with, tbl_name, .. } => {
+                self.define_with(with);
+                if let Some(alias) = &tbl_name.alias {
+                    self.define(alias, false);
+                }
+            }
+            _ => {}
+        }
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_select(&mut self, select: &'a Select) {
+        self.define_with(&select.with);
+        walk_select(self, select);
+    }
+
+    fn visit_select_table(&mut self, table: &'a SelectTable) {
+        match table {
+            SelectTable::Table(_, Some(As::As(name) | As::Elided(name)), _)
+            | SelectTable::TableCall(_, _, Some(As::As(name) | As::Elided(name)))
+            | SelectTable::Select(_, Some(As::As(name) | As::Elided(name)))
+            | SelectTable::Sub(_, Some(As::As(name) | As::Elided(name))) => {
+                self.define(name, false)
+            }
+            _ => {}
+        }
+        walk_select_table(self, table);
+    }
+}
+
+// second pass: definitions and references
+impl VisitorMut for Aliases {
+    fn visit_stmt(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::Delete { with, tbl_name, .. }
+            | Stmt::Insert { with, tbl_name, .. }
+            | Stmt::Update { with, tbl_name, .. } => {
+                self.rename_with(with);
+                self.rename_table(tbl_name);
+            }
+            _ => {}
+        }
+        walk_stmt_mut(self, stmt);
+    }
+
+    fn visit_select(&mut self, select: &mut Select) {
+        self.rename_with(&mut select.with);
+        walk_select_mut(self, select);
+    }
+
+    fn visit_select_table(&mut self, table: &mut SelectTable) {
+        match table {
+            SelectTable::Table(name, alias, _) => {
+                self.rename_table(name);
+                self.rename_as(alias);
+            }
+            SelectTable::TableCall(_, _, alias)
+            | SelectTable::Select(_, alias)
+            | SelectTable::Sub(_, alias) => self.rename_as(alias),
+        }
+        walk_select_table_mut(self, table);
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Qualified(tbl_name, _) => self.rename(tbl_name, false),
+            Expr::InTable { rhs, .. } => self.rename_table(rhs),
+            _ => {}
+        }
+        walk_expr_mut(self, expr);
+    }
+}
@@ -0,0 +1,485 @@
+//! Indented tree rendering of the AST (see [`Cmd::debug_tree`])
+use alloc::string::ToString;
+use core::fmt::{Debug, Write};
+
+use super::*;
+
+impl Cmd {
+    /// Render the AST as an indented tree: one node per line with its kind
+    /// and key fields, children indented by two spaces. Statements other than
+    /// `SELECT` / `INSERT` / `UPDATE` / `DELETE`, and clauses without
+    /// expression (types, window definitions, ...), are rendered as SQL.
+    /// ```text
+    /// Select
+    ///   Columns
+    ///     Column
+    ///       Binary Add
+    ///         Id a
+    ///         Literal 1
+    ///   From
+    ///     Table t
+    /// ```
+    /// The format is meant for humans and may change.
+    pub fn debug_tree(&self) -> String {
+        let mut tree = Tree::default();
+        match self {
+            Cmd::Explain(stmt) => tree.node("Explain", |tree| tree.stmt(stmt)),
+            Cmd::ExplainQueryPlan(stmt) => tree.node("ExplainQueryPlan", |tree| tree.stmt(stmt)),
+            Cmd::Stmt(stmt) => tree.stmt(stmt),
+        }
+        tree.out
+    }
+}
+
+#[derive(Default)]
+struct Tree {
+    out: String,
+    depth: usize,
+}
+
+// SQL rendering of `node`
+fn sql<T: ToTokens>(node: &T) -> String {
+    node.display_with(FormatOptions::default()).to_string()
+}
+
+// Name of the variant of `node` (as derived by `Debug`)
+fn kind<T: Debug>(node: &T) -> String {
+    let debug = format!("{:?}", node);
+    let end = debug
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(debug.len());
+    debug[..end].to_owned()
+}
+
+// ` NOT` when `not`
+fn not(not: bool) -> &'static str {
+    if not {
+        " NOT"
+    } else {
+        ""
+    }
+}
+
+// ` AS alias` if any
+fn alias(alias: &Option<As>) -> String {
+    match alias {
+        Some(As::As(name) | As::Elided(name)) => format!(" AS {}", name),
+        None => String::new(),
+    }
+}
+
+impl Tree {
+    fn line<L: Display>(&mut self, label: L) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+        let _ = writeln!(self.out, "{}", label);
+    }
+
+    fn node<L: Display, F: FnOnce(&mut Tree)>(&mut self, label: L, children: F) {
+        self.line(label);
+        self.depth += 1;
+        children(self);
+        self.depth -= 1;
+    }
+
+    fn exprs(&mut self, label: &str, exprs: &[Expr]) {
+        self.node(label, |tree| exprs.iter().for_each(|expr| tree.expr(expr)));
+    }
+
+    fn opt_expr(&mut self, label: &str, expr: &Option<Expr>) {
+        if let Some(expr) = expr {
+            self.node(label, |tree| tree.expr(expr));
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Select(select) => self.select(select),
+            Stmt::Insert {
+                with,
+                or_conflict,
+                tbl_name,
+                columns,
+                body,
+                returning,
+            } => {
+                let mut label = String::from("Insert");
+                if let Some(or_conflict) = or_conflict {
+                    let _ = write!(label, " OR {}", sql(or_conflict));
+                }
+                let _ = write!(label, " {}", sql(tbl_name));
+                if let Some(columns) = columns {
+                    let columns: Vec<String> = columns.iter().map(Name::to_string).collect();
+                    let _ = write!(label, " ({})", columns.join(", "));
+                }
+                self.node(label, |tree| {
+                    tree.with(with);
+                    match body {
+                        InsertBody::Select(select, upsert) => {
+                            tree.select(select);
+                            if let Some(upsert) = upsert {
+                                tree.line(format_args!("Upsert {}", sql(upsert)));
+                            }
+                        }
+                        InsertBody::DefaultValues => tree.line("DefaultValues"),
+                    }
+                    tree.returning(returning);
+                });
+            }
+            Stmt::Update {
+                with,
+                or_conflict,
+                tbl_name,
+                indexed,
+                sets,
+                from,
+                where_clause,
+                returning,
+                order_by,
+                limit,
+            } => {
+                let mut label = String::from("Update");
+                if let Some(or_conflict) = or_conflict {
+                    let _ = write!(label, " OR {}", sql(or_conflict));
+                }
+                let _ = write!(label, " {}", sql(tbl_name));
+                if let Some(indexed) = indexed {
+                    let _ = write!(label, " {}", sql(indexed));
+                }
+                self.node(label, |tree| {
+                    tree.with(with);
+                    for set in sets {
+                        let columns: Vec<String> =
+                            set.col_names.iter().map(Name::to_string).collect();
+                        tree.node(format_args!("Set {}", columns.join(", ")), |tree| {
+                            tree.expr(&set.expr)
+                        });
+                    }
+                    if let Some(from) = from {
+                        tree.node("From", |tree| tree.from(from));
+                    }
+                    tree.opt_expr("Where", where_clause);
+                    tree.returning(returning);
+                    tree.order_by(order_by);
+                    tree.limit(limit);
+                });
+            }
+            Stmt::Delete {
+                with,
+                tbl_name,
+                indexed,
+                where_clause,
+                returning,
+                order_by,
+                limit,
+            } => {
+                let mut label = format!("Delete {}", sql(tbl_name));
+                if let Some(indexed) = indexed {
+                    let _ = write!(label, " {}", sql(indexed));
+                }
+                self.node(label, |tree| {
+                    tree.with(with);
+                    tree.opt_expr("Where", where_clause);
+                    tree.returning(returning);
+                    tree.order_by(order_by);
+                    tree.limit(limit);
+                });
+            }
+            stmt => self.line(format_args!("{} {}", kind(stmt), sql(stmt))),
+        }
+    }
+
+    fn with(&mut self, with: &Option<With>) {
+        let Some(with) = with else {
+            return;
+        };
+        let label = if with.recursive {
+            "WithRecursive"
+        } else {
+            "With"
+        };
+        self.node(label, |tree| {
+            for cte in with.ctes.iter() {
+                let mut label = format!("Cte {}", cte.tbl_name);
+                if let Some(ref columns) = cte.columns {
+                    let columns: Vec<String> = columns.iter().map(sql).collect();
+                    let _ = write!(label, " ({})", columns.join(", "));
+                }
+                tree.node(label, |tree| tree.select(&cte.select));
+            }
+        });
+    }
+
+    fn select(&mut self, select: &Select) {
+        self.node("Select", |tree| {
+            tree.with(&select.with);
+            tree.one_select(&select.body.select);
+            for compound in select.body.compounds.iter().flatten() {
+                tree.node(kind(&compound.operator), |tree| {
+                    tree.one_select(&compound.select)
+                });
+            }
+            tree.order_by(&select.order_by);
+            tree.limit(&select.limit);
+        });
+    }
+
+    fn one_select(&mut self, select: &OneSelect) {
+        match select {
+            OneSelect::Select {
+                distinctness,
+                columns,
+                from,
+                where_clause,
+                group_by,
+                window_clause,
+            } => {
+                if let Some(distinctness) = distinctness {
+                    self.line(kind(distinctness));
+                }
+                self.result_columns("Columns", columns);
+                if let Some(from) = from {
+                    self.node("From", |tree| tree.from(from));
+                }
+                self.opt_expr("Where", where_clause);
+                if let Some(group_by) = group_by {
+                    if !group_by.exprs.is_empty() {
+                        self.exprs("GroupBy", &group_by.exprs);
+                    }
+                    self.opt_expr("Having", &group_by.having);
+                }
+                for window in window_clause.iter().flatten() {
+                    self.line(format_args!("Window {}", sql(window)));
+                }
+            }
+            OneSelect::Values(rows) => self.node("Values", |tree| {
+                rows.iter().for_each(|row| tree.exprs("Row", row))
+            }),
+        }
+    }
+
+    fn returning(&mut self, returning: &Option<Vec<ResultColumn>>) {
+        if let Some(returning) = returning {
+            self.result_columns("Returning", returning);
+        }
+    }
+
+    fn result_columns(&mut self, label: &str, columns: &[ResultColumn]) {
+        self.node(label, |tree| {
+            for column in columns {
+                match column {
+                    ResultColumn::Expr(expr, alias_) => tree
+                        .node(format_args!("Column{}", alias(alias_)), |tree| {
+                            tree.expr(expr)
+                        }),
+                    ResultColumn::Star => tree.line("Star"),
+                    ResultColumn::TableStar(name) => tree.line(format_args!("TableStar {}", name)),
+                }
+            }
+        });
+    }
+
+    fn from(&mut self, from: &FromClause) {
+        if let Some(ref table) = from.select {
+            self.select_table(table);
+        }
+        for join in from.joins.iter().flatten() {
+            let label = match join.operator {
+                JoinOperator::Comma => "Comma".to_owned(),
+                // `LEFT JOIN` => `Join LEFT`
+                ref operator => {
+                    let operator = sql(operator);
+                    let operator = operator.trim_end_matches("JOIN").trim_end();
+                    format!("Join {}", operator).trim_end().to_owned()
+                }
+            };
+            self.node(label, |tree| {
+                tree.select_table(&join.table);
+                match join.constraint {
+                    Some(JoinConstraint::On(ref expr)) => tree.node("On", |tree| tree.expr(expr)),
+                    Some(JoinConstraint::Using(ref names)) => {
+                        let names: Vec<String> = names.iter().map(Name::to_string).collect();
+                        tree.line(format_args!("Using {}", names.join(", ")))
+                    }
+                    None => {}
+                }
+            });
+        }
+    }
+
+    fn select_table(&mut self, table: &SelectTable) {
+        match table {
+            SelectTable::Table(name, alias_, indexed) => {
+                let mut label = format!("Table {}{}", sql(name), alias(alias_));
+                if let Some(indexed) = indexed {
+                    let _ = write!(label, " {}", sql(indexed));
+                }
+                self.line(label)
+            }
+            SelectTable::TableCall(name, args, alias_) => self.exprs(
+                &format!("TableCall {}{}", sql(name), alias(alias_)),
+                args.as_deref().unwrap_or_default(),
+            ),
+            SelectTable::Select(select, alias_) => self
+                .node(format_args!("Subquery{}", alias(alias_)), |tree| {
+                    tree.select(select)
+                }),
+            SelectTable::Sub(from, alias_) => {
+                self.node(format_args!("Sub{}", alias(alias_)), |tree| tree.from(from))
+            }
+        }
+    }
+
+    fn order_by(&mut self, order_by: &Option<Vec<SortedColumn>>) {
+        let Some(order_by) = order_by else {
+            return;
+        };
+        self.node("OrderBy", |tree| {
+            for column in order_by {
+                let mut label = String::from("Sort");
+                if let Some(order) = column.order {
+                    let _ = write!(label, " {}", sql(&order));
+                }
+                if let Some(nulls) = column.nulls {
+                    let _ = write!(label, " {}", sql(&nulls));
+                }
+                tree.node(label, |tree| tree.expr(&column.expr));
+            }
+        });
+    }
+
+    fn limit(&mut self, limit: &Option<Limit>) {
+        if let Some(limit) = limit {
+            self.node("Limit", |tree| tree.expr(&limit.expr));
+            self.opt_expr("Offset", &limit.offset);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Between {
+                lhs,
+                not: n,
+                start,
+                end,
+            } => self.node(format_args!("Between{}", not(*n)), |tree| {
+                tree.expr(lhs);
+                tree.expr(start);
+                tree.expr(end);
+            }),
+            Expr::Binary(lhs, op, rhs) => self.node(format_args!("Binary {:?}", op), |tree| {
+                tree.expr(lhs);
+                tree.expr(rhs);
+            }),
+            Expr::Case {
+                base,
+                when_then_pairs,
+                else_expr,
+            } => self.node("Case", |tree| {
+                if let Some(base) = base {
+                    tree.expr(base);
+                }
+                for (when, then) in when_then_pairs {
+                    tree.node("When", |tree| tree.expr(when));
+                    tree.node("Then", |tree| tree.expr(then));
+                }
+                if let Some(else_expr) = else_expr {
+                    tree.node("Else", |tree| tree.expr(else_expr));
+                }
+            }),
+            Expr::Cast { expr, type_name } => self
+                .node(format_args!("Cast AS {}", sql(type_name)), |tree| {
+                    tree.expr(expr)
+                }),
+            Expr::Collate(expr, collation) => self
+                .node(format_args!("Collate {}", collation), |tree| {
+                    tree.expr(expr)
+                }),
+            Expr::Exists(select) => self.node("Exists", |tree| tree.select(select)),
+            Expr::FunctionCall {
+                name,
+                distinctness,
+                args,
+                filter_over,
+            } => {
+                let mut label = format!("FunctionCall {}", name.0);
+                if let Some(distinctness) = distinctness {
+                    let _ = write!(label, " {}", sql(distinctness));
+                }
+                self.node(label, |tree| {
+                    args.iter().flatten().for_each(|arg| tree.expr(arg));
+                    if let Some(filter_over) = filter_over {
+                        tree.function_tail(filter_over);
+                    }
+                })
+            }
+            Expr::FunctionCallStar { name, filter_over } => {
+                self.node(format_args!("FunctionCallStar {}", name.0), |tree| {
+                    if let Some(filter_over) = filter_over {
+                        tree.function_tail(filter_over);
+                    }
+                })
+            }
+            Expr::InList { lhs, not: n, rhs } => {
+                self.node(format_args!("InList{}", not(*n)), |tree| {
+                    tree.expr(lhs);
+                    tree.exprs("List", rhs.as_deref().unwrap_or_default());
+                })
+            }
+            Expr::InSelect { lhs, not: n, rhs } => {
+                self.node(format_args!("InSelect{}", not(*n)), |tree| {
+                    tree.expr(lhs);
+                    tree.select(rhs);
+                })
+            }
+            Expr::InTable {
+                lhs,
+                not: n,
+                rhs,
+                args,
+            } => self.node(format_args!("InTable{} {}", not(*n), sql(rhs)), |tree| {
+                tree.expr(lhs);
+                if let Some(args) = args {
+                    tree.exprs("Args", args);
+                }
+            }),
+            Expr::IsNull(expr) => self.node("IsNull", |tree| tree.expr(expr)),
+            Expr::Like {
+                lhs,
+                not: n,
+                op,
+                rhs,
+                escape,
+            } => self.node(format_args!("Like{} {}", not(*n), sql(op)), |tree| {
+                tree.expr(lhs);
+                tree.expr(rhs);
+                if let Some(escape) = escape {
+                    tree.node("Escape", |tree| tree.expr(escape));
+                }
+            }),
+            Expr::NotNull(expr) => self.node("NotNull", |tree| tree.expr(expr)),
+            Expr::Parenthesized(exprs) => self.exprs("Parenthesized", exprs),
+            Expr::Subquery(select) => self.node("Subquery", |tree| tree.select(select)),
+            Expr::Unary(op, expr) => {
+                self.node(format_args!("Unary {:?}", op), |tree| tree.expr(expr))
+            }
+            Expr::DoublyQualified(..)
+            | Expr::Id(_)
+            | Expr::Literal(_)
+            | Expr::Name(_)
+            | Expr::Qualified(..)
+            | Expr::Raise(..)
+            | Expr::Variable(_) => self.line(format_args!("{} {}", kind(expr), sql(expr))),
+        }
+    }
+
+    fn function_tail(&mut self, tail: &FunctionTail) {
+        if let Some(ref filter) = tail.filter_clause {
+            self.node("Filter", |tree| tree.expr(filter));
+        }
+        if let Some(ref over) = tail.over_clause {
+            self.line(format_args!("Over {}", sql(over.as_ref())));
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Semantic checks of [`Stmt`]s which do not fit a single node
+use alloc::format;
+
+use super::visit::{walk_one_select, walk_select, walk_select_table, walk_stmt, Visitor};
+use super::*;
+
+/// Construct reported by a semantic check (like [`Cmd::validate`]), a lint
+/// (like [`lint_cartesian`]) or the parser (accepted but rejected by SQLite)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    /// Name or alias of the table concerned
+    pub table: Option<Name>,
+    /// (line, column) where the construct has been detected by the parser
+    pub position: Option<(u64, usize)>,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            message: message.into(),
+            table: None,
+            position: None,
+        }
+    }
+
+    pub(crate) fn on_table(mut self, table: &Name) -> Diagnostic {
+        self.table = Some(table.clone());
+        self
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(position) = self.position {
+            write!(f, " at {:?}", position)?;
+        }
+        Ok(())
+    }
+}
+
+/// Rows of each `VALUES` clause of `stmt` whose number of terms differs from
+/// the first row ("all VALUES must have the same number of terms")
+pub(super) fn values_arity(stmt: &Stmt) -> Vec<Diagnostic> {
+    let mut arity = ValuesArity::default();
+    arity.visit_stmt(stmt);
+    arity.errors
+}
+
+/// `VALUES` rows of an `INSERT` whose number of terms differs from the number
+/// of columns listed ("N values for M columns")
+pub(super) fn insert_arity(stmt: &Stmt) -> Vec<Diagnostic> {
+    let Stmt::Insert {
+        tbl_name,
+        columns: Some(columns),
+        body: InsertBody::Select(select, _),
+        ..
+    } = stmt
+    else {
+        return vec![];
+    };
+    let OneSelect::Values(ref rows) = select.body.select else {
+        return vec![];
+    };
+    rows.iter()
+        .enumerate()
+        .filter(|(_, row)| row.len() != columns.len())
+        .map(|(i, row)| {
+            Diagnostic::new(format!(
+                "{} values for {} columns (row {})",
+                row.len(),
+                columns.len(),
+                i + 1
+            ))
+            .on_table(&tbl_name.name)
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct ValuesArity {
+    errors: Vec<Diagnostic>,
+}
+
+impl<'a> Visitor<'a> for ValuesArity {
+    fn visit_one_select(&mut self, select: &'a OneSelect) {
+        if let OneSelect::Values(rows) = select {
+            if let Some((first, rows)) = rows.split_first() {
+                for (i, row) in rows.iter().enumerate() {
+                    if row.len() != first.len() {
+                        self.errors.push(Diagnostic::new(format!(
+                            "all VALUES must have the same number of terms: row {} has {}, expected {}",
+                            i + 2,
+                            row.len(),
+                            first.len()
+                        )));
+                    }
+                }
+            }
+        }
+        walk_one_select(self, select);
+    }
+}
+
+/// Illegal references between the CTEs of each `WITH` clause of `stmt`
+/// (see [`With::validate`])
+pub(super) fn cte_references(stmt: &Stmt) -> Vec<Diagnostic> {
+    let mut clauses = WithClauses::default();
+    clauses.visit_stmt(stmt);
+    clauses.errors
+}
+
+#[derive(Default)]
+struct WithClauses {
+    errors: Vec<Diagnostic>,
+}
+
+impl<'a> Visitor<'a> for WithClauses {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        if let Stmt::Delete {
+            with: Some(with), ..
+        }
+        | Stmt::Insert {
+            with: Some(with), ..
+        }
+        | Stmt::Update {
+            with: Some(with), ..
+        } = stmt
+        {
+            self.errors.extend(with.validate());
+        }
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_select(&mut self, select: &'a Select) {
+        if let Some(ref with) = select.with {
+            self.errors.extend(with.validate());
+        }
+        walk_select(self, select);
+    }
+}
+
+/// CTEs of `with` which reference themselves outside of the recursive
+/// part of a recursive CTE, or through other CTEs ("circular reference")
+pub(super) fn with_cycles(with: &With) -> Vec<Diagnostic> {
+    let names: Vec<&str> = with
+        .ctes
+        .iter()
+        .map(|cte| unquote(&cte.tbl_name.0))
+        .collect();
+    let index = |name: &Name| {
+        let name = unquote(&name.0);
+        names.iter().position(|n| n.eq_ignore_ascii_case(name))
+    };
+    // CTEs referenced by each CTE (itself excluded)
+    let mut dependencies = vec![vec![]; names.len()];
+    let mut circular = vec![false; names.len()];
+    for (i, cte) in with.ctes.iter().enumerate() {
+        let mut tables = TableReferences::default();
+        tables.visit_select(&cte.select);
+        let mut self_references = 0;
+        for name in tables.names {
+            match index(name) {
+                Some(j) if j == i => self_references += 1,
+                Some(j) => dependencies[i].push(j),
+                None => {}
+            }
+        }
+        circular[i] = self_references > recursive_references(&cte.select, names[i]);
+    }
+    for i in 0..names.len() {
+        // depth-first search of a path back to `i`
+        let mut visited = vec![false; names.len()];
+        let mut stack = dependencies[i].clone();
+        while let Some(j) = stack.pop() {
+            if j == i {
+                circular[i] = true;
+                break;
+            }
+            if !core::mem::replace(&mut visited[j], true) {
+                stack.extend(dependencies[j].iter().copied());
+            }
+        }
+    }
+    with.ctes
+        .iter()
+        .zip(circular)
+        .filter(|(_, circular)| *circular)
+        .map(|(cte, _)| {
+            Diagnostic::new(format!("circular reference: {}", cte.tbl_name)).on_table(&cte.tbl_name)
+        })
+        .collect()
+}
+
+// References to `name` allowed in a recursive CTE: directly in the `FROM`
+// clause of the recursive selects (those following the first one) of a
+// `UNION` / `UNION ALL` compound
+fn recursive_references(select: &Select, name: &str) -> usize {
+    let Some(ref compounds) = select.body.compounds else {
+        return 0;
+    };
+    if compounds.iter().any(|compound| {
+        !matches!(
+            compound.operator,
+            CompoundOperator::Union | CompoundOperator::UnionAll
+        )
+    }) {
+        return 0;
+    }
+    compounds
+        .iter()
+        .filter_map(|compound| match compound.select {
+            OneSelect::Select {
+                from: Some(ref from),
+                ..
+            } => Some(from),
+            _ => None,
+        })
+        .flat_map(|from| {
+            from.select
+                .as_deref()
+                .into_iter()
+                .chain(from.joins.iter().flatten().map(|join| &join.table))
+        })
+        .filter(|table| match table {
+            SelectTable::Table(qualified_name, ..) => {
+                qualified_name.db_name.is_none()
+                    && unquote(&qualified_name.name.0).eq_ignore_ascii_case(name)
+            }
+            _ => false,
+        })
+        .count()
+}
+
+// Unqualified table references
+#[derive(Default)]
+struct TableReferences<'a> {
+    names: Vec<&'a Name>,
+}
+
+impl<'a> Visitor<'a> for TableReferences<'a> {
+    fn visit_select_table(&mut self, table: &'a SelectTable) {
+        if let SelectTable::Table(qualified_name, ..) = table {
+            if qualified_name.db_name.is_none() {
+                self.names.push(&qualified_name.name);
+            }
+        }
+        walk_select_table(self, table);
+    }
+}
@@ -0,0 +1,925 @@
+//! AST traversal
+//!
+//! Each `visit_*` method of [`Visitor`] (resp. [`VisitorMut`]) defaults to the matching
+//! `walk_*` (resp. `walk_*_mut`) function, which visits the children of the node.
+//! Override a method to act on a node and call the `walk_*` function to keep
+//! traversing its children.
+use super::*;
+
+/// Read-only AST visitor
+///
+/// `'a` is the lifetime of the visited tree, so references to its nodes can be kept.
+pub trait Visitor<'a> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        walk_stmt(self, stmt)
+    }
+    fn visit_select(&mut self, select: &'a Select) {
+        walk_select(self, select)
+    }
+    fn visit_one_select(&mut self, select: &'a OneSelect) {
+        walk_one_select(self, select)
+    }
+    fn visit_from_clause(&mut self, from: &'a FromClause) {
+        walk_from_clause(self, from)
+    }
+    fn visit_select_table(&mut self, table: &'a SelectTable) {
+        walk_select_table(self, table)
+    }
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        walk_expr(self, expr)
+    }
+    fn visit_window(&mut self, window: &'a Window) {
+        walk_window(self, window)
+    }
+}
+
+fn visit_exprs<'a, V: Visitor<'a> + ?Sized>(v: &mut V, exprs: &'a [Expr]) {
+    for expr in exprs {
+        v.visit_expr(expr);
+    }
+}
+
+fn visit_sorted_columns<'a, V: Visitor<'a> + ?Sized>(v: &mut V, columns: &'a [SortedColumn]) {
+    for column in columns {
+        v.visit_expr(&column.expr);
+    }
+}
+
+fn visit_result_columns<'a, V: Visitor<'a> + ?Sized>(v: &mut V, columns: &'a [ResultColumn]) {
+    for column in columns {
+        if let ResultColumn::Expr(expr, _) = column {
+            v.visit_expr(expr);
+        }
+    }
+}
+
+fn visit_sets<'a, V: Visitor<'a> + ?Sized>(v: &mut V, sets: &'a [Set]) {
+    for set in sets {
+        v.visit_expr(&set.expr);
+    }
+}
+
+fn visit_with<'a, V: Visitor<'a> + ?Sized>(v: &mut V, with: &'a Option<With>) {
+    if let Some(with) = with {
+        for cte in &with.ctes {
+            v.visit_select(&cte.select);
+        }
+    }
+}
+
+fn visit_limit<'a, V: Visitor<'a> + ?Sized>(v: &mut V, limit: &'a Option<Limit>) {
+    if let Some(limit) = limit {
+        v.visit_expr(&limit.expr);
+        if let Some(ref offset) = limit.offset {
+            v.visit_expr(offset);
+        }
+    }
+}
+
+fn visit_upsert<'a, V: Visitor<'a> + ?Sized>(v: &mut V, upsert: &'a Option<Upsert>) {
+    let mut upsert = upsert.as_ref();
+    while let Some(u) = upsert {
+        if let Some(ref index) = u.index {
+            visit_sorted_columns(v, &index.targets);
+            if let Some(ref where_clause) = index.where_clause {
+                v.visit_expr(where_clause);
+            }
+        }
+        if let UpsertDo::Set {
+            ref sets,
+            ref where_clause,
+        } = u.do_clause
+        {
+            visit_sets(v, sets);
+            if let Some(where_clause) = where_clause {
+                v.visit_expr(where_clause);
+            }
+        }
+        upsert = u.next.as_deref();
+    }
+}
+
+fn visit_column_definition<'a, V: Visitor<'a> + ?Sized>(v: &mut V, column: &'a ColumnDefinition) {
+    for constraint in &column.constraints {
+        match constraint.constraint {
+            ColumnConstraint::Check(ref expr)
+            | ColumnConstraint::Default(ref expr)
+            | ColumnConstraint::Generated { ref expr, .. } => v.visit_expr(expr),
+            _ => {}
+        }
+    }
+}
+
+fn visit_trigger_cmd<'a, V: Visitor<'a> + ?Sized>(v: &mut V, cmd: &'a TriggerCmd) {
+    match cmd {
+        TriggerCmd::Update {
+            sets,
+            from,
+            where_clause,
+            ..
+        } => {
+            visit_sets(v, sets);
+            if let Some(from) = from {
+                v.visit_from_clause(from);
+            }
+            if let Some(where_clause) = where_clause {
+                v.visit_expr(where_clause);
+            }
+        }
+        TriggerCmd::Insert {
+            select,
+            upsert,
+            returning,
+            ..
+        } => {
+            v.visit_select(select);
+            visit_upsert(v, upsert);
+            if let Some(returning) = returning {
+                visit_result_columns(v, returning);
+            }
+        }
+        TriggerCmd::Delete { where_clause, .. } => {
+            if let Some(where_clause) = where_clause {
+                v.visit_expr(where_clause);
+            }
+        }
+        TriggerCmd::Select(select) => v.visit_select(select),
+    }
+}
+
+/// Visit the expressions and queries of `stmt`
+pub fn walk_stmt<'a, V: Visitor<'a> + ?Sized>(v: &mut V, stmt: &'a Stmt) {
+    match stmt {
+        Stmt::AlterTable(_, AlterTableBody::AddColumn(column)) => {
+            visit_column_definition(v, column)
+        }
+        Stmt::Attach { expr, db_name, key } => {
+            v.visit_expr(expr);
+            v.visit_expr(db_name);
+            if let Some(key) = key {
+                v.visit_expr(key);
+            }
+        }
+        Stmt::CreateIndex {
+            columns,
+            where_clause,
+            ..
+        } => {
+            visit_sorted_columns(v, columns);
+            if let Some(where_clause) = where_clause {
+                v.visit_expr(where_clause);
+            }
+        }
+        Stmt::CreateTable { body, .. } => match body {
+            CreateTableBody::ColumnsAndConstraints {
+                columns,
+                constraints,
+                ..
+            } => {
+                for column in columns {
+                    visit_column_definition(v, column);
+                }
+                for constraint in constraints.iter().flatten() {
+                    match constraint.constraint {
+                        TableConstraint::PrimaryKey { ref columns, .. }
+                        | TableConstraint::Unique { ref columns, .. } => {
+                            visit_sorted_columns(v, columns)
+                        }
+                        TableConstraint::Check(ref expr) => v.visit_expr(expr),
+                        TableConstraint::ForeignKey { .. } => {}
+                    }
+                }
+            }
+            CreateTableBody::AsSelect(select) => v.visit_select(select),
+        },
+        Stmt::CreateTrigger {
+            when_clause,
+            commands,
+            ..
+        } => {
+            if let Some(when_clause) = when_clause {
+                v.visit_expr(when_clause);
+            }
+            for cmd in commands {
+                visit_trigger_cmd(v, cmd);
+            }
+        }
+        Stmt::CreateView { select, .. } => v.visit_select(select),
+        Stmt::Delete {
+            with,
+            where_clause,
+            returning,
+            order_by,
+            limit,
+            ..
+        } => {
+            visit_with(v, with);
+            if let Some(where_clause) = where_clause {
+                v.visit_expr(where_clause);
+            }
+            if let Some(returning) = returning {
+                visit_result_columns(v, returning);
+            }
+            if let Some(order_by) = order_by {
+                visit_sorted_columns(v, order_by);
+            }
+            visit_limit(v, limit);
+        }
+        Stmt::Detach(expr) => v.visit_expr(expr),
+        Stmt::Insert {
+            with,
+            body,
+            returning,
+            ..
+        } => {
+            visit_with(v, with);
+            if let InsertBody::Select(select, upsert) = body {
+                v.visit_select(select);
+                visit_upsert(v, upsert);
+            }
+            if let Some(returning) = returning {
+                visit_result_columns(v, returning);
+            }
+        }
+        Stmt::Pragma(_, Some(PragmaBody::Equals(value) | PragmaBody::Call(value))) => {
+            v.visit_expr(value)
+        }
+        Stmt::Select(select) => v.visit_select(select),
+        Stmt::Update {
+            with,
+            sets,
+            from,
+            where_clause,
+            returning,
+            order_by,
+            limit,
+            ..
+        } => {
+            visit_with(v, with);
+            visit_sets(v, sets);
+            if let Some(from) = from {
+                v.visit_from_clause(from);
+            }
+            if let Some(where_clause) = where_clause {
+                v.visit_expr(where_clause);
+            }
+            if let Some(returning) = returning {
+                visit_result_columns(v, returning);
+            }
+            if let Some(order_by) = order_by {
+                visit_sorted_columns(v, order_by);
+            }
+            visit_limit(v, limit);
+        }
+        Stmt::Vacuum(_, Some(expr)) => v.visit_expr(expr),
+        _ => {}
+    }
+}
+
+/// Visit the CTEs, the simple selects, the `ORDER BY` and `LIMIT` clauses of `select`
+pub fn walk_select<'a, V: Visitor<'a> + ?Sized>(v: &mut V, select: &'a Select) {
+    visit_with(v, &select.with);
+    v.visit_one_select(&select.body.select);
+    for compound in select.body.compounds.iter().flatten() {
+        v.visit_one_select(&compound.select);
+    }
+    if let Some(ref order_by) = select.order_by {
+        visit_sorted_columns(v, order_by);
+    }
+    visit_limit(v, &select.limit);
+}
+
+/// Visit the clauses of `select`
+pub fn walk_one_select<'a, V: Visitor<'a> + ?Sized>(v: &mut V, select: &'a OneSelect) {
+    match select {
+        OneSelect::Select {
+            columns,
+            from,
+            where_clause,
+            group_by,
+            window_clause,
+            ..
+        } => {
+            visit_result_columns(v, columns);
+            if let Some(from) = from {
+                v.visit_from_clause(from);
+            }
+            if let Some(where_clause) = where_clause {
+                v.visit_expr(where_clause);
+            }
+            if let Some(group_by) = group_by {
+                visit_exprs(v, &group_by.exprs);
+                if let Some(ref having) = group_by.having {
+                    v.visit_expr(having);
+                }
+            }
+            for def in window_clause.iter().flatten() {
+                v.visit_window(&def.window);
+            }
+        }
+        OneSelect::Values(values) => {
+            for row in values {
+                visit_exprs(v, row);
+            }
+        }
+    }
+}
+
+/// Visit the tables and join constraints of `from`
+pub fn walk_from_clause<'a, V: Visitor<'a> + ?Sized>(v: &mut V, from: &'a FromClause) {
+    if let Some(ref table) = from.select {
+        v.visit_select_table(table);
+    }
+    for join in from.joins.iter().flatten() {
+        v.visit_select_table(&join.table);
+        if let Some(JoinConstraint::On(ref expr)) = join.constraint {
+            v.visit_expr(expr);
+        }
+    }
+}
+
+/// Visit the arguments of a table-valued function or the subquery of `table`
+pub fn walk_select_table<'a, V: Visitor<'a> + ?Sized>(v: &mut V, table: &'a SelectTable) {
+    match table {
+        SelectTable::Table(..) => {}
+        SelectTable::TableCall(_, args, _) => {
+            if let Some(args) = args {
+                visit_exprs(v, args);
+            }
+        }
+        SelectTable::Select(select, _) => v.visit_select(select),
+        SelectTable::Sub(from, _) => v.visit_from_clause(from),
+    }
+}
+
+/// Visit the subexpressions and subqueries of `expr`
+pub fn walk_expr<'a, V: Visitor<'a> + ?Sized>(v: &mut V, expr: &'a Expr) {
+    match expr {
+        Expr::Between {
+            lhs, start, end, ..
+        } => {
+            v.visit_expr(lhs);
+            v.visit_expr(start);
+            v.visit_expr(end);
+        }
+        Expr::Binary(lhs, _, rhs) => {
+            v.visit_expr(lhs);
+            v.visit_expr(rhs);
+        }
+        Expr::Case {
+            base,
+            when_then_pairs,
+            else_expr,
+        } => {
+            if let Some(base) = base {
+                v.visit_expr(base);
+            }
+            for (when, then) in when_then_pairs {
+                v.visit_expr(when);
+                v.visit_expr(then);
+            }
+            if let Some(else_expr) = else_expr {
+                v.visit_expr(else_expr);
+            }
+        }
+        Expr::Cast { expr, .. } => v.visit_expr(expr),
+        Expr::Collate(expr, _) => v.visit_expr(expr),
+        Expr::Exists(select) => v.visit_select(select),
+        Expr::FunctionCall {
+            args, filter_over, ..
+        } => {
+            if let Some(args) = args {
+                visit_exprs(v, args);
+            }
+            walk_function_tail(v, filter_over);
+        }
+        Expr::FunctionCallStar { filter_over, .. } => walk_function_tail(v, filter_over),
+        Expr::InList { lhs, rhs, .. } => {
+            v.visit_expr(lhs);
+            if let Some(rhs) = rhs {
+                visit_exprs(v, rhs);
+            }
+        }
+        Expr::InSelect { lhs, rhs, .. } => {
+            v.visit_expr(lhs);
+            v.visit_select(rhs);
+        }
+        Expr::InTable { lhs, args, .. } => {
+            v.visit_expr(lhs);
+            if let Some(args) = args {
+                visit_exprs(v, args);
+            }
+        }
+        Expr::IsNull(expr) | Expr::NotNull(expr) => v.visit_expr(expr),
+        Expr::Like {
+            lhs, rhs, escape, ..
+        } => {
+            v.visit_expr(lhs);
+            v.visit_expr(rhs);
+            if let Some(escape) = escape {
+                v.visit_expr(escape);
+            }
+        }
+        Expr::Parenthesized(exprs) => visit_exprs(v, exprs),
+        Expr::Raise(_, Some(err)) => v.visit_expr(err),
+        Expr::Subquery(select) => v.visit_select(select),
+        Expr::Unary(_, expr) => v.visit_expr(expr),
+        Expr::DoublyQualified(..)
+        | Expr::Id(_)
+        | Expr::Literal(_)
+        | Expr::Name(_)
+        | Expr::Qualified(..)
+        | Expr::Raise(_, None)
+        | Expr::Variable(_) => {}
+    }
+}
+
+fn walk_function_tail<'a, V: Visitor<'a> + ?Sized>(
+    v: &mut V,
+    filter_over: &'a Option<FunctionTail>,
+) {
+    if let Some(tail) = filter_over {
+        if let Some(ref filter_clause) = tail.filter_clause {
+            v.visit_expr(filter_clause);
+        }
+        if let Some(Over::Window(ref window)) = tail.over_clause.as_deref() {
+            v.visit_window(window);
+        }
+    }
+}
+
+/// Visit the `PARTITION BY`, `ORDER BY` and frame bound expressions of `window`
+pub fn walk_window<'a, V: Visitor<'a> + ?Sized>(v: &mut V, window: &'a Window) {
+    if let Some(ref partition_by) = window.partition_by {
+        visit_exprs(v, partition_by);
+    }
+    if let Some(ref order_by) = window.order_by {
+        visit_sorted_columns(v, order_by);
+    }
+    if let Some(ref frame_clause) = window.frame_clause {
+        for bound in core::iter::once(&frame_clause.start).chain(frame_clause.end.as_ref()) {
+            if let FrameBound::Following(expr) | FrameBound::Preceding(expr) = bound {
+                v.visit_expr(expr);
+            }
+        }
+    }
+}
+
+/// Mutable AST visitor
+pub trait VisitorMut {
+    fn visit_stmt(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt)
+    }
+    fn visit_select(&mut self, select: &mut Select) {
+        walk_select_mut(self, select)
+    }
+    fn visit_one_select(&mut self, select: &mut OneSelect) {
+        walk_one_select_mut(self, select)
+    }
+    fn visit_from_clause(&mut self, from: &mut FromClause) {
+        walk_from_clause_mut(self, from)
+    }
+    fn visit_select_table(&mut self, table: &mut SelectTable) {
+        walk_select_table_mut(self, table)
+    }
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+    fn visit_window(&mut self, window: &mut Window) {
+        walk_window_mut(self, window)
+    }
+}
+
+fn visit_exprs_mut<V: VisitorMut + ?Sized>(v: &mut V, exprs: &mut [Expr]) {
+    for expr in exprs {
+        v.visit_expr(expr);
+    }
+}
+
+fn visit_sorted_columns_mut<V: VisitorMut + ?Sized>(v: &mut V, columns: &mut [SortedColumn]) {
+    for column in columns {
+        v.visit_expr(&mut column.expr);
+    }
+}
+
+fn visit_result_columns_mut<V: VisitorMut + ?Sized>(v: &mut V, columns: &mut [ResultColumn]) {
+    for column in columns {
+        if let ResultColumn::Expr(expr, _) = column {
+            v.visit_expr(expr);
+        }
+    }
+}
+
+fn visit_sets_mut<V: VisitorMut + ?Sized>(v: &mut V, sets: &mut [Set]) {
+    for set in sets {
+        v.visit_expr(&mut set.expr);
+    }
+}
+
+fn visit_with_mut<V: VisitorMut + ?Sized>(v: &mut V, with: &mut Option<With>) {
+    if let Some(with) = with {
+        for cte in &mut with.ctes {
+            v.visit_select(&mut cte.select);
+        }
+    }
+}
+
+fn visit_limit_mut<V: VisitorMut + ?Sized>(v: &mut V, limit: &mut Option<Limit>) {
+    if let Some(limit) = limit {
+        v.visit_expr(&mut limit.expr);
+        if let Some(ref mut offset) = limit.offset {
+            v.visit_expr(offset);
+        }
+    }
+}
+
+fn visit_upsert_mut<V: VisitorMut + ?Sized>(v: &mut V, upsert: &mut Option<Upsert>) {
+    let mut upsert = upsert.as_mut();
+    while let Some(u) = upsert {
+        if let Some(ref mut index) = u.index {
+            visit_sorted_columns_mut(v, &mut index.targets);
+            if let Some(ref mut where_clause) = index.where_clause {
+                v.visit_expr(where_clause);
+            }
+        }
+        if let UpsertDo::Set {
+            ref mut sets,
+            ref mut where_clause,
+        } = u.do_clause
+        {
+            visit_sets_mut(v, sets);
+            if let Some(where_clause) = where_clause {
+                v.visit_expr(where_clause);
+            }
+        }
+        upsert = u.next.as_deref_mut();
+    }
+}
+
+fn visit_column_definition_mut<V: VisitorMut + ?Sized>(v: &mut V, column: &mut ColumnDefinition) {
+    for constraint in &mut column.constraints {
+        match constraint.constraint {
+            ColumnConstraint::Check(ref mut expr)
+            | ColumnConstraint::Default(ref mut expr)
+            | ColumnConstraint::Generated { ref mut expr, .. } => v.visit_expr(expr),
+            _ => {}
+        }
+    }
+}
+
+fn visit_trigger_cmd_mut<V: VisitorMut + ?Sized>(v: &mut V, cmd: &mut TriggerCmd) {
+    match cmd {
+        TriggerCmd::Update {
+            sets,
+            from,
+            where_clause,
+            ..
+        } => {
+            visit_sets_mut(v, sets);
+            if let Some(from) = from {
+                v.visit_from_clause(from);
+            }
+            if let Some(where_clause) = where_clause {
+                v.visit_expr(where_clause);
+            }
+        }
+        TriggerCmd::Insert {
+            select,
+            upsert,
+            returning,
+            ..
+        } => {
+            v.visit_select(select);
+            visit_upsert_mut(v, upsert);
+            if let Some(returning) = returning {
+                visit_result_columns_mut(v, returning);
+            }
+        }
+        TriggerCmd::Delete { where_clause, .. } => {
+            if let Some(where_clause) = where_clause {
+                v.visit_expr(where_clause);
+            }
+        }
+        TriggerCmd::Select(select) => v.visit_select(select),
+    }
+}
+
+/// Visit the expressions and queries of `stmt`
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Stmt) {
+    match stmt {
+        Stmt::AlterTable(_, AlterTableBody::AddColumn(column)) => {
+            visit_column_definition_mut(v, column)
+        }
+        Stmt::Attach { expr, db_name, key } => {
+            v.visit_expr(expr);
+            v.visit_expr(db_name);
+            if let Some(key) = key {
+                v.visit_expr(key);
+            }
+        }
+        Stmt::CreateIndex {
+            columns,
+            where_clause,
+            ..
+        } => {
+            visit_sorted_columns_mut(v, columns);
+            if let Some(where_clause) = where_clause {
+                v.visit_expr(where_clause);
+            }
+        }
+        Stmt::CreateTable { body, .. } => match body {
+            CreateTableBody::ColumnsAndConstraints {
+                columns,
+                constraints,
+                ..
+            } => {
+                for column in columns {
+                    visit_column_definition_mut(v, column);
+                }
+                for constraint in constraints.iter_mut().flatten() {
+                    match constraint.constraint {
+                        TableConstraint::PrimaryKey {
+                            ref mut columns, ..
+                        }
+                        | TableConstraint::Unique {
+                            ref mut columns, ..
+                        } => visit_sorted_columns_mut(v, columns),
+                        TableConstraint::Check(ref mut expr) => v.visit_expr(expr),
+                        TableConstraint::ForeignKey { .. } => {}
+                    }
+                }
+            }
+            CreateTableBody::AsSelect(select) => v.visit_select(select),
+        },
+        Stmt::CreateTrigger {
+            when_clause,
+            commands,
+            ..
+        } => {
+            if let Some(when_clause) = when_clause {
+                v.visit_expr(when_clause);
+            }
+            for cmd in commands {
+                visit_trigger_cmd_mut(v, cmd);
+            }
+        }
+        Stmt::CreateView { select, .. } => v.visit_select(select),
+        Stmt::Delete {
+            with,
+            where_clause,
+            returning,
+            order_by,
+            limit,
+            ..
+        } => {
+            visit_with_mut(v, with);
+            if let Some(where_clause) = where_clause {
+                v.visit_expr(where_clause);
+            }
+            if let Some(returning) = returning {
+                visit_result_columns_mut(v, returning);
+            }
+            if let Some(order_by) = order_by {
+                visit_sorted_columns_mut(v, order_by);
+            }
+            visit_limit_mut(v, limit);
+        }
+        Stmt::Detach(expr) => v.visit_expr(expr),
+        Stmt::Insert {
+            with,
+            body,
+            returning,
+            ..
+        } => {
+            visit_with_mut(v, with);
+            if let InsertBody::Select(select, upsert) = body {
+                v.visit_select(select);
+                visit_upsert_mut(v, upsert);
+            }
+            if let Some(returning) = returning {
+                visit_result_columns_mut(v, returning);
+            }
+        }
+        Stmt::Pragma(_, Some(PragmaBody::Equals(value) | PragmaBody::Call(value))) => {
+            v.visit_expr(value)
+        }
+        Stmt::Select(select) => v.visit_select(select),
+        Stmt::Update {
+            with,
+            sets,
+            from,
+            where_clause,
+            returning,
+            order_by,
+            limit,
+            ..
+        } => {
+            visit_with_mut(v, with);
+            visit_sets_mut(v, sets);
+            if let Some(from) = from {
+                v.visit_from_clause(from);
+            }
+            if let Some(where_clause) = where_clause {
+                v.visit_expr(where_clause);
+            }
+            if let Some(returning) = returning {
+                visit_result_columns_mut(v, returning);
+            }
+            if let Some(order_by) = order_by {
+                visit_sorted_columns_mut(v, order_by);
+            }
+            visit_limit_mut(v, limit);
+        }
+        Stmt::Vacuum(_, Some(expr)) => v.visit_expr(expr),
+        _ => {}
+    }
+}
+
+/// Visit the CTEs, the simple selects, the `ORDER BY` and `LIMIT` clauses of `select`
+pub fn walk_select_mut<V: VisitorMut + ?Sized>(v: &mut V, select: &mut Select) {
+    visit_with_mut(v, &mut select.with);
+    v.visit_one_select(&mut select.body.select);
+    for compound in select.body.compounds.iter_mut().flatten() {
+        v.visit_one_select(&mut compound.select);
+    }
+    if let Some(ref mut order_by) = select.order_by {
+        visit_sorted_columns_mut(v, order_by);
+    }
+    visit_limit_mut(v, &mut select.limit);
+}
+
+/// Visit the clauses of `select`
+pub fn walk_one_select_mut<V: VisitorMut + ?Sized>(v: &mut V, select: &mut OneSelect) {
+    match select {
+        OneSelect::Select {
+            columns,
+            from,
+            where_clause,
+            group_by,
+            window_clause,
+            ..
+        } => {
+            visit_result_columns_mut(v, columns);
+            if let Some(from) = from {
+                v.visit_from_clause(from);
+            }
+            if let Some(where_clause) = where_clause {
+                v.visit_expr(where_clause);
+            }
+            if let Some(group_by) = group_by {
+                visit_exprs_mut(v, &mut group_by.exprs);
+                if let Some(ref mut having) = group_by.having {
+                    v.visit_expr(having);
+                }
+            }
+            for def in window_clause.iter_mut().flatten() {
+                v.visit_window(&mut def.window);
+            }
+        }
+        OneSelect::Values(values) => {
+            for row in values {
+                visit_exprs_mut(v, row);
+            }
+        }
+    }
+}
+
+/// Visit the tables and join constraints of `from`
+pub fn walk_from_clause_mut<V: VisitorMut + ?Sized>(v: &mut V, from: &mut FromClause) {
+    if let Some(ref mut table) = from.select {
+        v.visit_select_table(table);
+    }
+    for join in from.joins.iter_mut().flatten() {
+        v.visit_select_table(&mut join.table);
+        if let Some(JoinConstraint::On(ref mut expr)) = join.constraint {
+            v.visit_expr(expr);
+        }
+    }
+}
+
+/// Visit the arguments of a table-valued function or the subquery of `table`
+pub fn walk_select_table_mut<V: VisitorMut + ?Sized>(v: &mut V, table: &mut SelectTable) {
+    match table {
+        SelectTable::Table(..) => {}
+        SelectTable::TableCall(_, args, _) => {
+            if let Some(args) = args {
+                visit_exprs_mut(v, args);
+            }
+        }
+        SelectTable::Select(select, _) => v.visit_select(select),
+        SelectTable::Sub(from, _) => v.visit_from_clause(from),
+    }
+}
+
+/// Visit the subexpressions and subqueries of `expr`
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut Expr) {
+    match expr {
+        Expr::Between {
+            lhs, start, end, ..
+        } => {
+            v.visit_expr(lhs);
+            v.visit_expr(start);
+            v.visit_expr(end);
+        }
+        Expr::Binary(lhs, _, rhs) => {
+            v.visit_expr(lhs);
+            v.visit_expr(rhs);
+        }
+        Expr::Case {
+            base,
+            when_then_pairs,
+            else_expr,
+        } => {
+            if let Some(base) = base {
+                v.visit_expr(base);
+            }
+            for (when, then) in when_then_pairs {
+                v.visit_expr(when);
+                v.visit_expr(then);
+            }
+            if let Some(else_expr) = else_expr {
+                v.visit_expr(else_expr);
+            }
+        }
+        Expr::Cast { expr, .. } => v.visit_expr(expr),
+        Expr::Collate(expr, _) => v.visit_expr(expr),
+        Expr::Exists(select) => v.visit_select(select),
+        Expr::FunctionCall {
+            args, filter_over, ..
+        } => {
+            if let Some(args) = args {
+                visit_exprs_mut(v, args);
+            }
+            walk_function_tail_mut(v, filter_over);
+        }
+        Expr::FunctionCallStar { filter_over, .. } => walk_function_tail_mut(v, filter_over),
+        Expr::InList { lhs, rhs, .. } => {
+            v.visit_expr(lhs);
+            if let Some(rhs) = rhs {
+                visit_exprs_mut(v, rhs);
+            }
+        }
+        Expr::InSelect { lhs, rhs, .. } => {
+            v.visit_expr(lhs);
+            v.visit_select(rhs);
+        }
+        Expr::InTable { lhs, args, .. } => {
+            v.visit_expr(lhs);
+            if let Some(args) = args {
+                visit_exprs_mut(v, args);
+            }
+        }
+        Expr::IsNull(expr) | Expr::NotNull(expr) => v.visit_expr(expr),
+        Expr::Like {
+            lhs, rhs, escape, ..
+        } => {
+            v.visit_expr(lhs);
+            v.visit_expr(rhs);
+            if let Some(escape) = escape {
+                v.visit_expr(escape);
+            }
+        }
+        Expr::Parenthesized(exprs) => visit_exprs_mut(v, exprs),
+        Expr::Raise(_, Some(err)) => v.visit_expr(err),
+        Expr::Subquery(select) => v.visit_select(select),
+        Expr::Unary(_, expr) => v.visit_expr(expr),
+        Expr::DoublyQualified(..)
+        | Expr::Id(_)
+        | Expr::Literal(_)
+        | Expr::Name(_)
+        | Expr::Qualified(..)
+        | Expr::Raise(_, None)
+        | Expr::Variable(_) => {}
+    }
+}
+
+fn walk_function_tail_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    filter_over: &mut Option<FunctionTail>,
+) {
+    if let Some(tail) = filter_over {
+        if let Some(ref mut filter_clause) = tail.filter_clause {
+            v.visit_expr(filter_clause);
+        }
+        if let Some(Over::Window(ref mut window)) = tail.over_clause.as_deref_mut() {
+            v.visit_window(window);
+        }
+    }
+}
+
+/// Visit the `PARTITION BY`, `ORDER BY` and frame bound expressions of `window`
+pub fn walk_window_mut<V: VisitorMut + ?Sized>(v: &mut V, window: &mut Window) {
+    if let Some(ref mut partition_by) = window.partition_by {
+        visit_exprs_mut(v, partition_by);
+    }
+    if let Some(ref mut order_by) = window.order_by {
+        visit_sorted_columns_mut(v, order_by);
+    }
+    if let Some(ref mut frame_clause) = window.frame_clause {
+        for bound in core::iter::once(&mut frame_clause.start).chain(frame_clause.end.as_mut()) {
+            if let FrameBound::Following(expr) | FrameBound::Preceding(expr) = bound {
+                v.visit_expr(expr);
+            }
+        }
+    }
+}
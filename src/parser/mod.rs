@@ -1,4 +1,8 @@
 //! SQLite parser
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt;
 use log::error;
 
 pub mod ast;
@@ -14,7 +18,9 @@ pub mod parse {
     include!(concat!(env!("OUT_DIR"), "/parse.rs"));
 }
 
-use ast::{Cmd, ExplainKind, Name, Stmt};
+use crate::dialect::TokenType::{self, *};
+use ast::{Cmd, Diagnostic, ExplainKind, Expr, Name, Stmt};
+use parse::{yyParser, YYCODETYPE};
 
 /// Parser error
 #[derive(Debug)]
@@ -26,12 +32,13 @@ impl ParserError {
     }
 }
 
-impl std::fmt::Display for ParserError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "parser error: {}", self.0,)
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for ParserError {}
 
 /// Parser context
@@ -39,8 +46,17 @@ pub struct Context {
     explain: Option<ExplainKind>,
     stmt: Option<Stmt>,
     constraint_name: Option<Name>, // transient
+    /// See `ParserOptions::double_quote_is_string`
+    pub(crate) double_quote_is_string: bool,
+    /// See `ParserOptions::update_delete_limit`
+    pub(crate) update_delete_limit: bool,
+    /// Estimated number of rows of the next `VALUES` clause (0 if unknown)
+    pub(crate) values_capacity: usize,
+    warnings: Vec<Diagnostic>,
     done: bool,
     error: Option<String>,
+    /// Tokens which would have been accepted where the syntax error occurred
+    expected: Vec<YYCODETYPE>,
 }
 
 impl Context {
@@ -49,8 +65,13 @@ impl Context {
             explain: None,
             stmt: None,
             constraint_name: None,
+            double_quote_is_string: true,
+            update_delete_limit: false,
+            values_capacity: 0,
+            warnings: Vec::new(),
             done: false,
             error: None,
+            expected: Vec::new(),
         }
     }
 
@@ -74,6 +95,20 @@ impl Context {
         self.constraint_name.is_none()
     }
 
+    /// Report a construct which would be rejected by SQLite
+    fn warning(&mut self, diagnostic: Diagnostic) {
+        self.warnings.push(diagnostic);
+    }
+    /// Consume reported warnings
+    pub(crate) fn take_warnings(&mut self) -> Vec<Diagnostic> {
+        core::mem::take(&mut self.warnings)
+    }
+
+    /// Rows of a `VALUES` clause with the estimated capacity
+    fn values(&mut self) -> Vec<Vec<Expr>> {
+        Vec::with_capacity(core::mem::take(&mut self.values_capacity).max(1))
+    }
+
     fn sqlite3_error_msg(&mut self, msg: &str) {
         error!("parser error: {}", msg);
     }
@@ -97,11 +132,63 @@ impl Context {
         self.error.take()
     }
 
+    /// Consume the tokens expected at the syntax error
+    pub(crate) fn take_expected(&mut self) -> Vec<YYCODETYPE> {
+        core::mem::take(&mut self.expected)
+    }
+
     pub fn reset(&mut self) {
         self.explain = None;
         self.stmt = None;
         self.constraint_name = None;
+        self.values_capacity = 0;
+        self.warnings.clear();
         self.done = false;
         self.error = None;
+        self.expected.clear();
+    }
+}
+
+/// Maximum number of expected tokens reported in a syntax error message
+const MAX_EXPECTED: usize = 10;
+
+/// Build the message for a syntax error near the `yymajor` token
+fn syntax_error_msg(
+    yymajor: YYCODETYPE,
+    yyminor: Option<&String>,
+    expected: &[YYCODETYPE],
+) -> String {
+    let near = match TokenType::from_code(yymajor) {
+        Some(tt) => tt.text(yyminor.map(String::as_str)),
+        None => yyminor.map_or("?", String::as_str),
+    };
+    let id_expected = expected.contains(&(TK_ID as YYCODETYPE));
+    let mut categories: Vec<&str> = vec![];
+    for &code in expected {
+        if id_expected
+            && (yyParser::parse_fallback(code) == TK_ID as YYCODETYPE
+                || code == TK_INDEXED as YYCODETYPE
+                || code == TK_JOIN_KW as YYCODETYPE)
+        {
+            // keyword which can be used as an identifier
+            continue;
+        }
+        let category = match TokenType::from_code(code) {
+            Some(TK_INTEGER) | Some(TK_FLOAT) => "number",
+            Some(tt) => tt.name(),
+            None => continue,
+        };
+        if !categories.contains(&category) {
+            categories.push(category);
+        }
+    }
+    if categories.is_empty() {
+        return format!("near \"{}\": syntax error", near);
+    }
+    let mut msg = format!("near \"{}\": syntax error, expected one of: ", near);
+    msg.push_str(&categories[..categories.len().min(MAX_EXPECTED)].join(", "));
+    if categories.len() > MAX_EXPECTED {
+        msg.push_str(", ...");
     }
+    msg
 }
@@ -37,13 +37,24 @@
 // This code runs whenever there is a syntax error
 //
 %syntax_error {
+  let expected = self.expected_tokens();
   if TokenType::TK_EOF as YYCODETYPE == yymajor {
     error!(target: TARGET, "incomplete input");
     self.ctx.error = Some("incomplete input".to_owned());
+  } else if self.identity_column(yymajor, yyminor) {
+    let msg = "SQLite does not support IDENTITY columns; use INTEGER PRIMARY KEY AUTOINCREMENT.";
+    error!(target: TARGET, "{}", msg);
+    self.ctx.error = Some(msg.to_owned());
+  } else if self.offset_alias() {
+    let msg = "OFFSET requires LIMIT";
+    error!(target: TARGET, "{}", msg);
+    self.ctx.error = Some(msg.to_owned());
   } else {
-    error!(target: TARGET, "near {}, \"{:?}\": syntax error", yyTokenName[yymajor as usize], yyminor);
-    self.ctx.error = Some(format!("near {}, \"{:?}\": syntax error", yyTokenName[yymajor as usize], yyminor));
+    let msg = crate::parser::syntax_error_msg(yymajor, yyminor, &expected);
+    error!(target: TARGET, "{}", msg);
+    self.ctx.error = Some(msg);
   }
+  self.ctx.expected = expected;
 }
 %stack_overflow {
   error!(target: TARGET, "parser stack overflow");
@@ -58,13 +69,56 @@
 // code file that implements the parser.
 //
 %include {
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
 use crate::parser::ast::*;
-use crate::parser::Context;
+use crate::parser::{Context, ParserError};
 use crate::dialect::{from_token, TokenType};
-use log::{debug, error, log_enabled};
+use log::{debug, error, log_enabled, warn};
 
 #[allow(non_camel_case_types)]
 type sqlite3ParserError = crate::parser::ParserError;
+
+impl yyParser {
+  // PostgreSQL `GENERATED { ALWAYS | BY DEFAULT } AS IDENTITY` column:
+  // `IDENTITY` where the `(` of a generated column is expected, or `BY`
+  // following the `GENERATED` keyword of a column constraint.
+  // (`GENERATED ...` directly following the column name or type is part
+  // of the type name)
+  fn identity_column(&self, yymajor: YYCODETYPE, yyminor: Option<&String>) -> bool {
+    let code = |token_type: TokenType| token_type as YYCODETYPE;
+    if yymajor == code(TokenType::TK_BY) {
+      return self[0].major == code(TokenType::TK_GENERATED);
+    }
+    if yymajor != code(TokenType::TK_ID)
+      || !yyminor.is_some_and(|id| id.eq_ignore_ascii_case("IDENTITY"))
+      || self[0].major != code(TokenType::TK_AS)
+    {
+      return false;
+    }
+    // `GENERATED ALWAYS AS` or `AS` in the constraints of a column
+    // (and not `WINDOW w AS`)
+    let below: Vec<YYACTIONTYPE> =
+      self.yystack[..self.yyidx].iter().map(|entry| entry.stateno).collect();
+    self[-1].major == code(TokenType::TK_ALWAYS)
+      || yy_would_shift(&below, code(TokenType::TK_GENERATED))
+  }
+
+  // `OFFSET` without `LIMIT` where an alias is allowed (`... FROM t OFFSET 1`):
+  // the `OFFSET` fallback is shifted as an alias and its expression is the
+  // unexpected token. (elsewhere, `limit_opt ::= OFFSET expr` rejects it)
+  fn offset_alias(&self) -> bool {
+    if self.yy_top_major() != TokenType::TK_OFFSET as YYCODETYPE {
+      return false;
+    }
+    let mut below = self.yy_states();
+    below.pop();
+    yy_would_shift(&below, TokenType::TK_LIMIT as YYCODETYPE)
+  }
+}
 } // end %include
 
 // Input is a single SQL command
@@ -111,7 +165,7 @@ cmd ::= ROLLBACK trans_opt(Y) TO savepoint_opt nm(X). {
 ///////////////////// The CREATE TABLE statement ////////////////////////////
 //
 cmd ::= createkw temp(T) TABLE ifnotexists(E) fullname(Y) create_table_args(X). {
-  self.ctx.stmt = Some(Stmt::CreateTable{ temporary: T, if_not_exists: E, tbl_name: Y, body: X });
+  self.ctx.stmt = Some(Stmt::CreateTable{ temporary: T, if_not_exists: E, tbl_name: Y, body: *X });
 }
 createkw(A) ::= CREATE(A).
 
@@ -124,12 +178,12 @@ temp(A) ::= TEMP.  {A = true;}
 %endif  SQLITE_OMIT_TEMPDB
 temp(A) ::= .      {A = false;}
 
-%type create_table_args {CreateTableBody}
+%type create_table_args {Box<CreateTableBody>}
 create_table_args(A) ::= LP columnlist(C) conslist_opt(X) RP table_option_set(F). {
-  A = CreateTableBody::columns_and_constraints(C, X, F)?;
+  A = Box::new(CreateTableBody::columns_and_constraints(C, X, F)?);
 }
 create_table_args(A) ::= AS select(S). {
-  A = CreateTableBody::AsSelect(S);
+  A = Box::new(CreateTableBody::AsSelect(*S));
 }
 %type table_option_set {TableOptions}
 %type table_option {TableOptions}
@@ -253,12 +307,15 @@ nm(A) ::= JOIN_KW(X). { A = Name::from_token(@X, X); }
 //
 %type typetoken {Option<Type>}
 typetoken(A) ::= .   {A = None;}
-typetoken(A) ::= typename(X). {A = Some(Type{ name: X, size: None });}
-typetoken(A) ::= typename(X) LP signed(Y) RP. {
-  A = Some(Type{ name: X, size: Some(TypeSize::MaxSize(Box::new(Y))) });
+typetoken(A) ::= ntypetoken(X). {A = Some(X);}
+// A non-empty typetoken (mandatory in a CAST)
+%type ntypetoken {Type}
+ntypetoken(A) ::= typename(X). {A = Type{ name: X, size: None };}
+ntypetoken(A) ::= typename(X) LP signed(Y) RP. {
+  A = Type{ name: X, size: Some(TypeSize::MaxSize(Box::new(Y))) };
 }
-typetoken(A) ::= typename(X) LP signed(Y) COMMA signed(Z) RP. {
-  A = Some(Type{ name: X, size: Some(TypeSize::TypeSize(Box::new(Y), Box::new(Z))) });
+ntypetoken(A) ::= typename(X) LP signed(Y) COMMA signed(Z) RP. {
+  A = Type{ name: X, size: Some(TypeSize::TypeSize(Box::new(Y), Box::new(Z))) };
 }
 %type typename {String}
 typename(A) ::= ids(X). {A=from_token(@X, X);}
@@ -310,8 +367,14 @@ ccons(A) ::= DEFAULT MINUS term(X).      {
   A = NamedColumnConstraint{ name, constraint };
 }
 ccons(A) ::= DEFAULT id(X).       {
+  let id = Expr::id(@X, X);
+  if let Expr::Id(Id(ref x)) = id {
+    if !self.ctx.double_quote_is_string && x.starts_with('"') {
+      return Err(ParserError(format!("double-quoted string literal: {}", x)));
+    }
+  }
   let name = self.ctx.constraint_name();
-  let constraint = ColumnConstraint::Default(Expr::id(@X, X));
+  let constraint = ColumnConstraint::Default(id);
   A = NamedColumnConstraint{ name, constraint };
 }
 
@@ -340,7 +403,7 @@ ccons(A) ::= UNIQUE onconf(R).      {
 }
 ccons(A) ::= CHECK LP expr(X) RP.   {
   let name = self.ctx.constraint_name();
-  let constraint = ColumnConstraint::Check(X);
+  let constraint = ColumnConstraint::check(X)?;
   A = NamedColumnConstraint{ name, constraint };
 }
 ccons(A) ::= REFERENCES nm(T) eidlist_opt(TA) refargs(R). {
@@ -430,7 +493,7 @@ tcons(A) ::= UNIQUE LP sortlist(X) RP onconf(R). {
 }
 tcons(A) ::= CHECK LP expr(E) RP onconf. {
   let name = self.ctx.constraint_name();
-  let constraint = TableConstraint::Check(E);
+  let constraint = TableConstraint::check(E)?;
   A = NamedTableConstraint{ name, constraint };
 }
 tcons(A) ::= FOREIGN KEY LP eidlist(FA) RP
@@ -473,7 +536,7 @@ ifexists(A) ::= .            {A = false;}
 cmd ::= createkw temp(T) VIEW ifnotexists(E) fullname(Y) eidlist_opt(C)
           AS select(S). {
   self.ctx.stmt = Some(Stmt::CreateView{ temporary: T, if_not_exists: E, view_name: Y, columns: C,
-                                         select: S });
+                                         select: *S });
 }
 cmd ::= DROP VIEW ifexists(E) fullname(X). {
   self.ctx.stmt = Some(Stmt::DropView{ if_exists: E, view_name: X });
@@ -483,34 +546,34 @@ cmd ::= DROP VIEW ifexists(E) fullname(X). {
 //////////////////////// The SELECT statement /////////////////////////////////
 //
 cmd ::= select(X).  {
-  self.ctx.stmt = Some(Stmt::Select(X));
+  self.ctx.stmt = Some(Stmt::Select(*X));
 }
 
-%type select {Select}
-%type selectnowith {SelectBody}
-%type oneselect {OneSelect}
+%type select {Box<Select>}
+%type selectnowith {Box<SelectBody>}
+%type oneselect {Box<OneSelect>}
 
 %include {
 }
 
 %ifndef SQLITE_OMIT_CTE
 select(A) ::= WITH wqlist(W) selectnowith(X) orderby_opt(Z) limit_opt(L). {
-  A = Select{ with: Some(With { recursive: false, ctes: W }), body: X, order_by: Z, limit: L };
+  A = Box::new(Select{ with: Some(With { recursive: false, ctes: W }), body: *X, order_by: Z, limit: L });
 }
 select(A) ::= WITH RECURSIVE wqlist(W) selectnowith(X) orderby_opt(Z) limit_opt(L). {
-  A = Select{ with: Some(With { recursive: true, ctes: W }), body: X, order_by: Z, limit: L };
+  A = Box::new(Select{ with: Some(With { recursive: true, ctes: W }), body: *X, order_by: Z, limit: L });
 }
 %endif /* SQLITE_OMIT_CTE */
 select(A) ::= selectnowith(X) orderby_opt(Z) limit_opt(L). {
-  A = Select{ with: None, body: X, order_by: Z, limit: L }; /*A-overwrites-X*/
+  A = Box::new(Select{ with: None, body: *X, order_by: Z, limit: L }); /*A-overwrites-X*/
 }
 
 selectnowith(A) ::= oneselect(X). {
-  A = SelectBody{ select: X, compounds: None };
+  A = Box::new(SelectBody{ select: *X, compounds: None });
 }
 %ifndef SQLITE_OMIT_COMPOUND_SELECT
 selectnowith(A) ::= selectnowith(A) multiselect_op(Y) oneselect(Z).  {
-  let cs = CompoundSelect{ operator: Y, select: Z };
+  let cs = CompoundSelect{ operator: Y, select: *Z };
   A.push(cs);
 }
 %type multiselect_op {CompoundOperator}
@@ -522,23 +585,25 @@ multiselect_op(A) ::= INTERSECT.         {A = CompoundOperator::Intersect;}
 
 oneselect(A) ::= SELECT distinct(D) selcollist(W) from(X) where_opt(Y)
                  groupby_opt(P). {
-  A = OneSelect::Select{ distinctness: D, columns: W, from: X, where_clause: Y,
-                         group_by: P, window_clause: None };
+  A = Box::new(OneSelect::Select{ distinctness: D, columns: W, from: X, where_clause: Y,
+                         group_by: P, window_clause: None });
     }
 %ifndef SQLITE_OMIT_WINDOWFUNC
 oneselect(A) ::= SELECT distinct(D) selcollist(W) from(X) where_opt(Y)
                  groupby_opt(P) window_clause(R). {
-  A = OneSelect::Select{ distinctness: D, columns: W, from: X, where_clause: Y,
-                         group_by: P, window_clause: Some(R) };
+  A = Box::new(OneSelect::Select{ distinctness: D, columns: W, from: X, where_clause: Y,
+                         group_by: P, window_clause: Some(R) });
 }
 %endif
 
 
-oneselect(A) ::= values(X). { A = OneSelect::Values(X); }
+oneselect(A) ::= values(X). { A = Box::new(OneSelect::Values(X)); }
 
 %type values {Vec<Vec<Expr>>}
 values(A) ::= VALUES LP nexprlist(X) RP. {
-  A = vec![X];
+  let mut rows = self.ctx.values();
+  rows.push(X);
+  A = rows;
 }
 values(A) ::= values(A) COMMA LP nexprlist(Y) RP. {
   let exprs = Y;
@@ -618,7 +683,7 @@ seltablist(A) ::= stl_prefix(A) fullname(Y) LP exprlist(E) RP as(Z)
 %ifndef SQLITE_OMIT_SUBQUERY
   seltablist(A) ::= stl_prefix(A) LP select(S) RP
                     as(Z) on_using(N). {
-    let st = SelectTable::Select(S, Z);
+    let st = SelectTable::Select(*S, Z);
     let jc = N;
     A.push(st, jc);
   }
@@ -729,6 +794,7 @@ nulls(A) ::= .                  {A = None;}
 %type groupby_opt {Option<GroupBy>}
 groupby_opt(A) ::= .                      {A = None;}
 groupby_opt(A) ::= GROUP BY nexprlist(X) having_opt(Y). {A = Some(GroupBy{ exprs: X, having: Y });}
+groupby_opt(A) ::= HAVING expr(X).        {A = Some(GroupBy{ exprs: vec![], having: Some(X) });}
 
 %type having_opt {Option<Expr>}
 having_opt(A) ::= .                {A = None;}
@@ -751,15 +817,23 @@ limit_opt(A) ::= LIMIT expr(X) OFFSET expr(Y).
                          {A = Some(Limit{ expr: X, offset: Some(Y) });}
 limit_opt(A) ::= LIMIT expr(X) COMMA expr(Y). 
                          {A = Some(Limit{ expr: X, offset: Some(Y) });}
+// `OFFSET` is an alias (ID fallback) wherever one is allowed: see offset_alias
+limit_opt ::= OFFSET expr. {
+  return Err(ParserError("OFFSET requires LIMIT".to_owned()));
+}
 
 /////////////////////////// The DELETE statement /////////////////////////////
 //
 %if SQLITE_ENABLE_UPDATE_DELETE_LIMIT || SQLITE_UDL_CAPABLE_PARSER
 cmd ::= with(C) DELETE FROM xfullname(X) indexed_opt(I) where_opt_ret(W)
         orderby_opt(O) limit_opt(L). {
+  let (order_by, limit) = (O, L);
+  if !self.ctx.update_delete_limit && (order_by.is_some() || limit.is_some()) {
+    return Err(ParserError("ORDER BY and LIMIT are not supported on DELETE".to_owned()));
+  }
   let (where_clause, returning) = W;
   self.ctx.stmt = Some(Stmt::Delete{ with: C, tbl_name: X, indexed: I, where_clause, returning,
-                                     order_by: O, limit: L });
+                                     order_by, limit });
 }
 %else
 cmd ::= with(C) DELETE FROM xfullname(X) indexed_opt(I) where_opt_ret(W). {
@@ -786,9 +860,13 @@ where_opt_ret(A) ::= WHERE expr(X) RETURNING selcollist(Y).
 %if SQLITE_ENABLE_UPDATE_DELETE_LIMIT || SQLITE_UDL_CAPABLE_PARSER
 cmd ::= with(C) UPDATE orconf(R) xfullname(X) indexed_opt(I) SET setlist(Y) from(F)
         where_opt_ret(W) orderby_opt(O) limit_opt(L).  {
+  let (order_by, limit) = (O, L);
+  if !self.ctx.update_delete_limit && (order_by.is_some() || limit.is_some()) {
+    return Err(ParserError("ORDER BY and LIMIT are not supported on UPDATE".to_owned()));
+  }
   let (where_clause, returning) = W;
   self.ctx.stmt = Some(Stmt::Update { with: C, or_conflict: R, tbl_name: X, indexed: I, sets: Y, from: F,
-                                      where_clause, returning, order_by: O, limit: L });
+                                      where_clause, returning, order_by, limit });
 }
 %else
 cmd ::= with(C) UPDATE orconf(R) xfullname(X) indexed_opt(I) SET setlist(Y) from(F)
@@ -823,7 +901,7 @@ setlist(A) ::= LP idlist(X) RP EQ expr(Y). {
 cmd ::= with(W) insert_cmd(R) INTO xfullname(X) idlist_opt(F) select(S)
         upsert(U). {
   let (upsert, returning) = U;
-  let body = InsertBody::Select(S, upsert);
+  let body = InsertBody::Select(*S, upsert);
   self.ctx.stmt = Some(Stmt::Insert{ with: W, or_conflict: R, tbl_name: X, columns: F,
                                      body, returning });
 }
@@ -908,8 +986,8 @@ expr(A) ::= expr(X) COLLATE ids(C). {
   A = Expr::collate(X, @C, C); /*A-overwrites-X*/
 }
 %ifndef SQLITE_OMIT_CAST
-expr(A) ::= CAST LP expr(E) AS typetoken(T) RP. {
-  A = Expr::cast(E, T.unwrap()); // FIXME mandatory ?
+expr(A) ::= CAST LP expr(E) AS ntypetoken(T) RP. {
+  A = Expr::cast(E, T);
 }
 %endif  SQLITE_OMIT_CAST
 
@@ -982,10 +1060,10 @@ expr(A) ::= expr(X) IS NOT expr(Y). {
   A = Expr::binary(X, TokenType::TK_NOT as YYCODETYPE, Y); /*A-overwrites-X*/
 }
 expr(A) ::= expr(X) IS NOT DISTINCT FROM expr(Y).     {
-  A = Expr::binary(X, TokenType::TK_IS as YYCODETYPE, Y); /*A-overwrites-X*/
+  A = Expr::Binary(Box::new(X), Operator::IsNotDistinctFrom, Box::new(Y)); /*A-overwrites-X*/
 }
 expr(A) ::= expr(X) IS DISTINCT FROM expr(Y). {
-  A = Expr::binary(X, TokenType::TK_NOT as YYCODETYPE, Y); /*A-overwrites-X*/
+  A = Expr::Binary(Box::new(X), Operator::IsDistinctFrom, Box::new(Y)); /*A-overwrites-X*/
 }
 
 expr(A) ::= NOT(B) expr(X).
@@ -1014,16 +1092,16 @@ expr(A) ::= expr(B) between_op(N) expr(X) AND expr(Y). [BETWEEN] {
     A = Expr::in_list(X, N, Y);/*A-overwrites-X*/
   }
   expr(A) ::= LP select(X) RP. {
-    A = Expr::sub_query(X);
+    A = Expr::Subquery(X);
   }
   expr(A) ::= expr(X) in_op(N) LP select(Y) RP.  [IN] {
-    A = Expr::in_select(X, N, Y);/*A-overwrites-X*/
+    A = Expr::InSelect{ lhs: Box::new(X), not: N, rhs: Y };/*A-overwrites-X*/
   }
   expr(A) ::= expr(X) in_op(N) fullname(Y) paren_exprlist(E). [IN] {
     A = Expr::in_table(X, N, Y, E);/*A-overwrites-X*/
   }
   expr(A) ::= EXISTS LP select(Y) RP. {
-    A = Expr::Exists(Box::new(Y));
+    A = Expr::Exists(Y);
   }
 %endif SQLITE_OMIT_SUBQUERY
 
@@ -1186,11 +1264,11 @@ when_clause(A) ::= WHEN expr(X). { A = Some(X); }
 
 %type trigger_cmd_list {Vec<TriggerCmd>}
 trigger_cmd_list(A) ::= trigger_cmd_list(A) trigger_cmd(X) SEMI. {
-  let tc = X;
+  let tc = *X;
   A.push(tc);
 }
 trigger_cmd_list(A) ::= trigger_cmd(X) SEMI. {
-  A = vec![X];
+  A = vec![*X];
 }
 
 // Disallow qualified table names on INSERT, UPDATE, and DELETE statements
@@ -1224,32 +1302,32 @@ tridxby ::= NOT INDEXED. {
 
 
 
-%type trigger_cmd {TriggerCmd}
+%type trigger_cmd {Box<TriggerCmd>}
 // UPDATE 
 trigger_cmd(A) ::=
    UPDATE orconf(R) trnm(X) tridxby SET setlist(Y) from(F) where_opt(Z).
-   {A = TriggerCmd::Update{ or_conflict: R, tbl_name: X, sets: Y, from: F, where_clause: Z };}
+   {A = Box::new(TriggerCmd::Update{ or_conflict: R, tbl_name: X, sets: Y, from: F, where_clause: Z });}
 
 // INSERT
 trigger_cmd(A) ::= insert_cmd(R) INTO
                       trnm(X) idlist_opt(F) select(S) upsert(U). {
   let (upsert, returning) = U;
-   A = TriggerCmd::Insert{ or_conflict: R, tbl_name: X, col_names: F, select: S, upsert, returning };/*A-overwrites-R*/
+   A = Box::new(TriggerCmd::Insert{ or_conflict: R, tbl_name: X, col_names: F, select: *S, upsert, returning });/*A-overwrites-R*/
 }
 // DELETE
 trigger_cmd(A) ::= DELETE FROM trnm(X) tridxby where_opt(Y).
-   {A = TriggerCmd::Delete{ tbl_name: X, where_clause: Y };}
+   {A = Box::new(TriggerCmd::Delete{ tbl_name: X, where_clause: Y });}
 
 // SELECT
 trigger_cmd(A) ::= select(X).
-   {A = TriggerCmd::Select(X); /*A-overwrites-X*/}
+   {A = Box::new(TriggerCmd::Select(*X)); /*A-overwrites-X*/}
 
 // The special RAISE expression that may occur in trigger programs
 expr(A) ::= RAISE LP IGNORE RP.  {
   A = Expr::Raise(ResolveType::Ignore, None);
 }
-expr(A) ::= RAISE LP raisetype(T) COMMA nm(Z) RP.  {
-  A = Expr::Raise(T, Some(Z));
+expr(A) ::= RAISE LP raisetype(T) COMMA expr(Z) RP.  {
+  A = Expr::Raise(T, Some(Box::new(Z)));
 }
 %endif  !SQLITE_OMIT_TRIGGER
 
@@ -1300,11 +1378,19 @@ cmd ::= ANALYZE fullname(X).  {self.ctx.stmt = Some(Stmt::Analyze(Some(X)));}
 cmd ::= ALTER TABLE fullname(X) RENAME TO nm(Z). {
   self.ctx.stmt = Some(Stmt::AlterTable(X, AlterTableBody::RenameTo(Z)));
 }
+cmd ::= ALTER TABLE fullname RENAME TO nm(S) DOT nm(Z). {
+  return Err(ParserError(format!("new table name cannot be qualified: {}.{}", S, Z)));
+}
 cmd ::= ALTER TABLE fullname(X)
         ADD kwcolumn_opt columnname(Y) carglist(C). {
   let (col_name, col_type) = Y;
   let cd = ColumnDefinition{ col_name, col_type, constraints: C };
-  self.ctx.stmt = Some(Stmt::AlterTable(X, AlterTableBody::AddColumn(cd)));
+  let tbl_name = X;
+  for diagnostic in cd.add_column_warnings() {
+    warn!(target: TARGET, "{}", diagnostic);
+    self.ctx.warning(diagnostic.on_table(&tbl_name.name));
+  }
+  self.ctx.stmt = Some(Stmt::AlterTable(tbl_name, AlterTableBody::AddColumn(cd)));
 }
 cmd ::= ALTER TABLE fullname(X) RENAME kwcolumn_opt nm(Y) TO nm(Z). {
   self.ctx.stmt = Some(Stmt::AlterTable(X, AlterTableBody::RenameColumn{ old: Y, new: Z }));
@@ -1319,18 +1405,18 @@ kwcolumn_opt ::= COLUMNKW.
 
 //////////////////////// CREATE VIRTUAL TABLE ... /////////////////////////////
 %ifndef SQLITE_OMIT_VIRTUALTABLE
-cmd ::= create_vtab(X).                       {self.ctx.stmt = Some(X);}
+cmd ::= create_vtab(X).                       {self.ctx.stmt = Some(*X);}
 cmd ::= create_vtab(X) LP vtabarglist(Y) RP.  {
-  let mut stmt = X;
+  let mut stmt = *X;
   if let Stmt::CreateVirtualTable{ ref mut args, .. } = stmt {
     *args = Y;
   }
   self.ctx.stmt = Some(stmt);
 }
-%type create_vtab {Stmt}
+%type create_vtab {Box<Stmt>}
 create_vtab(A) ::= createkw VIRTUAL TABLE ifnotexists(E)
                 fullname(X) USING nm(Z). {
-    A = Stmt::CreateVirtualTable{ if_not_exists: E, tbl_name: X, module_name: Z, args: None };
+    A = Box::new(Stmt::CreateVirtualTable{ if_not_exists: E, tbl_name: X, module_name: Z, args: None });
 }
 vtabarglist ::= vtabarg.
 vtabarglist ::= vtabarglist COMMA vtabarg.
@@ -1348,7 +1434,7 @@ anylist ::= anylist ANY.
 //////////////////////// COMMON TABLE EXPRESSIONS ////////////////////////////
 %type with {Option<With>}
 %type wqlist {Vec<CommonTableExpr>}
-%type wqitem {CommonTableExpr}
+%type wqitem {Box<CommonTableExpr>}
 // %destructor wqitem {sqlite3CteDelete(pParse->db, $$);} // not reachable
 
 with(A) ::= . { A = None; }
@@ -1361,13 +1447,13 @@ wqas(A)   ::= AS.                  {A = Materialized::Any;}
 wqas(A)   ::= AS MATERIALIZED.     {A = Materialized::Yes;}
 wqas(A)   ::= AS NOT MATERIALIZED. {A = Materialized::No;}
 wqitem(A) ::= nm(X) eidlist_opt(Y) wqas(M) LP select(Z) RP. {
-  A = CommonTableExpr{ tbl_name: X, columns: Y, materialized: M, select: Z }; /*A-overwrites-X*/
+  A = Box::new(CommonTableExpr{ tbl_name: X, columns: Y, materialized: M, select: *Z }); /*A-overwrites-X*/
 }
 wqlist(A) ::= wqitem(X). {
-  A = vec![X]; /*A-overwrites-X*/
+  A = vec![*X]; /*A-overwrites-X*/
 }
 wqlist(A) ::= wqlist(A) COMMA wqitem(X). {
-  let cte = X;
+  let cte = *X;
   CommonTableExpr::add_cte(A, cte)?;
 }
 %endif  SQLITE_OMIT_CTE
@@ -1380,24 +1466,24 @@ wqlist(A) ::= wqlist(A) COMMA wqitem(X). {
 //
 %ifndef SQLITE_OMIT_WINDOWFUNC
 %type windowdefn_list {Vec<WindowDef>}
-windowdefn_list(A) ::= windowdefn(Z). { A = vec![Z]; }
+windowdefn_list(A) ::= windowdefn(Z). { A = vec![*Z]; }
 windowdefn_list(A) ::= windowdefn_list(A) COMMA windowdefn(Z). {
-  let w = Z;
+  let w = *Z;
   A.push(w);
 }
 
-%type windowdefn {WindowDef}
+%type windowdefn {Box<WindowDef>}
 windowdefn(A) ::= nm(X) AS LP window(Y) RP. {
-  A = WindowDef { name: X, window: Y};
+  A = Box::new(WindowDef { name: X, window: *Y});
 }
 
-%type window {Window}
+%type window {Box<Window>}
 
 %type frame_opt {Option<FrameClause>}
 
 %type filter_clause {Expr}
 
-%type over_clause {Over}
+%type over_clause {Box<Over>}
 
 %type filter_over {FunctionTail}
 
@@ -1408,22 +1494,22 @@ windowdefn(A) ::= nm(X) AS LP window(Y) RP. {
 %type frame_bound_e {FrameBound}
 
 window(A) ::= PARTITION BY nexprlist(X) orderby_opt(Y) frame_opt(Z). {
-  A = Window{ base: None,  partition_by: Some(X), order_by: Y, frame_clause: Z};
+  A = Box::new(Window{ base: None,  partition_by: Some(X), order_by: Y, frame_clause: Z});
 }
 window(A) ::= nm(W) PARTITION BY nexprlist(X) orderby_opt(Y) frame_opt(Z). {
-  A = Window{ base: Some(W),  partition_by: Some(X), order_by: Y, frame_clause: Z};
+  A = Box::new(Window{ base: Some(W),  partition_by: Some(X), order_by: Y, frame_clause: Z});
 }
 window(A) ::= ORDER BY sortlist(Y) frame_opt(Z). {
-  A = Window{ base: None,  partition_by: None, order_by: Some(Y), frame_clause: Z};
+  A = Box::new(Window{ base: None,  partition_by: None, order_by: Some(Y), frame_clause: Z});
 }
 window(A) ::= nm(W) ORDER BY sortlist(Y) frame_opt(Z). {
-  A = Window{ base: Some(W),  partition_by: None, order_by: Some(Y), frame_clause: Z};
+  A = Box::new(Window{ base: Some(W),  partition_by: None, order_by: Some(Y), frame_clause: Z});
 }
 window(A) ::= frame_opt(Z). {
-  A = Window{ base: None,  partition_by: None, order_by: None, frame_clause: Z};
+  A = Box::new(Window{ base: None,  partition_by: None, order_by: None, frame_clause: Z});
 }
 window(A) ::= nm(W) frame_opt(Z). {
-  A = Window{ base: Some(W),  partition_by: None, order_by: None, frame_clause: Z};
+  A = Box::new(Window{ base: Some(W),  partition_by: None, order_by: None, frame_clause: Z});
 }
 
 frame_opt(A) ::= .                             {
@@ -1465,20 +1551,20 @@ frame_exclude(A) ::= TIES.        { A = FrameExclude::Ties; }
 window_clause(A) ::= WINDOW windowdefn_list(B). { A = B; }
 
 filter_over(A) ::= filter_clause(F) over_clause(O). {
-  A = FunctionTail{ filter_clause: Some(Box::new(F)), over_clause: Some(Box::new(O)) };
+  A = FunctionTail{ filter_clause: Some(Box::new(F)), over_clause: Some(O) };
 }
 filter_over(A) ::= over_clause(O). {
-  A = FunctionTail{ filter_clause: None, over_clause: Some(Box::new(O)) };
+  A = FunctionTail{ filter_clause: None, over_clause: Some(O) };
 }
 filter_over(A) ::= filter_clause(F). {
   A = FunctionTail{ filter_clause: Some(Box::new(F)), over_clause: None };
 }
 
 over_clause(A) ::= OVER LP window(Z) RP. {
-  A = Over::Window(Z);
+  A = Box::new(Over::Window(*Z));
 }
 over_clause(A) ::= OVER nm(Z). {
-  A = Over::Name(Z);
+  A = Box::new(Over::Name(Z));
 }
 
 filter_clause(A) ::= FILTER LP WHERE expr(X) RP.  { A = X; }
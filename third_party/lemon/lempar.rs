@@ -187,10 +187,12 @@ pub struct yyParser {
     yyerrcnt: i32, /* Shifts left before out of the error */
 %%                               /* A place to hold %extra_context */
     yystack: SmallVec<[yyStackEntry; YYSTACKDEPTH]>, /* The parser's stack */
+    yylow: usize,                /* Lowest index of the stack reduced by the last input token */
+    yypopped: Vec<(YYACTIONTYPE, YYCODETYPE)>, /* Entries above yylow popped by these reductions */
 }
 
-use std::cmp::Ordering;
-use std::ops::Neg;
+use core::cmp::Ordering;
+use core::ops::Neg;
 impl yyParser {
     fn shift(&self, shift: i8) -> usize {
         assert!(shift <= 1);
@@ -210,7 +212,7 @@ impl yyParser {
     }
 
     fn yy_move(&mut self, shift: i8) -> yyStackEntry {
-        use std::mem::take;
+        use core::mem::take;
         let idx = self.shift(shift);
         take(&mut self.yystack[idx])
     }
@@ -224,7 +226,7 @@ impl yyParser {
     }
 }
 
-use std::ops::{Index, IndexMut};
+use core::ops::{Index, IndexMut};
 impl Index<i8> for yyParser {
     type Output = yyStackEntry;
 
@@ -325,6 +327,8 @@ impl yyParser {
             #[cfg(feature = "YYTRACKMAXSTACKDEPTH")]
             yyhwm: 0,
             yystack: SmallVec::new(),
+            yylow: 0,
+            yypopped: Vec::new(),
             //#[cfg(not(feature = "YYNOERRORRECOVERY"))]
             yyerrcnt: -1,
 %%               /* Optional %extra_context store */
@@ -339,7 +343,7 @@ impl yyParser {
 */
 impl yyParser {
     fn yy_pop_parser_stack(&mut self) {
-        use std::mem::take;
+        use core::mem::take;
         let yytos = take(&mut self.yystack[self.yyidx]);
         self.yyidx = self.yyidx.checked_sub(1).unwrap();
         //assert_eq!(self.yyidx+1, self.yystack.len());
@@ -761,6 +765,10 @@ impl yyParser {
         }
 
         let mut yyact: YYACTIONTYPE = self[0].stateno; /* The parser action. */
+        /* Default reductions may be performed before a syntax error is detected:
+         ** keep track of the popped states (see yy_states) */
+        self.yylow = self.yyidx;
+        self.yypopped.clear();
         #[cfg(not(feature = "NDEBUG"))]
         {
             if yyact < YY_MIN_REDUCE {
@@ -817,6 +825,12 @@ impl yyParser {
                         break;
                     }
                 }
+                let yysize = yyRuleInfoNRhs[yyruleno as usize].unsigned_abs() as usize;
+                while self.yylow + yysize > self.yyidx {
+                    let yytos = &self.yystack[self.yylow];
+                    self.yypopped.push((yytos.stateno, yytos.major));
+                    self.yylow -= 1;
+                }
                 yyact = self.yy_reduce(yyruleno, yymajor, yyminor.as_ref())?;
             } else if yyact <= YY_MAX_SHIFTREDUCE {
                 self.yy_shift(yyact, yymajor, yyminor.take());
@@ -953,4 +967,59 @@ impl yyParser {
         }
         0
     }
+
+    /*
+     ** Return the terminal symbols that would have been accepted (shifted,
+     ** possibly after some reductions) instead of the last input token.
+     */
+    pub fn expected_tokens(&self) -> Vec<YYCODETYPE> {
+        let stack = self.yy_states();
+        (1..YYNTOKEN as YYCODETYPE)
+            .filter(|&i_token| yy_would_shift(&stack, i_token))
+            .collect()
+    }
+
+    /*
+     ** States of the stack before the last input token: the reductions it
+     ** triggered are undone.
+     */
+    fn yy_states(&self) -> Vec<YYACTIONTYPE> {
+        self.yystack[..=self.yylow]
+            .iter()
+            .map(|entry| entry.stateno)
+            .chain(self.yypopped.iter().rev().map(|&(stateno, _)| stateno))
+            .collect()
+    }
+
+    /*
+     ** Major symbol on top of the stack before the last input token
+     */
+    fn yy_top_major(&self) -> YYCODETYPE {
+        match self.yypopped.first() {
+            Some(&(_, major)) => major,
+            None => self.yystack[self.yylow].major,
+        }
+    }
+}
+
+/*
+** Simulate the reductions triggered by the look-ahead token iLookAhead
+** from the states of stack (without executing any action) and check it is
+** not a syntax error.
+*/
+fn yy_would_shift(stack: &[YYACTIONTYPE], i_look_ahead: YYCODETYPE) -> bool {
+    let mut stack = stack.to_vec();
+    let mut yyact = stack[stack.len() - 1];
+    loop {
+        yyact = yy_find_shift_action(i_look_ahead, yyact);
+        if yyact >= YY_MIN_REDUCE {
+            let yyruleno = (yyact - YY_MIN_REDUCE) as usize;
+            let yysize = yyRuleInfoNRhs[yyruleno].unsigned_abs() as usize;
+            stack.truncate(stack.len() - yysize);
+            yyact = yy_find_reduce_action(stack[stack.len() - 1], yyRuleInfoLhs[yyruleno]);
+            stack.push(yyact);
+        } else {
+            return yyact != YY_ERROR_ACTION;
+        }
+    }
 }